@@ -10,6 +10,10 @@ pub static GLOBAL_CONFIG: OnceCell<AppConfig> = OnceCell::new();
 pub struct AppConfig {
     pub database: DatabaseConfig,
     pub redis: RedisConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub embeds: EmbedsConfig,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -28,6 +32,70 @@ pub struct RedisConfig {
     pub password: Option<String>,
 }
 
+/// Limits on what users can create. Every field is optional and falls back to its default.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Maximum number of messages that can be pinned in a single channel.
+    pub max_pins_per_channel: u32,
+    /// Maximum size of a single message attachment, in bytes.
+    pub max_attachment_size: u64,
+    /// Maximum number of attachments a single message can have.
+    pub max_attachments_per_message: u32,
+    /// MIME types attachments may have, such as `image/png` or `image/*`.
+    /// If empty, attachments of any type are allowed.
+    pub allowed_attachment_types: Vec<String>,
+    /// Minutes without new messages after which threads are archived, unless they say otherwise.
+    pub default_thread_auto_archive_minutes: u32,
+    /// Longest inactivity threads can ask for before they're archived, in minutes.
+    pub max_thread_auto_archive_minutes: u32,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_pins_per_channel: 50,
+            max_attachment_size: 8 * 1024 * 1024,
+            max_attachments_per_message: 10,
+            allowed_attachment_types: Vec::new(),
+            default_thread_auto_archive_minutes: 24 * 60,
+            max_thread_auto_archive_minutes: 7 * 24 * 60,
+        }
+    }
+}
+
+/// Settings for generating embeds from links in messages. Every field is optional and falls back to its default.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EmbedsConfig {
+    /// Whether links in new messages are fetched to generate embeds at all.
+    pub enabled: bool,
+    /// Domains links may be fetched from. Subdomains are included.
+    /// If empty, any domain that isn't denied is allowed.
+    pub allowed_domains: Vec<String>,
+    /// Domains links are never fetched from. Subdomains are included.
+    pub denied_domains: Vec<String>,
+    /// Maximum number of bytes read from a single response. Anything past this is ignored.
+    pub max_response_size: usize,
+    /// Maximum number of links a single message gets embeds for.
+    pub max_embeds_per_message: u32,
+    /// Seconds to wait for a single link to be fetched before giving up on it.
+    pub fetch_timeout_secs: u64,
+}
+
+impl Default for EmbedsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            allowed_domains: Vec::new(),
+            denied_domains: Vec::new(),
+            max_response_size: 1024 * 1024,
+            max_embeds_per_message: 5,
+            fetch_timeout_secs: 10,
+        }
+    }
+}
+
 impl Display for RedisConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str("redis://")?;
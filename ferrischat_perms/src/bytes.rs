@@ -0,0 +1,19 @@
+use ferrischat_common::perms::GuildPermissions;
+
+/// Decode the raw contents of a `permissions` column into a permission set.
+///
+/// Unknown bits are dropped. Anything that isn't an 8 byte big-endian integer,
+/// including the empty default, is treated as no permissions at all.
+#[must_use]
+pub fn permissions_from_bytes(bytes: &[u8]) -> GuildPermissions {
+    bytes.try_into().map_or_else(
+        |_| GuildPermissions::empty(),
+        |b| GuildPermissions::from_bits_truncate(u64::from_be_bytes(b)),
+    )
+}
+
+/// Encode a permission set for storage in a `permissions` column.
+#[must_use]
+pub fn permissions_to_bytes(perms: GuildPermissions) -> [u8; 8] {
+    perms.bits().to_be_bytes()
+}
@@ -0,0 +1,16 @@
+#![allow(clippy::module_name_repetitions)]
+
+mod bytes;
+mod overwrites;
+mod rank;
+mod resolve;
+mod roles;
+
+pub use bytes::*;
+pub use overwrites::*;
+pub use rank::*;
+pub use resolve::*;
+pub use roles::*;
+
+#[macro_use]
+extern crate ferrischat_macros;
@@ -0,0 +1,37 @@
+use crate::permissions_from_bytes;
+use ferrischat_common::types::{OverwriteType, PermissionOverwrite};
+use num_traits::ToPrimitive;
+use sqlx::{Executor, Postgres};
+
+/// Fetch every permission overwrite set on `channel_id`.
+///
+/// This can be run inside a transaction, to see overwrites it changed before it's committed.
+///
+/// # Errors
+/// Returns any error the database returns.
+pub async fn get_permission_overwrites<'e, E: Executor<'e, Database = Postgres>>(
+    db: E,
+    channel_id: u128,
+) -> Result<Vec<PermissionOverwrite>, sqlx::Error> {
+    Ok(sqlx::query!(
+        "SELECT * FROM permission_overwrites WHERE channel_id = $1",
+        u128_to_bigdecimal!(channel_id)
+    )
+    .fetch_all(db)
+    .await?
+    .iter()
+    .filter_map(|x| {
+        Some(PermissionOverwrite {
+            target_id: x
+                .target_id
+                .with_scale(0)
+                .into_bigint_and_exponent()
+                .0
+                .to_u128()?,
+            target_type: OverwriteType::from_i16(x.target_type)?,
+            allow: permissions_from_bytes(&x.allow),
+            deny: permissions_from_bytes(&x.deny),
+        })
+    })
+    .collect())
+}
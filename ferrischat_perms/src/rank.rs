@@ -0,0 +1,61 @@
+use sqlx::{Pool, Postgres};
+
+/// Where a member sits in a guild's role hierarchy.
+///
+/// Ranks are ordered so that a member can only manage roles and members ranked strictly below them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rank {
+    /// A regular member, along with the position of their highest role if they have any.
+    Member(Option<i16>),
+    /// The guild owner, who outranks everyone.
+    Owner,
+}
+
+impl Rank {
+    /// The rank a role at `position` gives its holders.
+    #[must_use]
+    pub const fn of_role(position: i16) -> Self {
+        Self::Member(Some(position))
+    }
+}
+
+/// Compute the rank of `user_id` in `guild_id`.
+///
+/// Returns `None` if the guild does not exist or the user is not a member of it.
+///
+/// # Errors
+/// Returns any error the database returns.
+pub async fn get_rank(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+    user_id: u128,
+) -> Result<Option<Rank>, sqlx::Error> {
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+    let bigdecimal_user_id = u128_to_bigdecimal!(user_id);
+
+    let owner_id = match sqlx::query!(
+        "SELECT g.owner_id FROM guilds g INNER JOIN members m ON m.guild_id = g.id WHERE g.id = $1 AND m.user_id = $2",
+        bigdecimal_guild_id,
+        bigdecimal_user_id
+    )
+    .fetch_optional(db)
+    .await?
+    {
+        Some(r) => r.owner_id,
+        None => return Ok(None),
+    };
+    if owner_id == bigdecimal_user_id {
+        return Ok(Some(Rank::Owner));
+    }
+
+    let position = sqlx::query!(
+        "SELECT MAX(r.position) AS position FROM roles r INNER JOIN role_data d ON d.role_id = r.id WHERE d.guild_id = $1 AND d.user_id = $2",
+        bigdecimal_guild_id,
+        bigdecimal_user_id
+    )
+    .fetch_one(db)
+    .await?
+    .position;
+
+    Ok(Some(Rank::Member(position)))
+}
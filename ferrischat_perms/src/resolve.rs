@@ -0,0 +1,265 @@
+use crate::{permissions_from_bytes, role_permissions};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{ChannelType, OverwriteType};
+use num_traits::ToPrimitive;
+use sqlx::types::BigDecimal;
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+
+/// Permissions given to the everyone role of a new guild.
+pub const DEFAULT_PERMISSIONS: GuildPermissions = GuildPermissions::from_bits_truncate(
+    GuildPermissions::READ_MESSAGES.bits()
+        | GuildPermissions::SEND_MESSAGES.bits()
+        | GuildPermissions::CREATE_INVITES.bits(),
+);
+
+/// Compute the effective permissions of `user_id` in `guild_id`.
+///
+/// The guild owner, and anyone granted `ADMINISTRATOR` by one of their roles, has every permission.
+/// Everyone else gets the permissions of the guild's everyone role
+/// plus the union of the permissions of all their other roles.
+///
+/// Returns `None` if the guild does not exist or the user is not a member of it.
+///
+/// # Errors
+/// Returns any error the database returns.
+pub async fn get_guild_permissions(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+    user_id: u128,
+) -> Result<Option<GuildPermissions>, sqlx::Error> {
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+    let bigdecimal_user_id = u128_to_bigdecimal!(user_id);
+
+    let owner_id = match sqlx::query!(
+        "SELECT g.owner_id FROM guilds g INNER JOIN members m ON m.guild_id = g.id WHERE g.id = $1 AND m.user_id = $2",
+        bigdecimal_guild_id,
+        bigdecimal_user_id
+    )
+    .fetch_optional(db)
+    .await?
+    {
+        Some(r) => r.owner_id,
+        None => return Ok(None),
+    };
+    if owner_id == bigdecimal_user_id {
+        return Ok(Some(GuildPermissions::all()));
+    }
+
+    let base = sqlx::query!(
+        "SELECT permissions FROM roles WHERE id = $1 AND parent_guild = $1",
+        bigdecimal_guild_id
+    )
+    .fetch_optional(db)
+    .await?
+    .map_or_else(GuildPermissions::empty, |r| {
+        role_permissions(guild_id, guild_id, &r.permissions)
+    });
+
+    let perms = sqlx::query!(
+        "SELECT r.permissions FROM roles r INNER JOIN role_data d ON d.role_id = r.id WHERE d.guild_id = $1 AND d.user_id = $2",
+        bigdecimal_guild_id,
+        bigdecimal_user_id
+    )
+    .fetch_all(db)
+    .await?
+    .iter()
+    .fold(base, |perms, r| perms | permissions_from_bytes(&r.permissions));
+
+    if perms.contains(GuildPermissions::ADMINISTRATOR) {
+        Ok(Some(GuildPermissions::all()))
+    } else {
+        Ok(Some(perms))
+    }
+}
+
+/// Compute the effective permissions of `user_id` in the channel `channel_id` of `guild_id`.
+///
+/// This starts from their guild permissions (see [`get_guild_permissions`]) and applies the channel's
+/// permission overwrites on top: first the overwrite for the everyone role is applied,
+/// then everything denied by any of their other roles is removed
+/// and everything allowed by any of their roles is added,
+/// then the same is done with the overwrite for the member themselves, if any.
+/// Members with `ADMINISTRATOR` are not affected by overwrites.
+/// Threads have no overwrites of their own, and use their parent channel's instead.
+///
+/// Returns `None` if the guild does not exist or the user is not a member of it.
+///
+/// # Errors
+/// Returns any error the database returns.
+pub async fn get_channel_permissions(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+    channel_id: u128,
+    user_id: u128,
+) -> Result<Option<GuildPermissions>, sqlx::Error> {
+    let perms = match get_guild_permissions(db, guild_id, user_id).await? {
+        Some(perms) if !perms.contains(GuildPermissions::ADMINISTRATOR) => perms,
+        other => return Ok(other),
+    };
+
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+    let bigdecimal_user_id = u128_to_bigdecimal!(user_id);
+
+    let overwrites = sqlx::query!(
+        r#"
+SELECT target_id, target_type, allow, deny
+FROM permission_overwrites
+WHERE channel_id = COALESCE((SELECT parent_id FROM channels WHERE id = $1 AND channel_type = $4), $1)
+  AND (target_id = $2
+    OR target_id = $3
+    OR target_id IN (SELECT role_id FROM role_data WHERE guild_id = $3 AND user_id = $2))
+"#,
+        bigdecimal_channel_id,
+        bigdecimal_user_id,
+        bigdecimal_guild_id,
+        ChannelType::Thread as i16
+    )
+    .fetch_all(db)
+    .await?;
+
+    let overwrites: Vec<_> = overwrites
+        .iter()
+        .map(|o| StoredOverwrite {
+            target_id: &o.target_id,
+            target_type: o.target_type,
+            allow: &o.allow,
+            deny: &o.deny,
+        })
+        .collect();
+    Ok(Some(apply_overwrites(
+        perms,
+        &bigdecimal_guild_id,
+        &overwrites,
+    )))
+}
+
+/// Compute the effective permissions of `user_id` in every channel of `guild_id` at once,
+/// the same way [`get_channel_permissions`] does for a single channel.
+///
+/// Returns `None` if the guild does not exist or the user is not a member of it.
+///
+/// # Errors
+/// Returns any error the database returns.
+pub async fn get_all_channel_permissions(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+    user_id: u128,
+) -> Result<Option<HashMap<u128, GuildPermissions>>, sqlx::Error> {
+    let perms = match get_guild_permissions(db, guild_id, user_id).await? {
+        Some(perms) => perms,
+        None => return Ok(None),
+    };
+
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+    let bigdecimal_user_id = u128_to_bigdecimal!(user_id);
+
+    let channel_ids = sqlx::query!(
+        "SELECT id FROM channels WHERE guild_id = $1",
+        bigdecimal_guild_id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .filter_map(|c| c.id.with_scale(0).into_bigint_and_exponent().0.to_u128());
+    if perms.contains(GuildPermissions::ADMINISTRATOR) {
+        return Ok(Some(channel_ids.map(|id| (id, perms)).collect()));
+    }
+
+    // threads use their parent channel's overwrites, like in `get_channel_permissions`
+    let overwrites = sqlx::query!(
+        r#"
+SELECT c.id AS channel_id, o.target_id, o.target_type, o.allow, o.deny
+FROM channels c
+    INNER JOIN permission_overwrites o
+        ON o.channel_id = COALESCE(CASE WHEN c.channel_type = $3 THEN c.parent_id END, c.id)
+WHERE c.guild_id = $1
+  AND (o.target_id = $2
+    OR o.target_id = $1
+    OR o.target_id IN (SELECT role_id FROM role_data WHERE guild_id = $1 AND user_id = $2))
+"#,
+        bigdecimal_guild_id,
+        bigdecimal_user_id,
+        ChannelType::Thread as i16
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut by_channel: HashMap<u128, Vec<StoredOverwrite>> = HashMap::new();
+    for o in &overwrites {
+        if let Some(channel_id) = o
+            .channel_id
+            .with_scale(0)
+            .into_bigint_and_exponent()
+            .0
+            .to_u128()
+        {
+            by_channel
+                .entry(channel_id)
+                .or_default()
+                .push(StoredOverwrite {
+                    target_id: &o.target_id,
+                    target_type: o.target_type,
+                    allow: &o.allow,
+                    deny: &o.deny,
+                });
+        }
+    }
+
+    Ok(Some(
+        channel_ids
+            .map(|id| {
+                let overwrites = by_channel.get(&id).map_or(&[][..], Vec::as_slice);
+                (
+                    id,
+                    apply_overwrites(perms, &bigdecimal_guild_id, overwrites),
+                )
+            })
+            .collect(),
+    ))
+}
+
+/// A permission overwrite as it's stored, that applies to the member whose permissions are resolved.
+struct StoredOverwrite<'a> {
+    target_id: &'a BigDecimal,
+    target_type: i16,
+    allow: &'a [u8],
+    deny: &'a [u8],
+}
+
+/// Apply the overwrites on a channel that apply to a member to their guild permissions `perms`,
+/// in the order described on [`get_channel_permissions`].
+fn apply_overwrites(
+    mut perms: GuildPermissions,
+    bigdecimal_guild_id: &BigDecimal,
+    overwrites: &[StoredOverwrite],
+) -> GuildPermissions {
+    let (member, roles): (Vec<_>, Vec<_>) = overwrites
+        .iter()
+        .partition(|o| o.target_type == OverwriteType::Member as i16);
+    let (everyone, roles): (Vec<_>, Vec<_>) = roles
+        .into_iter()
+        .partition(|o| o.target_id == bigdecimal_guild_id);
+
+    if let Some(o) = everyone.first() {
+        perms = (perms - permissions_from_bytes(o.deny)) | permissions_from_bytes(o.allow);
+    }
+
+    let (allow, deny) = roles.iter().fold(
+        (GuildPermissions::empty(), GuildPermissions::empty()),
+        |(allow, deny), o| {
+            (
+                allow | permissions_from_bytes(o.allow),
+                deny | permissions_from_bytes(o.deny),
+            )
+        },
+    );
+    perms = (perms - deny) | allow;
+
+    if let Some(o) = member.first() {
+        perms = (perms - permissions_from_bytes(o.deny)) | permissions_from_bytes(o.allow);
+    }
+
+    perms
+}
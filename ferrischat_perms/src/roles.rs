@@ -0,0 +1,59 @@
+use crate::{permissions_from_bytes, DEFAULT_PERMISSIONS};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::Role;
+use num_traits::ToPrimitive;
+use sqlx::{Pool, Postgres};
+
+/// Name given to the everyone role of a new guild.
+pub const EVERYONE_ROLE_NAME: &str = "@everyone";
+
+/// Whether `role_id` is the everyone role of `guild_id`.
+///
+/// Every guild has exactly one everyone role, sharing its ID with the guild itself.
+/// It is held implicitly by every member and cannot be deleted.
+#[must_use]
+pub const fn is_everyone_role(guild_id: u128, role_id: u128) -> bool {
+    guild_id == role_id
+}
+
+/// Decode the permissions stored on a role.
+///
+/// Everyone roles backfilled for guilds created before they existed store no permissions at all,
+/// and get [`DEFAULT_PERMISSIONS`] until they are edited.
+#[must_use]
+pub fn role_permissions(guild_id: u128, role_id: u128, bytes: &[u8]) -> GuildPermissions {
+    if bytes.is_empty() && is_everyone_role(guild_id, role_id) {
+        DEFAULT_PERMISSIONS
+    } else {
+        permissions_from_bytes(bytes)
+    }
+}
+
+/// Fetch every role in `guild_id`, including its everyone role.
+///
+/// # Errors
+/// Returns any error the database returns.
+pub async fn get_guild_roles(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+) -> Result<Vec<Role>, sqlx::Error> {
+    Ok(sqlx::query!(
+        "SELECT * FROM roles WHERE parent_guild = $1 ORDER BY position",
+        u128_to_bigdecimal!(guild_id)
+    )
+    .fetch_all(db)
+    .await?
+    .iter()
+    .filter_map(|x| {
+        let id = x.id.with_scale(0).into_bigint_and_exponent().0.to_u128()?;
+        Some(Role {
+            id,
+            name: x.name.clone(),
+            color: x.color,
+            position: x.position,
+            guild_id,
+            guild_permissions: role_permissions(guild_id, id, &x.permissions),
+        })
+    })
+    .collect())
+}
@@ -0,0 +1,104 @@
+use crate::WebServerError;
+use axum::body::Body;
+use axum::extract::{FromRequest, RequestParts};
+use ferrischat_common::types::{AuditLogAction, ErrorJson, ModelType};
+use ferrischat_snowflake_generator::generate_snowflake;
+use serde::Serialize;
+use sqlx::{Postgres, Transaction};
+
+/// Name of the header clients can set to explain why they took an action.
+pub const AUDIT_LOG_REASON_HEADER: &str = "X-Audit-Log-Reason";
+
+/// Maximum length of an audit log reason, in bytes.
+pub const MAX_AUDIT_LOG_REASON_LENGTH: usize = 512;
+
+/// Request parameter for the optional `X-Audit-Log-Reason` header.
+///
+/// The header value is URL-decoded, so clients can pass reasons that aren't valid header values.
+///
+/// Field 0: the reason, if one was passed
+pub struct AuditLogReason(pub Option<String>);
+
+#[async_trait::async_trait]
+impl FromRequest<Body> for AuditLogReason {
+    type Rejection = WebServerError;
+
+    async fn from_request(req: &mut RequestParts<Body>) -> Result<Self, Self::Rejection> {
+        let header = match req.headers().and_then(|h| h.get(AUDIT_LOG_REASON_HEADER)) {
+            Some(h) => h,
+            None => return Ok(Self(None)),
+        };
+
+        let raw = std::str::from_utf8(header.as_bytes()).map_err(|e| {
+            ErrorJson::new_400(format!(
+                "{} header contained invalid UTF-8: {}",
+                AUDIT_LOG_REASON_HEADER, e
+            ))
+        })?;
+        let reason = urlencoding::decode(raw).map_err(|e| {
+            ErrorJson::new_400(format!(
+                "{} header contained invalid URL-encoded data: {}",
+                AUDIT_LOG_REASON_HEADER, e
+            ))
+        })?;
+
+        if reason.len() > MAX_AUDIT_LOG_REASON_LENGTH {
+            return Err(ErrorJson::new_400(format!(
+                "audit log reasons must be at most {} bytes long",
+                MAX_AUDIT_LOG_REASON_LENGTH
+            ))
+            .into());
+        }
+
+        Ok(Self(Some(reason.into_owned())))
+    }
+}
+
+/// Record an action `actor_id` took in `guild_id` in the guild's audit log.
+///
+/// `before` and `after` are the state of the target before and after the action, where they apply.
+/// The entry is written as part of `tx`, the transaction the action itself is carried out in,
+/// so that no action is ever left out of the log.
+///
+/// # Errors
+/// Returns a HTTP 500 if the database returns an error or the target could not be serialized.
+pub async fn add_audit_log_entry<T: Serialize>(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: u128,
+    actor_id: u128,
+    action: AuditLogAction,
+    target_id: Option<u128>,
+    before: Option<&T>,
+    after: Option<&T>,
+    reason: Option<String>,
+) -> Result<(), WebServerError> {
+    let to_json = |obj: Option<&T>| {
+        obj.map(serde_json::to_value).transpose().map_err(|e| {
+            ErrorJson::new_500(
+                format!("failed to serialize audit log entry: {}", e),
+                false,
+                None,
+            )
+        })
+    };
+    let before = to_json(before)?;
+    let after = to_json(after)?;
+
+    let entry_id = generate_snowflake::<0>(ModelType::AuditLogEntry as u8, get_node_id!());
+
+    sqlx::query!(
+        "INSERT INTO audit_log VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        u128_to_bigdecimal!(entry_id),
+        u128_to_bigdecimal!(guild_id),
+        u128_to_bigdecimal!(actor_id),
+        action as i16,
+        target_id.map(|id| u128_to_bigdecimal!(id)),
+        before,
+        after,
+        reason
+    )
+    .execute(tx)
+    .await?;
+
+    Ok(())
+}
@@ -2,26 +2,54 @@ use crate::ws::fire_event;
 use crate::{Json, WebServerError};
 use axum::extract::Path;
 use axum::Json as JsonInput;
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::ChannelCreateJson;
-use ferrischat_common::types::{Channel, ErrorJson, ModelType};
+use ferrischat_common::types::{AuditLogAction, Channel, ChannelType, ErrorJson, ModelType};
 use ferrischat_common::ws::WsOutboundEvent;
 use ferrischat_macros::get_db_or_fail;
 use ferrischat_snowflake_generator::generate_snowflake;
 
 /// POST `/v0/guilds/{guild_id/channels`
 pub async fn create_channel(
-    _: crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
     channel_info: JsonInput<ChannelCreateJson>,
     Path(guild_id): Path<u128>,
 ) -> Result<Json<Channel>, WebServerError> {
     let db = get_db_or_fail!();
 
-    let ChannelCreateJson { name } = channel_info.0;
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::MANAGE_CHANNELS)
+        .await?;
+
+    let ChannelCreateJson {
+        name,
+        channel_type,
+        parent_id,
+        topic,
+        nsfw,
+    } = channel_info.0;
     if name.contains(char::is_whitespace) {
         return Err(
             ErrorJson::new_400("A channel name may not contain a whitespace!".to_string()).into(),
         );
     }
+    let channel_type = channel_type.unwrap_or_default();
+    if matches!(
+        channel_type,
+        ChannelType::Thread | ChannelType::DirectMessage | ChannelType::GroupDirectMessage
+    ) {
+        return Err(ErrorJson::new_400(format!(
+            "channels of type {:?} can't be created here",
+            channel_type
+        ))
+        .into());
+    }
+    let topic = topic.filter(|t| !t.is_empty());
+    if let Some(ref topic) = topic {
+        super::check_topic(topic)?;
+    }
+    super::check_parent(db, guild_id, channel_type, parent_id).await?;
+    let nsfw = nsfw.unwrap_or(false);
 
     let node_id = get_node_id!();
     let channel_id = generate_snowflake::<0>(ModelType::Channel as u8, node_id);
@@ -29,21 +57,52 @@ pub async fn create_channel(
 
     let bigint_guild_id = u128_to_bigdecimal!(guild_id);
 
-    sqlx::query!(
-        "INSERT INTO channels VALUES ($1, $2, $3)",
+    // new channels go after every existing one
+    let mut tx = db.begin().await?;
+    let position = sqlx::query!(
+        "INSERT INTO channels (id, name, guild_id, channel_type, parent_id, position, topic, nsfw) \
+        SELECT $1, $2, $3, $4, $5, COALESCE(MAX(position) + 1, 0), $6, $7 FROM channels WHERE guild_id = $3 \
+        RETURNING position",
         bigint_channel_id,
         name,
-        bigint_guild_id
+        bigint_guild_id,
+        channel_type as i16,
+        parent_id.map(|p| u128_to_bigdecimal!(p)),
+        topic,
+        nsfw
     )
-    .execute(db)
-    .await?;
+    .fetch_one(&mut tx)
+    .await?
+    .position;
 
     let channel_obj = Channel {
         id: channel_id,
         name,
-        guild_id,
+        guild_id: Some(guild_id),
+        channel_type,
+        parent_id,
+        position,
+        topic,
+        nsfw,
+        owner_id: None,
+        recipients: None,
+        thread_metadata: None,
+        permission_overwrites: None,
     };
 
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::ChannelCreate,
+        Some(channel_id),
+        None,
+        Some(&channel_obj),
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
     let event = WsOutboundEvent::ChannelCreate {
         channel: channel_obj.clone(),
     };
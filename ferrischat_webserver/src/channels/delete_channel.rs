@@ -1,33 +1,146 @@
 use crate::ws::fire_event;
 use crate::WebServerError;
 use axum::extract::Path;
-use ferrischat_common::types::{Channel, ErrorJson};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{AuditLogAction, Channel, ChannelType, ErrorJson};
 use ferrischat_common::ws::WsOutboundEvent;
 
 /// DELETE `/v0/channels/{channel_id}`
 pub async fn delete_channel(
     Path(channel_id): Path<u128>,
-    _: crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
 ) -> Result<http::StatusCode, WebServerError> {
     let db = get_db_or_fail!();
+
+    let (guild_id, _) = crate::require_channel_permissions(
+        channel_id,
+        auth_user,
+        GuildPermissions::MANAGE_CHANNELS,
+    )
+    .await?;
+
     let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
 
+    let mut tx = db.begin().await?;
+
+    // threads go along with the channel they were started in
+    let deleted_threads = sqlx::query!(
+        r#"DELETE FROM channels WHERE parent_id = $1 AND channel_type = $2 RETURNING *, ARRAY(SELECT a.id FROM attachments a INNER JOIN messages m ON m.id = a.message_id WHERE m.channel_id = channels.id) AS "attachment_ids!""#,
+        bigdecimal_channel_id,
+        ChannelType::Thread as i16
+    )
+    .fetch_all(&mut tx)
+    .await?;
+    let mut threads = Vec::with_capacity(deleted_threads.len());
+    // files are only removed from storage once the deletion is committed
+    let mut attachment_ids = Vec::new();
+    for thread in deleted_threads {
+        attachment_ids.extend(thread.attachment_ids);
+        threads.push(Channel {
+            id: bigdecimal_to_u128!(thread.id),
+            guild_id: Some(guild_id),
+            name: thread.name,
+            channel_type: ChannelType::Thread,
+            parent_id: Some(channel_id),
+            position: thread.position,
+            topic: thread.topic,
+            nsfw: thread.nsfw,
+            owner_id: match thread.owner_id {
+                Some(o) => Some(bigdecimal_to_u128!(o)),
+                None => None,
+            },
+            recipients: None,
+            thread_metadata: super::threads::thread_metadata(
+                ChannelType::Thread,
+                thread.archived,
+                thread.auto_archive_minutes,
+                thread.starter_message_id,
+            ),
+            permission_overwrites: None,
+        });
+    }
+
+    // deleting a category moves the channels in it out, which clients need to hear about
+    let children = sqlx::query!(
+        "SELECT id FROM channels WHERE parent_id = $1",
+        bigdecimal_channel_id
+    )
+    .fetch_all(&mut tx)
+    .await?;
+
     let channel = sqlx::query!(
-        "DELETE FROM channels WHERE id = $1 RETURNING *",
+        r#"DELETE FROM channels WHERE id = $1 RETURNING *, ARRAY(SELECT a.id FROM attachments a INNER JOIN messages m ON m.id = a.message_id WHERE m.channel_id = channels.id) AS "attachment_ids!""#,
         bigdecimal_channel_id,
     )
-    .fetch_optional(db)
+    .fetch_optional(&mut tx)
     .await?
     .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+    attachment_ids.extend(channel.attachment_ids);
+    let channel_type = ChannelType::from_i16(channel.channel_type).unwrap_or_default();
     let channel = Channel {
         id: bigdecimal_to_u128!(channel.id),
-        guild_id: bigdecimal_to_u128!(channel.guild_id),
+        guild_id: Some(guild_id),
         name: channel.name,
+        channel_type,
+        parent_id: match channel.parent_id {
+            Some(p) => Some(bigdecimal_to_u128!(p)),
+            None => None,
+        },
+        position: channel.position,
+        topic: channel.topic,
+        nsfw: channel.nsfw,
+        owner_id: match channel.owner_id {
+            Some(o) => Some(bigdecimal_to_u128!(o)),
+            None => None,
+        },
+        recipients: None,
+        thread_metadata: super::threads::thread_metadata(
+            channel_type,
+            channel.archived,
+            channel.auto_archive_minutes,
+            channel.starter_message_id,
+        ),
+        permission_overwrites: None,
     };
 
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::ChannelDelete,
+        Some(channel_id),
+        Some(&channel),
+        None,
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+    crate::messages::attachments::delete_stored_attachments(attachment_ids).await;
+
     let event = WsOutboundEvent::ChannelDelete { channel };
 
     fire_event(&event).await?;
 
+    for channel in threads {
+        let event = WsOutboundEvent::ChannelDelete { channel };
+        fire_event(&event).await?;
+    }
+
+    for child in children {
+        let new = match super::fetch_channel(db, bigdecimal_to_u128!(child.id)).await? {
+            Some(c) => c,
+            None => continue,
+        };
+        let event = WsOutboundEvent::ChannelUpdate {
+            old: Channel {
+                parent_id: Some(channel_id),
+                ..new.clone()
+            },
+            new,
+        };
+        fire_event(&event).await?;
+    }
+
     Ok(http::StatusCode::NO_CONTENT)
 }
@@ -1,32 +1,61 @@
 use crate::ws::fire_event;
 use crate::{Json, WebServerError};
 use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::ChannelUpdateJson;
-use ferrischat_common::types::{Channel, ErrorJson};
+use ferrischat_common::types::{AuditLogAction, Channel, ChannelType, ErrorJson};
 use ferrischat_common::ws::WsOutboundEvent;
 
 /// PATCH `/v0/channels/{channel_id}`
+///
+/// A channel's type can't be changed, and its position is changed with [`super::reorder_channels`].
+///
+/// Threads can also be edited by whoever started them, and are archived and unarchived here.
+/// They can't be moved to another channel.
 pub async fn edit_channel(
     Path(channel_id): Path<u128>,
     channel_info: axum::extract::Json<ChannelUpdateJson>,
-    _: crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
 ) -> Result<Json<Channel>, WebServerError> {
     let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
     let db = get_db_or_fail!();
-    let ChannelUpdateJson { name } = channel_info.0;
 
-    let c = sqlx::query!(
-        "SELECT * FROM channels WHERE id = $1",
-        bigdecimal_channel_id
-    )
-    .fetch_optional(db)
-    .await?
-    .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
-    let old = Channel {
-        id: channel_id,
-        name: c.name,
-        guild_id: bigdecimal_to_u128!(c.guild_id),
-    };
+    let (guild_id, perms) =
+        crate::require_channel_permissions(channel_id, auth_user, GuildPermissions::empty())
+            .await?;
+
+    let ChannelUpdateJson {
+        name,
+        parent_id,
+        topic,
+        nsfw,
+        archived,
+        auto_archive_minutes,
+    } = channel_info.0;
+
+    let old = super::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+    let is_thread = old.channel_type == ChannelType::Thread;
+
+    if !perms.contains(GuildPermissions::MANAGE_CHANNELS)
+        && !(is_thread && old.owner_id == Some(auth_user))
+    {
+        return Err(ErrorJson::new_403(format!(
+            "missing permissions: {:?}",
+            GuildPermissions::MANAGE_CHANNELS
+        ))
+        .into());
+    }
+    if is_thread && parent_id.is_some() {
+        return Err(ErrorJson::new_400("threads can't be moved".to_string()).into());
+    }
+    if !is_thread && (archived.is_some() || auto_archive_minutes.is_some()) {
+        return Err(ErrorJson::new_400("only threads can be archived".to_string()).into());
+    }
+
+    let mut tx = db.begin().await?;
 
     if let Some(name) = name {
         if name.contains(char::is_whitespace) {
@@ -40,26 +69,93 @@ pub async fn edit_channel(
             name,
             bigdecimal_channel_id
         )
-        .execute(db)
+        .execute(&mut tx)
+        .await?;
+    }
+
+    // `null` moves the channel out of its category
+    if let Some(parent_id) = parent_id {
+        super::check_parent(db, guild_id, old.channel_type, parent_id).await?;
+        sqlx::query!(
+            "UPDATE channels SET parent_id = $1 WHERE id = $2",
+            parent_id.map(|p| u128_to_bigdecimal!(p)),
+            bigdecimal_channel_id
+        )
+        .execute(&mut tx)
         .await?;
     }
 
-    let channel = sqlx::query!(
-        "SELECT * FROM channels WHERE id = $1",
-        bigdecimal_channel_id
+    // an empty topic clears it
+    if let Some(topic) = topic {
+        super::check_topic(&topic)?;
+        sqlx::query!(
+            "UPDATE channels SET topic = $1 WHERE id = $2",
+            Some(topic).filter(|t| !t.is_empty()),
+            bigdecimal_channel_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    if let Some(nsfw) = nsfw {
+        sqlx::query!(
+            "UPDATE channels SET nsfw = $1 WHERE id = $2",
+            nsfw,
+            bigdecimal_channel_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    if let Some(auto_archive_minutes) = auto_archive_minutes {
+        let auto_archive_minutes = super::threads::check_auto_archive(Some(auto_archive_minutes))?;
+        sqlx::query!(
+            "UPDATE channels SET auto_archive_minutes = $1 WHERE id = $2",
+            auto_archive_minutes,
+            bigdecimal_channel_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    // unarchived threads get a full inactivity period before they're archived again
+    if let Some(archived) = archived {
+        sqlx::query!(
+            "UPDATE channels SET archived = $1, archived_at = CASE WHEN $1 THEN now() END, last_activity_at = now() WHERE id = $2",
+            archived,
+            bigdecimal_channel_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    let new = super::fetch_channel(&mut tx, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::ChannelUpdate,
+        Some(channel_id),
+        Some(&old),
+        Some(&new),
+        reason,
     )
-    .fetch_optional(db)
-    .await?
-    .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
-    let new = Channel {
-        id: channel_id,
-        name: channel.name,
-        guild_id: bigdecimal_to_u128!(channel.guild_id),
-    };
+    .await?;
+    tx.commit().await?;
 
-    let event = WsOutboundEvent::ChannelUpdate {
-        old,
-        new: new.clone(),
+    let event = if is_thread {
+        WsOutboundEvent::ThreadUpdate {
+            old,
+            new: new.clone(),
+        }
+    } else {
+        WsOutboundEvent::ChannelUpdate {
+            old,
+            new: new.clone(),
+        }
     };
 
     fire_event(&event).await?;
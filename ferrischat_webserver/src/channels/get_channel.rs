@@ -1,25 +1,28 @@
 use crate::WebServerError;
 use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::types::{Channel, ErrorJson};
 
 /// GET `/v0/guilds/{guild_id/channels/{channel_id}`
 pub async fn get_channel(
     Path(channel_id): Path<u128>,
-    _: crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
 ) -> Result<crate::Json<Channel>, WebServerError> {
-    let c = sqlx::query!(
-        "SELECT * FROM channels WHERE id = $1",
-        u128_to_bigdecimal!(channel_id)
-    )
-    .fetch_optional(get_db_or_fail!())
-    .await?
-    .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+    crate::require_channel_access(channel_id, auth_user, GuildPermissions::READ_MESSAGES).await?;
+
+    let db = get_db_or_fail!();
+
+    let mut channel = super::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+    if channel.guild_id.is_some() {
+        channel.permission_overwrites =
+            Some(ferrischat_perms::get_permission_overwrites(db, channel_id).await?);
+    } else {
+        channel.recipients = Some(super::private::get_recipients(db, channel_id).await?);
+    }
     Ok(crate::Json {
-        obj: Channel {
-            id: channel_id,
-            name: c.name,
-            guild_id: bigdecimal_to_u128!(c.guild_id),
-        },
+        obj: channel,
         code: 200,
     })
 }
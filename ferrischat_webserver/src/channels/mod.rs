@@ -2,26 +2,132 @@ mod create_channel;
 mod delete_channel;
 mod edit_channel;
 mod get_channel;
-mod typing_end;
-mod typing_start;
+pub mod permissions;
+pub mod pins;
+pub mod private;
+mod reorder_channels;
+pub mod threads;
+pub mod typing;
 
 pub use create_channel::*;
 pub use delete_channel::*;
 pub use edit_channel::*;
 pub use get_channel::*;
-pub use typing_end::*;
-pub use typing_start::*;
+pub use reorder_channels::*;
 
+use crate::WebServerError;
 use axum::routing::{get, post};
 use axum::Router;
+use ferrischat_common::types::{Channel, ChannelType, ErrorJson};
+use sqlx::{Executor, Pool, Postgres};
+
+/// Maximum length of a channel's topic, in bytes.
+pub const MAX_TOPIC_LENGTH: usize = 1024;
+
+/// Fetch `channel_id` without its permission overwrites or recipients, or `None` if it doesn't exist.
+pub async fn fetch_channel<'e, E: Executor<'e, Database = Postgres>>(
+    db: E,
+    channel_id: u128,
+) -> Result<Option<Channel>, WebServerError> {
+    let c = match sqlx::query!(
+        "SELECT * FROM channels WHERE id = $1",
+        u128_to_bigdecimal!(channel_id)
+    )
+    .fetch_optional(db)
+    .await?
+    {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+
+    let channel_type = ChannelType::from_i16(c.channel_type).unwrap_or_default();
+    Ok(Some(Channel {
+        id: channel_id,
+        name: c.name,
+        guild_id: match c.guild_id {
+            Some(g) => Some(bigdecimal_to_u128!(g)),
+            None => None,
+        },
+        channel_type,
+        parent_id: match c.parent_id {
+            Some(p) => Some(bigdecimal_to_u128!(p)),
+            None => None,
+        },
+        position: c.position,
+        topic: c.topic,
+        nsfw: c.nsfw,
+        owner_id: match c.owner_id {
+            Some(o) => Some(bigdecimal_to_u128!(o)),
+            None => None,
+        },
+        recipients: None,
+        thread_metadata: threads::thread_metadata(
+            channel_type,
+            c.archived,
+            c.auto_archive_minutes,
+            c.starter_message_id,
+        ),
+        permission_overwrites: None,
+    }))
+}
+
+/// Make sure a channel of type `channel_type` in `guild_id` can be placed under `parent_id`.
+///
+/// # Errors
+/// Returns a HTTP 400 if the channel is a category, since categories can't be nested,
+/// or if the parent isn't a category in the same guild.
+pub async fn check_parent(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+    channel_type: ChannelType,
+    parent_id: Option<u128>,
+) -> Result<(), WebServerError> {
+    let parent_id = match parent_id {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    if channel_type == ChannelType::Category {
+        return Err(ErrorJson::new_400("categories can't have a parent".to_string()).into());
+    }
+
+    let parent = sqlx::query!(
+        "SELECT channel_type FROM channels WHERE id = $1 AND guild_id = $2",
+        u128_to_bigdecimal!(parent_id),
+        u128_to_bigdecimal!(guild_id)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_400(format!("Unknown parent channel with ID {}", parent_id)))?;
+    if ChannelType::from_i16(parent.channel_type) == Some(ChannelType::Category) {
+        Ok(())
+    } else {
+        Err(ErrorJson::new_400(format!("parent channel {} is not a category", parent_id)).into())
+    }
+}
+
+/// Make sure `topic` isn't too long.
+///
+/// # Errors
+/// Returns a HTTP 400 if it is longer than [`MAX_TOPIC_LENGTH`].
+pub fn check_topic(topic: &str) -> Result<(), WebServerError> {
+    if topic.len() > MAX_TOPIC_LENGTH {
+        return Err(ErrorJson::new_400(format!(
+            "channel topics must be at most {} bytes",
+            MAX_TOPIC_LENGTH
+        ))
+        .into());
+    }
+    Ok(())
+}
 
 pub fn generate_channels_routes() -> axum::Router {
     debug!("generating routes for channels");
     Router::new()
         // POST   /guilds/:guild_id/channels
+        // PATCH  /guilds/:guild_id/channels
         .route(
             expand_version!("guilds/:guild_id/channels"),
-            post(create_channel),
+            post(create_channel).patch(reorder_channels),
         )
         // GET    /channels/:channel_id
         // PATCH  /channels/:channel_id
@@ -30,10 +136,14 @@ pub fn generate_channels_routes() -> axum::Router {
             expand_version!("channels/:channel_id"),
             get(get_channel).patch(edit_channel).delete(delete_channel),
         )
-        // POST   /channels/:channel_id/typing
-        // DELETE /channels/:channel_id/typing
-        .route(
-            expand_version!("channels/:channel_id/typing"),
-            post(typing_start).delete(typing_end),
-        )
+        // permission overwrite routes
+        .merge(permissions::generate_permissions_routes())
+        // pins routes
+        .merge(pins::generate_pins_routes())
+        // typing routes
+        .merge(typing::generate_typing_routes())
+        // private channels routes
+        .merge(private::generate_private_channels_routes())
+        // threads routes
+        .merge(threads::generate_threads_routes())
 }
@@ -0,0 +1,71 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{AuditLogAction, Channel, ErrorJson};
+use ferrischat_common::ws::WsOutboundEvent;
+
+/// DELETE `/v0/channels/{channel_id}/permissions/{target_id}`
+pub async fn delete_channel_overwrite(
+    Path((channel_id, target_id)): Path<(u128, u128)>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
+) -> Result<http::StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) = crate::require_channel_permissions(
+        channel_id,
+        auth_user,
+        GuildPermissions::MANAGE_CHANNELS,
+    )
+    .await?;
+
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+    let bigdecimal_target_id = u128_to_bigdecimal!(target_id);
+
+    let mut old = crate::channels::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+    old.permission_overwrites =
+        Some(ferrischat_perms::get_permission_overwrites(db, channel_id).await?);
+
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "DELETE FROM permission_overwrites WHERE channel_id = $1 AND target_id = $2 RETURNING target_id",
+        bigdecimal_channel_id,
+        bigdecimal_target_id
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or_else(|| {
+        ErrorJson::new_404(format!(
+            "Unknown permission overwrite for {} in {}",
+            target_id, channel_id
+        ))
+    })?;
+
+    let new = Channel {
+        permission_overwrites: Some(
+            ferrischat_perms::get_permission_overwrites(&mut tx, channel_id).await?,
+        ),
+        ..old.clone()
+    };
+
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::ChannelOverwriteDelete,
+        Some(channel_id),
+        Some(&old),
+        Some(&new),
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
+    let event = WsOutboundEvent::ChannelUpdate { old, new };
+
+    fire_event(&event).await?;
+    Ok(http::StatusCode::NO_CONTENT)
+}
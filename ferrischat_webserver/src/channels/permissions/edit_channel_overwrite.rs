@@ -0,0 +1,125 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use axum::Json;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::request_json::PermissionOverwriteJson;
+use ferrischat_common::types::{
+    AuditLogAction, Channel, ErrorJson, OverwriteType, PermissionOverwrite,
+};
+use ferrischat_common::ws::WsOutboundEvent;
+
+/// PUT `/v0/channels/{channel_id}/permissions/{target_id}`
+///
+/// Creates or replaces the overwrite for the role or member `target_id`.
+pub async fn edit_channel_overwrite(
+    Path((channel_id, target_id)): Path<(u128, u128)>,
+    Json(PermissionOverwriteJson {
+        target_type,
+        allow,
+        deny,
+    }): Json<PermissionOverwriteJson>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
+) -> Result<crate::Json<PermissionOverwrite>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, auth_perms) = crate::require_channel_permissions(
+        channel_id,
+        auth_user,
+        GuildPermissions::MANAGE_CHANNELS,
+    )
+    .await?;
+
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+    let bigdecimal_target_id = u128_to_bigdecimal!(target_id);
+
+    let target_exists = match target_type {
+        OverwriteType::Role => {
+            sqlx::query!(
+                r#"SELECT EXISTS(SELECT * FROM roles WHERE id = $1 AND parent_guild = $2) AS "exists!""#,
+                bigdecimal_target_id,
+                bigdecimal_guild_id
+            )
+            .fetch_one(db)
+            .await?
+            .exists
+        }
+        OverwriteType::Member => {
+            sqlx::query!(
+                r#"SELECT EXISTS(SELECT * FROM members WHERE user_id = $1 AND guild_id = $2) AS "exists!""#,
+                bigdecimal_target_id,
+                bigdecimal_guild_id
+            )
+            .fetch_one(db)
+            .await?
+            .exists
+        }
+    };
+    if !target_exists {
+        return Err(ErrorJson::new_404(format!(
+            "Unknown {:?} with ID {} in {}",
+            target_type, target_id, guild_id
+        ))
+        .into());
+    }
+
+    let mut old = crate::channels::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+    old.permission_overwrites =
+        Some(ferrischat_perms::get_permission_overwrites(db, channel_id).await?);
+
+    let allow = allow.unwrap_or_else(GuildPermissions::empty);
+    let deny = deny.unwrap_or_else(GuildPermissions::empty);
+    // allowing or denying permissions you don't have yourself could be used to hand them out
+    crate::require_can_grant(auth_perms, allow | deny)?;
+    let allow_bytes = ferrischat_perms::permissions_to_bytes(allow);
+    let deny_bytes = ferrischat_perms::permissions_to_bytes(deny);
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "INSERT INTO permission_overwrites VALUES ($1, $2, $3, $4, $5) \
+        ON CONFLICT (channel_id, target_id) DO UPDATE SET target_type = $3, allow = $4, deny = $5",
+        bigdecimal_channel_id,
+        bigdecimal_target_id,
+        target_type as i16,
+        &allow_bytes[..],
+        &deny_bytes[..]
+    )
+    .execute(&mut tx)
+    .await?;
+
+    let new = Channel {
+        permission_overwrites: Some(
+            ferrischat_perms::get_permission_overwrites(&mut tx, channel_id).await?,
+        ),
+        ..old.clone()
+    };
+
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::ChannelOverwriteUpdate,
+        Some(channel_id),
+        Some(&old),
+        Some(&new),
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
+    let event = WsOutboundEvent::ChannelUpdate { old, new };
+
+    fire_event(&event).await?;
+    Ok(crate::Json {
+        obj: PermissionOverwrite {
+            target_id,
+            target_type,
+            allow,
+            deny,
+        },
+        code: 200,
+    })
+}
@@ -0,0 +1,18 @@
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::PermissionOverwrite;
+
+/// GET `/v0/channels/{channel_id}/permissions`
+pub async fn get_channel_overwrites(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<crate::Json<Vec<PermissionOverwrite>>, WebServerError> {
+    crate::require_channel_permissions(channel_id, auth_user, GuildPermissions::READ_MESSAGES)
+        .await?;
+
+    Ok(crate::Json {
+        obj: ferrischat_perms::get_permission_overwrites(get_db_or_fail!(), channel_id).await?,
+        code: 200,
+    })
+}
@@ -0,0 +1,26 @@
+mod delete_channel_overwrite;
+mod edit_channel_overwrite;
+mod get_channel_overwrites;
+
+pub use delete_channel_overwrite::*;
+pub use edit_channel_overwrite::*;
+pub use get_channel_overwrites::*;
+
+use axum::routing::{get, put};
+use axum::Router;
+
+pub fn generate_permissions_routes() -> axum::Router {
+    debug!("generating routes for channel permissions");
+    Router::new()
+        // GET    /channels/:channel_id/permissions
+        .route(
+            expand_version!("channels/:channel_id/permissions"),
+            get(get_channel_overwrites),
+        )
+        // PUT    /channels/:channel_id/permissions/:target_id
+        // DELETE /channels/:channel_id/permissions/:target_id
+        .route(
+            expand_version!("channels/:channel_id/permissions/:target_id"),
+            put(edit_channel_overwrite).delete(delete_channel_overwrite),
+        )
+}
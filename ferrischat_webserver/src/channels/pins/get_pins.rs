@@ -0,0 +1,111 @@
+use crate::embeds::get_message_embeds;
+use crate::messages::attachments::get_message_attachments;
+use crate::messages::reactions::get_message_reactions;
+use crate::messages::references::get_referenced_messages;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{ErrorJson, Message, Pronouns, User, UserFlags};
+use num_traits::ToPrimitive;
+
+/// GET `/v0/channels/{channel_id}/pins`
+///
+/// Lists the messages pinned in the channel, most recently pinned first.
+pub async fn get_pins(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<crate::Json<Vec<Message>>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_channel_permissions(channel_id, auth_user, GuildPermissions::READ_MESSAGES)
+        .await?;
+
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+
+    let channel_obj = crate::channels::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+
+    let resp = sqlx::query!(
+        r#"
+SELECT m.*,
+       a.name AS author_name,
+       a.avatar AS avatar,
+       a.flags AS author_flags,
+       a.discriminator AS author_discriminator,
+       a.pronouns AS author_pronouns
+FROM pins p
+    INNER JOIN messages m ON m.id = p.message_id
+    CROSS JOIN LATERAL (
+        SELECT *
+        FROM users
+        WHERE id = m.author_id
+        ) AS a
+WHERE p.channel_id = $1
+ORDER BY p.pinned_at DESC
+"#,
+        bigdecimal_channel_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let message_ids: Vec<_> = resp
+        .iter()
+        .filter_map(|x| x.id.with_scale(0).into_bigint_and_exponent().0.to_u128())
+        .collect();
+    let mut reactions = get_message_reactions(db, &message_ids, auth_user).await?;
+    let mut attachments = get_message_attachments(db, &message_ids).await?;
+    let mut embeds = get_message_embeds(db, &message_ids).await?;
+    let reference_ids: Vec<_> = resp
+        .iter()
+        .filter_map(|x| x.reference_id.as_ref())
+        .filter_map(|r| r.with_scale(0).into_bigint_and_exponent().0.to_u128())
+        .collect();
+    let referenced_messages = get_referenced_messages(db, &channel_obj, &reference_ids).await?;
+
+    let mut messages = Vec::with_capacity(resp.len());
+    for x in resp {
+        let id = bigdecimal_to_u128!(x.id);
+        let author_id = bigdecimal_to_u128!(x.author_id);
+        let reference_id = match x.reference_id {
+            Some(r) => Some(bigdecimal_to_u128!(r)),
+            None => None,
+        };
+
+        messages.push(Message {
+            id,
+            content: x.content,
+            channel: channel_obj.clone(),
+            channel_id,
+            author_id,
+            author: Some(User {
+                id: author_id,
+                name: x.author_name,
+                avatar: x.avatar,
+                guilds: None,
+                flags: UserFlags::from_bits_truncate(x.author_flags),
+                discriminator: x.author_discriminator,
+                pronouns: x.author_pronouns.and_then(Pronouns::from_i16),
+                is_bot: UserFlags::from_bits_truncate(x.author_flags)
+                    .contains(UserFlags::BOT_ACCOUNT),
+            }),
+            edited_at: x.edited_at,
+            embeds: embeds.remove(&id).unwrap_or_default(),
+            nonce: None,
+            reactions: Some(reactions.remove(&id).unwrap_or_default()),
+            reference_id,
+            referenced_message: reference_id
+                .and_then(|r| referenced_messages.get(&r).cloned())
+                .map(Box::new),
+            mentions: crate::messages::mentions::from_bigdecimals(x.mentions),
+            mention_roles: crate::messages::mentions::from_bigdecimals(x.mention_roles),
+            mention_everyone: x.mention_everyone,
+            attachments: Some(attachments.remove(&id).unwrap_or_default()),
+        });
+    }
+
+    Ok(crate::Json {
+        obj: messages,
+        code: 200,
+    })
+}
@@ -0,0 +1,113 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{AuditLogAction, ErrorJson, MessagePin};
+use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_config::LimitsConfig;
+use http::StatusCode;
+
+/// PUT `/v0/channels/{channel_id}/pins/{message_id}`
+pub async fn pin_message(
+    Path((channel_id, message_id)): Path<(u128, u128)>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
+) -> Result<StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) = crate::require_channel_permissions(
+        channel_id,
+        auth_user,
+        GuildPermissions::READ_MESSAGES | GuildPermissions::MANAGE_MESSAGES,
+    )
+    .await?;
+
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+    let bigdecimal_message_id = u128_to_bigdecimal!(message_id);
+
+    sqlx::query!(
+        "SELECT id FROM messages WHERE id = $1 AND channel_id = $2",
+        bigdecimal_message_id,
+        bigdecimal_channel_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown message with ID {}", message_id)))?;
+
+    let max_pins = ferrischat_config::GLOBAL_CONFIG.get().map_or_else(
+        || LimitsConfig::default().max_pins_per_channel,
+        |c| c.limits.max_pins_per_channel,
+    );
+
+    let mut tx = db.begin().await?;
+
+    // lock the channel so concurrent pins can't both squeeze in under the limit
+    sqlx::query!(
+        "SELECT id FROM channels WHERE id = $1 FOR UPDATE",
+        bigdecimal_channel_id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    let already_pinned = sqlx::query!(
+        r#"SELECT EXISTS(SELECT * FROM pins WHERE channel_id = $1 AND message_id = $2) AS "exists!""#,
+        bigdecimal_channel_id,
+        bigdecimal_message_id
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .exists;
+    // pinning a message twice is a no-op
+    if already_pinned {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    let pin_count = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM pins WHERE channel_id = $1"#,
+        bigdecimal_channel_id
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .count;
+    if pin_count >= i64::from(max_pins) {
+        return Err(ErrorJson::new_400(format!(
+            "channels can have at most {} pinned messages",
+            max_pins
+        ))
+        .into());
+    }
+
+    sqlx::query!(
+        "INSERT INTO pins (channel_id, message_id, pinned_by) VALUES ($1, $2, $3)",
+        bigdecimal_channel_id,
+        bigdecimal_message_id,
+        u128_to_bigdecimal!(auth_user)
+    )
+    .execute(&mut tx)
+    .await?;
+
+    let pin = MessagePin {
+        guild_id,
+        channel_id,
+        message_id,
+        user_id: auth_user,
+    };
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::MessagePin,
+        Some(message_id),
+        None,
+        Some(&pin),
+        reason,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    let event = WsOutboundEvent::MessagePin { pin };
+
+    fire_event(&event).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
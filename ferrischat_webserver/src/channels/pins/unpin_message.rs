@@ -0,0 +1,62 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{AuditLogAction, ErrorJson, MessagePin};
+use ferrischat_common::ws::WsOutboundEvent;
+use http::StatusCode;
+
+/// DELETE `/v0/channels/{channel_id}/pins/{message_id}`
+pub async fn unpin_message(
+    Path((channel_id, message_id)): Path<(u128, u128)>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
+) -> Result<StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) = crate::require_channel_permissions(
+        channel_id,
+        auth_user,
+        GuildPermissions::READ_MESSAGES | GuildPermissions::MANAGE_MESSAGES,
+    )
+    .await?;
+
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "DELETE FROM pins WHERE channel_id = $1 AND message_id = $2 RETURNING message_id",
+        u128_to_bigdecimal!(channel_id),
+        u128_to_bigdecimal!(message_id)
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or_else(|| {
+        ErrorJson::new_404(format!(
+            "Message {} is not pinned in {}",
+            message_id, channel_id
+        ))
+    })?;
+
+    let pin = MessagePin {
+        guild_id,
+        channel_id,
+        message_id,
+        user_id: auth_user,
+    };
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::MessageUnpin,
+        Some(message_id),
+        Some(&pin),
+        None,
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
+    let event = WsOutboundEvent::MessageUnpin { pin };
+
+    fire_event(&event).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,160 @@
+use super::MAX_GROUP_RECIPIENTS;
+use crate::ws::fire_event_to_users;
+use crate::WebServerError;
+use axum::Json as JsonInput;
+use ferrischat_common::request_json::PrivateChannelCreateJson;
+use ferrischat_common::types::{Channel, ChannelType, ErrorJson, ModelType};
+use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_snowflake_generator::generate_snowflake;
+use num_traits::ToPrimitive;
+
+/// POST `/v0/users/me/channels`
+///
+/// With a single recipient, this opens a direct message with them.
+/// If there already is one, it's returned with a 200 instead of a new one being created.
+/// With more, this creates a group direct message owned by the caller, named `name`.
+///
+/// Users can only start private channels with people they share a guild with,
+/// and never with someone they've blocked or who has blocked them.
+pub async fn create_private_channel(
+    crate::Authorization(auth_user, _): crate::Authorization,
+    channel_info: JsonInput<PrivateChannelCreateJson>,
+) -> Result<crate::Json<Channel>, WebServerError> {
+    let PrivateChannelCreateJson {
+        mut recipients,
+        name,
+    } = channel_info.0;
+
+    recipients.sort_unstable();
+    recipients.dedup();
+    recipients.retain(|r| *r != auth_user);
+    if recipients.is_empty() {
+        return Err(
+            ErrorJson::new_400("private channels need at least one recipient".to_string()).into(),
+        );
+    }
+    if recipients.len() + 1 > MAX_GROUP_RECIPIENTS {
+        return Err(ErrorJson::new_400(format!(
+            "group direct messages can have at most {} recipients",
+            MAX_GROUP_RECIPIENTS
+        ))
+        .into());
+    }
+    let channel_type = if recipients.len() == 1 {
+        ChannelType::DirectMessage
+    } else {
+        ChannelType::GroupDirectMessage
+    };
+    let name = match name {
+        Some(name) if channel_type == ChannelType::GroupDirectMessage => {
+            if name.len() > 100 {
+                return Err(ErrorJson::new_400(
+                    "channel names must be at most 100 bytes".to_string(),
+                )
+                .into());
+            }
+            name
+        }
+        // direct messages are named after whoever's on the other end by clients
+        _ => String::new(),
+    };
+
+    let db = get_db_or_fail!();
+    let bigdecimal_auth_user = u128_to_bigdecimal!(auth_user);
+    let bigdecimal_recipients: Vec<_> = recipients
+        .iter()
+        .map(|id| u128_to_bigdecimal!(*id))
+        .collect();
+
+    // recipients that don't share a guild with the caller are as good as unknown to them
+    let reachable: Vec<u128> = sqlx::query!(
+        r#"SELECT DISTINCT m.user_id AS "user_id!" FROM members m INNER JOIN members me ON me.guild_id = m.guild_id WHERE me.user_id = $1 AND m.user_id = ANY($2)"#,
+        bigdecimal_auth_user,
+        &bigdecimal_recipients[..]
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .filter_map(|x| x.user_id.with_scale(0).into_bigint_and_exponent().0.to_u128())
+    .collect();
+    if let Some(unreachable) = recipients.iter().find(|r| !reachable.contains(r)) {
+        return Err(ErrorJson::new_403(format!(
+            "you can only message users you share a guild with, which {} is not",
+            unreachable
+        ))
+        .into());
+    }
+
+    if crate::users::has_block_between(db, auth_user, &recipients).await? {
+        return Err(ErrorJson::new_403(
+            "you can't message users you've blocked or who have blocked you".to_string(),
+        )
+        .into());
+    }
+
+    if channel_type == ChannelType::DirectMessage {
+        let existing = sqlx::query!(
+            "SELECT c.id FROM channels c INNER JOIN channel_recipients a ON a.channel_id = c.id AND a.user_id = $1 INNER JOIN channel_recipients b ON b.channel_id = c.id AND b.user_id = $2 WHERE c.channel_type = $3",
+            bigdecimal_auth_user,
+            bigdecimal_recipients[0],
+            ChannelType::DirectMessage as i16
+        )
+        .fetch_optional(db)
+        .await?;
+        if let Some(existing) = existing {
+            let channel_id = bigdecimal_to_u128!(existing.id);
+            let channel = super::fetch_private_channel(db, channel_id)
+                .await?
+                .ok_or_else(|| {
+                    ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id))
+                })?;
+            return Ok(crate::Json {
+                obj: channel,
+                code: 200,
+            });
+        }
+    }
+
+    let channel_id = generate_snowflake::<0>(ModelType::Channel as u8, get_node_id!());
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+    let owner_id = (channel_type == ChannelType::GroupDirectMessage).then(|| auth_user);
+
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "INSERT INTO channels (id, name, channel_type, owner_id) VALUES ($1, $2, $3, $4)",
+        bigdecimal_channel_id,
+        name,
+        channel_type as i16,
+        owner_id.map(|o| u128_to_bigdecimal!(o))
+    )
+    .execute(&mut tx)
+    .await?;
+    for recipient in bigdecimal_recipients
+        .into_iter()
+        .chain(std::iter::once(bigdecimal_auth_user))
+    {
+        sqlx::query!(
+            "INSERT INTO channel_recipients VALUES ($1, $2)",
+            bigdecimal_channel_id,
+            recipient
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    let channel = super::fetch_private_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+
+    recipients.push(auth_user);
+    let event = WsOutboundEvent::ChannelCreate {
+        channel: channel.clone(),
+    };
+    fire_event_to_users(&event, &recipients).await?;
+
+    Ok(crate::Json {
+        obj: channel,
+        code: 201,
+    })
+}
@@ -0,0 +1,37 @@
+use crate::WebServerError;
+use ferrischat_common::types::Channel;
+use num_traits::ToPrimitive;
+
+/// GET `/v0/users/me/channels`
+///
+/// Returns every direct and group direct message the user is in, most recently created first.
+pub async fn get_private_channels(
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<crate::Json<Vec<Channel>>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let ids = sqlx::query!(
+        "SELECT channel_id FROM channel_recipients WHERE user_id = $1 ORDER BY channel_id DESC",
+        u128_to_bigdecimal!(auth_user)
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut channels = Vec::with_capacity(ids.len());
+    for id in ids.into_iter().filter_map(|x| {
+        x.channel_id
+            .with_scale(0)
+            .into_bigint_and_exponent()
+            .0
+            .to_u128()
+    }) {
+        if let Some(channel) = super::fetch_private_channel(db, id).await? {
+            channels.push(channel);
+        }
+    }
+
+    Ok(crate::Json {
+        obj: channels,
+        code: 200,
+    })
+}
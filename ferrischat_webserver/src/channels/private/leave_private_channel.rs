@@ -0,0 +1,103 @@
+use crate::ws::fire_event_to_users;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{Channel, ChannelType, ErrorJson};
+use ferrischat_common::ws::WsOutboundEvent;
+use num_traits::ToPrimitive;
+
+/// DELETE `/v0/users/me/channels/{channel_id}`
+///
+/// Leaves a group direct message. If its owner leaves, the longest-standing other recipient takes over,
+/// and once everyone has left the channel is deleted.
+/// Direct messages can't be left.
+pub async fn leave_private_channel(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<http::StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) =
+        crate::require_channel_access(channel_id, auth_user, GuildPermissions::empty()).await?;
+    if guild_id.is_some() {
+        return Err(ErrorJson::new_400("guild channels can't be left".to_string()).into());
+    }
+
+    let old = super::fetch_private_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+    if old.channel_type != ChannelType::GroupDirectMessage {
+        return Err(ErrorJson::new_400("direct messages can't be left".to_string()).into());
+    }
+
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "DELETE FROM channel_recipients WHERE channel_id = $1 AND user_id = $2",
+        bigdecimal_channel_id,
+        u128_to_bigdecimal!(auth_user)
+    )
+    .execute(&mut tx)
+    .await?;
+    // recipients are added all at once, so the lowest ID is as fair a pick as any
+    let remaining: Vec<u128> = sqlx::query!(
+        "SELECT user_id FROM channel_recipients WHERE channel_id = $1 ORDER BY user_id",
+        bigdecimal_channel_id
+    )
+    .fetch_all(&mut tx)
+    .await?
+    .into_iter()
+    .filter_map(|x| {
+        x.user_id
+            .with_scale(0)
+            .into_bigint_and_exponent()
+            .0
+            .to_u128()
+    })
+    .collect();
+    let mut attachment_ids = vec![];
+    if remaining.is_empty() {
+        attachment_ids = sqlx::query!(
+            r#"DELETE FROM channels WHERE id = $1 RETURNING ARRAY(SELECT a.id FROM attachments a INNER JOIN messages m ON m.id = a.message_id WHERE m.channel_id = channels.id) AS "attachment_ids!""#,
+            bigdecimal_channel_id
+        )
+        .fetch_one(&mut tx)
+        .await?
+        .attachment_ids;
+    } else if old.owner_id == Some(auth_user) {
+        sqlx::query!(
+            "UPDATE channels SET owner_id = $1 WHERE id = $2",
+            u128_to_bigdecimal!(remaining[0]),
+            bigdecimal_channel_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+    crate::messages::attachments::delete_stored_attachments(attachment_ids).await;
+
+    let event = WsOutboundEvent::ChannelDelete {
+        channel: old.clone(),
+    };
+    fire_event_to_users(&event, &[auth_user]).await?;
+
+    if !remaining.is_empty() {
+        let new = Channel {
+            owner_id: if old.owner_id == Some(auth_user) {
+                Some(remaining[0])
+            } else {
+                old.owner_id
+            },
+            recipients: old.recipients.clone().map(|mut r| {
+                r.retain(|u| u.id != auth_user);
+                r
+            }),
+            ..old.clone()
+        };
+        let event = WsOutboundEvent::ChannelUpdate { old, new };
+        fire_event_to_users(&event, &remaining).await?;
+    }
+
+    Ok(http::StatusCode::NO_CONTENT)
+}
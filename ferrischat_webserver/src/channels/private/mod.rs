@@ -0,0 +1,74 @@
+mod create_private_channel;
+mod get_private_channels;
+mod leave_private_channel;
+
+pub use create_private_channel::*;
+pub use get_private_channels::*;
+pub use leave_private_channel::*;
+
+use crate::WebServerError;
+use axum::routing::{delete, get};
+use axum::Router;
+use ferrischat_common::types::{Channel, Pronouns, User, UserFlags};
+use num_traits::ToPrimitive;
+use sqlx::{Pool, Postgres};
+
+/// Maximum number of recipients in a group direct message, including its owner.
+pub const MAX_GROUP_RECIPIENTS: usize = 10;
+
+/// Fetch the users `channel_id` is shared between.
+pub async fn get_recipients(
+    db: &Pool<Postgres>,
+    channel_id: u128,
+) -> Result<Vec<User>, WebServerError> {
+    Ok(sqlx::query!(
+        "SELECT u.* FROM channel_recipients r INNER JOIN users u ON u.id = r.user_id WHERE r.channel_id = $1 ORDER BY u.id",
+        u128_to_bigdecimal!(channel_id)
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .filter_map(|u| {
+        let flags = UserFlags::from_bits_truncate(u.flags);
+        Some(User {
+            id: u.id.with_scale(0).into_bigint_and_exponent().0.to_u128()?,
+            name: u.name,
+            avatar: u.avatar,
+            guilds: None,
+            flags,
+            discriminator: u.discriminator,
+            pronouns: u.pronouns.and_then(Pronouns::from_i16),
+            is_bot: flags.contains(UserFlags::BOT_ACCOUNT),
+        })
+    })
+    .collect())
+}
+
+/// Fetch the private channel `channel_id` along with its recipients, or `None` if it doesn't exist.
+pub async fn fetch_private_channel(
+    db: &Pool<Postgres>,
+    channel_id: u128,
+) -> Result<Option<Channel>, WebServerError> {
+    let mut channel = match super::fetch_channel(db, channel_id).await? {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+    channel.recipients = Some(get_recipients(db, channel_id).await?);
+    Ok(Some(channel))
+}
+
+pub fn generate_private_channels_routes() -> axum::Router {
+    debug!("generating routes for private channels");
+    Router::new()
+        // GET    /users/me/channels
+        // POST   /users/me/channels
+        .route(
+            expand_version!("users/me/channels"),
+            get(get_private_channels).post(create_private_channel),
+        )
+        // DELETE /users/me/channels/:channel_id
+        .route(
+            expand_version!("users/me/channels/:channel_id"),
+            delete(leave_private_channel),
+        )
+}
@@ -0,0 +1,170 @@
+use crate::ws::fire_event;
+use crate::{Json, WebServerError};
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::request_json::ChannelPositionJson;
+use ferrischat_common::types::{AuditLogAction, Channel, ChannelType, ErrorJson};
+use ferrischat_common::ws::WsOutboundEvent;
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+
+/// PATCH `/v0/guilds/{guild_id}/channels`
+///
+/// Moves any number of channels at once: either every change is made, or none are.
+/// Channels can be moved between categories at the same time by passing a `parent_id`.
+///
+/// Returns every channel in the guild, in order.
+pub async fn reorder_channels(
+    Path(guild_id): Path<u128>,
+    positions: axum::extract::Json<Vec<ChannelPositionJson>>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
+) -> Result<Json<Vec<Channel>>, WebServerError> {
+    let positions = positions.0;
+    let db = get_db_or_fail!();
+
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::MANAGE_CHANNELS)
+        .await?;
+
+    if positions.is_empty() {
+        return Err(ErrorJson::new_400("no channels to move were given".to_string()).into());
+    }
+
+    let mut tx = db.begin().await?;
+
+    // lock every channel in the guild, so nothing else moves them while they're checked
+    // threads aren't ordered, and follow their parent channel around
+    let mut channels: HashMap<u128, Channel> = HashMap::new();
+    for c in sqlx::query!(
+        "SELECT * FROM channels WHERE guild_id = $1 AND channel_type != $2 FOR UPDATE",
+        u128_to_bigdecimal!(guild_id),
+        ChannelType::Thread as i16
+    )
+    .fetch_all(&mut tx)
+    .await?
+    {
+        let id = bigdecimal_to_u128!(c.id);
+        channels.insert(
+            id,
+            Channel {
+                id,
+                name: c.name,
+                guild_id: Some(guild_id),
+                channel_type: ChannelType::from_i16(c.channel_type).unwrap_or_default(),
+                parent_id: c
+                    .parent_id
+                    .and_then(|p| p.with_scale(0).into_bigint_and_exponent().0.to_u128()),
+                position: c.position,
+                topic: c.topic,
+                nsfw: c.nsfw,
+                owner_id: None,
+                recipients: None,
+                thread_metadata: None,
+                permission_overwrites: None,
+            },
+        );
+    }
+
+    let mut moved = HashMap::with_capacity(positions.len());
+    for ChannelPositionJson {
+        id,
+        position,
+        parent_id,
+    } in positions
+    {
+        let old = channels
+            .get(&id)
+            .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", id)))?;
+        let parent_id = parent_id.unwrap_or(old.parent_id);
+
+        // parents are checked against the guild's channels as they were before the move,
+        // which is fine since a channel's type never changes
+        if let Some(parent_id) = parent_id {
+            if old.channel_type == ChannelType::Category {
+                return Err(
+                    ErrorJson::new_400("categories can't have a parent".to_string()).into(),
+                );
+            }
+            match channels.get(&parent_id) {
+                Some(parent) if parent.channel_type == ChannelType::Category => {}
+                Some(_) => {
+                    return Err(ErrorJson::new_400(format!(
+                        "parent channel {} is not a category",
+                        parent_id
+                    ))
+                    .into())
+                }
+                None => {
+                    return Err(ErrorJson::new_400(format!(
+                        "Unknown parent channel with ID {}",
+                        parent_id
+                    ))
+                    .into())
+                }
+            }
+        }
+
+        if moved
+            .insert(
+                id,
+                Channel {
+                    position,
+                    parent_id,
+                    ..old.clone()
+                },
+            )
+            .is_some()
+        {
+            return Err(ErrorJson::new_400(format!("channel {} was given twice", id)).into());
+        }
+    }
+
+    for new in moved.values() {
+        sqlx::query!(
+            "UPDATE channels SET position = $1, parent_id = $2 WHERE id = $3",
+            new.position,
+            new.parent_id.map(|p| u128_to_bigdecimal!(p)),
+            u128_to_bigdecimal!(new.id)
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    let mut changes = Vec::with_capacity(moved.len());
+    for (id, new) in moved {
+        let old = match channels.insert(id, new.clone()) {
+            Some(old) => old,
+            None => continue,
+        };
+        if old.position == new.position && old.parent_id == new.parent_id {
+            continue;
+        }
+
+        crate::add_audit_log_entry(
+            &mut tx,
+            guild_id,
+            auth_user,
+            AuditLogAction::ChannelUpdate,
+            Some(id),
+            Some(&old),
+            Some(&new),
+            reason.clone(),
+        )
+        .await?;
+        changes.push((old, new));
+    }
+    tx.commit().await?;
+
+    for (old, new) in changes {
+        let event = WsOutboundEvent::ChannelUpdate { old, new };
+        fire_event(&event).await?;
+    }
+
+    let mut channels: Vec<_> = channels.into_values().collect();
+    channels.sort_unstable_by_key(|c| (c.position, c.id));
+
+    Ok(Json {
+        obj: channels,
+        code: 200,
+    })
+}
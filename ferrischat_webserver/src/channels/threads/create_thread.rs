@@ -0,0 +1,63 @@
+use crate::WebServerError;
+use axum::extract::Path;
+use axum::Json as JsonInput;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::request_json::ThreadCreateJson;
+use ferrischat_common::types::{Channel, ErrorJson};
+
+/// POST `/v0/channels/{channel_id}/threads`
+///
+/// Starts a thread that isn't attached to any message.
+pub async fn create_thread(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    thread_info: JsonInput<ThreadCreateJson>,
+) -> Result<crate::Json<Channel>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) = crate::require_channel_permissions(
+        channel_id,
+        auth_user,
+        GuildPermissions::READ_MESSAGES | GuildPermissions::SEND_MESSAGES,
+    )
+    .await?;
+
+    super::start_thread(db, guild_id, channel_id, auth_user, None, thread_info.0).await
+}
+
+/// POST `/v0/channels/{channel_id}/messages/{message_id}/threads`
+///
+/// Starts a thread from a message. Each message can only have one thread.
+pub async fn create_thread_from_message(
+    Path((channel_id, message_id)): Path<(u128, u128)>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    thread_info: JsonInput<ThreadCreateJson>,
+) -> Result<crate::Json<Channel>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) = crate::require_channel_permissions(
+        channel_id,
+        auth_user,
+        GuildPermissions::READ_MESSAGES | GuildPermissions::SEND_MESSAGES,
+    )
+    .await?;
+
+    sqlx::query!(
+        "SELECT id FROM messages WHERE id = $1 AND channel_id = $2",
+        u128_to_bigdecimal!(message_id),
+        u128_to_bigdecimal!(channel_id)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown message with ID {}", message_id)))?;
+
+    super::start_thread(
+        db,
+        guild_id,
+        channel_id,
+        auth_user,
+        Some(message_id),
+        thread_info.0,
+    )
+    .await
+}
@@ -0,0 +1,44 @@
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::ThreadMember;
+use num_traits::ToPrimitive;
+
+/// GET `/v0/channels/{thread_id}/members`
+pub async fn get_thread_members(
+    Path(thread_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<crate::Json<Vec<ThreadMember>>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) =
+        crate::require_channel_permissions(thread_id, auth_user, GuildPermissions::READ_MESSAGES)
+            .await?;
+    super::fetch_thread(db, thread_id).await?;
+
+    let members = sqlx::query!(
+        "SELECT user_id FROM thread_members WHERE thread_id = $1 ORDER BY joined_at, user_id",
+        u128_to_bigdecimal!(thread_id)
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .filter_map(|x| {
+        Some(ThreadMember {
+            thread_id,
+            user_id: x
+                .user_id
+                .with_scale(0)
+                .into_bigint_and_exponent()
+                .0
+                .to_u128()?,
+            guild_id,
+        })
+    })
+    .collect();
+
+    Ok(crate::Json {
+        obj: members,
+        code: 200,
+    })
+}
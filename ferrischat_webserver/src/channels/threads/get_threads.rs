@@ -0,0 +1,87 @@
+use crate::WebServerError;
+use axum::extract::{Path, Query};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::request_json::GetArchivedThreadsParams;
+use ferrischat_common::types::{Channel, ChannelType, ErrorJson};
+
+/// Maximum number of archived threads returned at once.
+pub const MAX_ARCHIVED_THREADS_PAGE: i64 = 100;
+
+/// GET `/v0/channels/{channel_id}/threads`
+///
+/// Returns every thread in the channel that isn't archived, most recently active first.
+pub async fn get_active_threads(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<crate::Json<Vec<Channel>>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_channel_permissions(channel_id, auth_user, GuildPermissions::READ_MESSAGES)
+        .await?;
+
+    let resp = sqlx::query!(
+        "SELECT id FROM channels WHERE parent_id = $1 AND channel_type = $2 AND NOT archived ORDER BY last_activity_at DESC, id DESC",
+        u128_to_bigdecimal!(channel_id),
+        ChannelType::Thread as i16
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut threads = Vec::with_capacity(resp.len());
+    for x in resp {
+        if let Some(thread) = crate::channels::fetch_channel(db, bigdecimal_to_u128!(x.id)).await? {
+            threads.push(thread);
+        }
+    }
+
+    Ok(crate::Json {
+        obj: threads,
+        code: 200,
+    })
+}
+
+/// GET `/v0/channels/{channel_id}/threads/archived`
+///
+/// Returns the channel's archived threads, most recently archived first.
+/// Older pages can be fetched by passing the last thread of a page as `before`.
+pub async fn get_archived_threads(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    Query(GetArchivedThreadsParams { before, limit }): Query<GetArchivedThreadsParams>,
+) -> Result<crate::Json<Vec<Channel>>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_channel_permissions(channel_id, auth_user, GuildPermissions::READ_MESSAGES)
+        .await?;
+
+    let limit = limit.unwrap_or(50);
+    if !(1..=MAX_ARCHIVED_THREADS_PAGE).contains(&limit) {
+        return Err(ErrorJson::new_400(format!(
+            "limit must be between 1 and {}",
+            MAX_ARCHIVED_THREADS_PAGE
+        ))
+        .into());
+    }
+
+    let resp = sqlx::query!(
+        "SELECT id FROM channels WHERE parent_id = $1 AND channel_type = $2 AND archived AND ($3::numeric(39) IS NULL OR (archived_at, id) < (SELECT archived_at, id FROM channels WHERE id = $3)) ORDER BY archived_at DESC, id DESC LIMIT $4",
+        u128_to_bigdecimal!(channel_id),
+        ChannelType::Thread as i16,
+        before.map(|b| u128_to_bigdecimal!(b)),
+        limit
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut threads = Vec::with_capacity(resp.len());
+    for x in resp {
+        if let Some(thread) = crate::channels::fetch_channel(db, bigdecimal_to_u128!(x.id)).await? {
+            threads.push(thread);
+        }
+    }
+
+    Ok(crate::Json {
+        obj: threads,
+        code: 200,
+    })
+}
@@ -0,0 +1,45 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::ThreadMember;
+use ferrischat_common::ws::WsOutboundEvent;
+use http::StatusCode;
+
+/// PUT `/v0/channels/{thread_id}/members/me`
+///
+/// Joining a thread twice is a no-op.
+pub async fn join_thread(
+    Path(thread_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) =
+        crate::require_channel_permissions(thread_id, auth_user, GuildPermissions::READ_MESSAGES)
+            .await?;
+    super::fetch_thread(db, thread_id).await?;
+
+    let joined = sqlx::query!(
+        "INSERT INTO thread_members (thread_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        u128_to_bigdecimal!(thread_id),
+        u128_to_bigdecimal!(auth_user)
+    )
+    .execute(db)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if joined {
+        let event = WsOutboundEvent::ThreadMemberAdd {
+            member: ThreadMember {
+                thread_id,
+                user_id: auth_user,
+                guild_id,
+            },
+        };
+        fire_event(&event).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,39 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{ErrorJson, ThreadMember};
+use ferrischat_common::ws::WsOutboundEvent;
+use http::StatusCode;
+
+/// DELETE `/v0/channels/{thread_id}/members/me`
+pub async fn leave_thread(
+    Path(thread_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) =
+        crate::require_channel_permissions(thread_id, auth_user, GuildPermissions::empty()).await?;
+    super::fetch_thread(db, thread_id).await?;
+
+    sqlx::query!(
+        "DELETE FROM thread_members WHERE thread_id = $1 AND user_id = $2 RETURNING user_id",
+        u128_to_bigdecimal!(thread_id),
+        u128_to_bigdecimal!(auth_user)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404("you are not a member of this thread".to_string()))?;
+
+    let event = WsOutboundEvent::ThreadMemberRemove {
+        member: ThreadMember {
+            thread_id,
+            user_id: auth_user,
+            guild_id,
+        },
+    };
+    fire_event(&event).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
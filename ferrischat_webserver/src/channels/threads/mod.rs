@@ -0,0 +1,347 @@
+//! Threads: side conversations started in a text or announcement channel, either on their own
+//! or from one of its messages.
+//!
+//! Threads are channels of type [`ChannelType::Thread`] whose `parent_id` is the channel they were
+//! started in, and whose permissions are that channel's.
+//! They are archived once nobody has posted in them for a while, and sending a message unarchives them.
+
+mod create_thread;
+mod get_thread_members;
+mod get_threads;
+mod join_thread;
+mod leave_thread;
+
+pub use create_thread::*;
+pub use get_thread_members::*;
+pub use get_threads::*;
+pub use join_thread::*;
+pub use leave_thread::*;
+
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::routing::{get, post, put};
+use axum::Router;
+use ferrischat_common::request_json::ThreadCreateJson;
+use ferrischat_common::types::{
+    Channel, ChannelType, ErrorJson, ModelType, ThreadMember, ThreadMetadata,
+};
+use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_config::LimitsConfig;
+use ferrischat_snowflake_generator::generate_snowflake;
+use num_traits::ToPrimitive;
+use sqlx::types::BigDecimal;
+use sqlx::{Pool, Postgres};
+use std::time::Duration;
+
+/// Shortest inactivity threads can ask for before they're archived, in minutes.
+pub const MIN_AUTO_ARCHIVE_MINUTES: i32 = 60;
+
+/// How often threads are checked for inactivity.
+pub const ARCHIVE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+fn get_limits() -> LimitsConfig {
+    ferrischat_config::GLOBAL_CONFIG
+        .get()
+        .map_or_else(LimitsConfig::default, |c| c.limits.clone())
+}
+
+/// Build the thread metadata of a channel from its row, or `None` if it isn't a thread.
+pub fn thread_metadata(
+    channel_type: ChannelType,
+    archived: bool,
+    auto_archive_minutes: Option<i32>,
+    starter_message_id: Option<BigDecimal>,
+) -> Option<ThreadMetadata> {
+    (channel_type == ChannelType::Thread).then(|| ThreadMetadata {
+        archived,
+        auto_archive_minutes: auto_archive_minutes.unwrap_or_default(),
+        starter_message_id: starter_message_id
+            .and_then(|m| m.with_scale(0).into_bigint_and_exponent().0.to_u128()),
+    })
+}
+
+/// Make sure `minutes` is an inactivity threads can be archived after,
+/// falling back to the configured default if it's `None`.
+///
+/// # Errors
+/// Returns a HTTP 400 if it's outside the range allowed by the config.
+pub fn check_auto_archive(minutes: Option<i32>) -> Result<i32, WebServerError> {
+    let limits = get_limits();
+    let max = i32::try_from(limits.max_thread_auto_archive_minutes).unwrap_or(i32::MAX);
+    let minutes = match minutes {
+        Some(m) => m,
+        None => {
+            return Ok(i32::try_from(limits.default_thread_auto_archive_minutes)
+                .unwrap_or(max)
+                .clamp(MIN_AUTO_ARCHIVE_MINUTES, max.max(MIN_AUTO_ARCHIVE_MINUTES)))
+        }
+    };
+    if (MIN_AUTO_ARCHIVE_MINUTES..=max).contains(&minutes) {
+        Ok(minutes)
+    } else {
+        Err(ErrorJson::new_400(format!(
+            "threads must be archived after between {} and {} minutes of inactivity",
+            MIN_AUTO_ARCHIVE_MINUTES, max
+        ))
+        .into())
+    }
+}
+
+/// Fetch `channel_id`, making sure it's a thread.
+///
+/// # Errors
+/// Returns a HTTP 404 if the channel doesn't exist, and a HTTP 400 if it isn't a thread.
+pub async fn fetch_thread(
+    db: &Pool<Postgres>,
+    channel_id: u128,
+) -> Result<Channel, WebServerError> {
+    let channel = super::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+    if channel.channel_type == ChannelType::Thread {
+        Ok(channel)
+    } else {
+        Err(ErrorJson::new_400(format!("channel {} is not a thread", channel_id)).into())
+    }
+}
+
+/// Start a thread in `parent_id`, optionally from the message `starter_message_id`,
+/// with `owner_id` as its owner and first member.
+///
+/// The caller must have made sure the owner can send messages in the parent channel,
+/// and that the starter message is in it.
+///
+/// # Errors
+/// Returns a HTTP 400 if threads can't be started in the parent channel or the thread info is invalid,
+/// and a HTTP 409 if the starter message already has a thread.
+pub async fn start_thread(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+    parent_id: u128,
+    owner_id: u128,
+    starter_message_id: Option<u128>,
+    thread_info: ThreadCreateJson,
+) -> Result<crate::Json<Channel>, WebServerError> {
+    let ThreadCreateJson {
+        name,
+        auto_archive_minutes,
+    } = thread_info;
+
+    let parent = super::fetch_channel(db, parent_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", parent_id)))?;
+    if !matches!(
+        parent.channel_type,
+        ChannelType::Text | ChannelType::Announcement
+    ) {
+        return Err(ErrorJson::new_400(
+            "threads can only be started in text and announcement channels".to_string(),
+        )
+        .into());
+    }
+    if name.is_empty() || name.len() > 100 {
+        return Err(
+            ErrorJson::new_400("thread names must be between 1 and 100 bytes".to_string()).into(),
+        );
+    }
+    let auto_archive_minutes = check_auto_archive(auto_archive_minutes)?;
+
+    if let Some(starter_message_id) = starter_message_id {
+        let existing = sqlx::query!(
+            "SELECT id FROM channels WHERE starter_message_id = $1",
+            u128_to_bigdecimal!(starter_message_id)
+        )
+        .fetch_optional(db)
+        .await?;
+        if existing.is_some() {
+            return Err(ErrorJson::new_409(format!(
+                "message {} already has a thread",
+                starter_message_id
+            ))
+            .into());
+        }
+    }
+
+    let thread_id = generate_snowflake::<0>(ModelType::Channel as u8, get_node_id!());
+    let bigdecimal_thread_id = u128_to_bigdecimal!(thread_id);
+    let bigdecimal_owner_id = u128_to_bigdecimal!(owner_id);
+
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "INSERT INTO channels (id, name, guild_id, channel_type, parent_id, owner_id, starter_message_id, auto_archive_minutes, last_activity_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, now())",
+        bigdecimal_thread_id,
+        name,
+        u128_to_bigdecimal!(guild_id),
+        ChannelType::Thread as i16,
+        u128_to_bigdecimal!(parent_id),
+        bigdecimal_owner_id,
+        starter_message_id.map(|m| u128_to_bigdecimal!(m)),
+        auto_archive_minutes
+    )
+    .execute(&mut tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO thread_members (thread_id, user_id) VALUES ($1, $2)",
+        bigdecimal_thread_id,
+        bigdecimal_owner_id
+    )
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    let thread = fetch_thread(db, thread_id).await?;
+
+    let event = WsOutboundEvent::ThreadCreate {
+        thread: thread.clone(),
+    };
+    fire_event(&event).await?;
+
+    let event = WsOutboundEvent::ThreadMemberAdd {
+        member: ThreadMember {
+            thread_id,
+            user_id: owner_id,
+            guild_id,
+        },
+    };
+    fire_event(&event).await?;
+
+    Ok(crate::Json {
+        obj: thread,
+        code: 201,
+    })
+}
+
+/// Record that `user_id` just posted in `channel`, if it's a thread:
+/// this unarchives it, pushes back its next archival, and adds the user to its members.
+///
+/// Does nothing for any other channel.
+pub async fn record_thread_activity(
+    db: &Pool<Postgres>,
+    channel: &Channel,
+    user_id: u128,
+) -> Result<(), WebServerError> {
+    let guild_id = match channel.guild_id {
+        Some(g) if channel.channel_type == ChannelType::Thread => g,
+        _ => return Ok(()),
+    };
+    let bigdecimal_thread_id = u128_to_bigdecimal!(channel.id);
+
+    sqlx::query!(
+        "UPDATE channels SET last_activity_at = now(), archived = false, archived_at = NULL WHERE id = $1",
+        bigdecimal_thread_id
+    )
+    .execute(db)
+    .await?;
+
+    if let Some(metadata) = channel.thread_metadata.as_ref().filter(|m| m.archived) {
+        let event = WsOutboundEvent::ThreadUpdate {
+            old: channel.clone(),
+            new: Channel {
+                thread_metadata: Some(ThreadMetadata {
+                    archived: false,
+                    ..metadata.clone()
+                }),
+                ..channel.clone()
+            },
+        };
+        fire_event(&event).await?;
+    }
+
+    let joined = sqlx::query!(
+        "INSERT INTO thread_members (thread_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        bigdecimal_thread_id,
+        u128_to_bigdecimal!(user_id)
+    )
+    .execute(db)
+    .await?
+    .rows_affected()
+        > 0;
+    if joined {
+        let event = WsOutboundEvent::ThreadMemberAdd {
+            member: ThreadMember {
+                thread_id: channel.id,
+                user_id,
+                guild_id,
+            },
+        };
+        fire_event(&event).await?;
+    }
+
+    Ok(())
+}
+
+/// Archive every thread that has gone quiet for longer than it asked for,
+/// and let clients know about it.
+async fn archive_inactive_threads() -> Result<(), WebServerError> {
+    let db = get_db_or_fail!();
+
+    let archived = sqlx::query!(
+        "UPDATE channels SET archived = true, archived_at = now() WHERE channel_type = $1 AND NOT archived AND last_activity_at + auto_archive_minutes * interval '1 minute' < now() RETURNING id",
+        ChannelType::Thread as i16
+    )
+    .fetch_all(db)
+    .await?;
+
+    for thread in archived {
+        let new = match super::fetch_channel(db, bigdecimal_to_u128!(thread.id)).await? {
+            Some(c) => c,
+            None => continue,
+        };
+        let old = Channel {
+            thread_metadata: new.thread_metadata.clone().map(|m| ThreadMetadata {
+                archived: false,
+                ..m
+            }),
+            ..new.clone()
+        };
+        let event = WsOutboundEvent::ThreadUpdate { old, new };
+        fire_event(&event).await?;
+    }
+
+    Ok(())
+}
+
+/// Start the background task that archives inactive threads.
+pub fn init_thread_archiver() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(ARCHIVE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if archive_inactive_threads().await.is_err() {
+                warn!("failed to archive inactive threads");
+            }
+        }
+    });
+}
+
+pub fn generate_threads_routes() -> axum::Router {
+    debug!("generating routes for threads");
+    Router::new()
+        // GET    /channels/:channel_id/threads
+        // POST   /channels/:channel_id/threads
+        .route(
+            expand_version!("channels/:channel_id/threads"),
+            get(get_active_threads).post(create_thread),
+        )
+        // GET    /channels/:channel_id/threads/archived
+        .route(
+            expand_version!("channels/:channel_id/threads/archived"),
+            get(get_archived_threads),
+        )
+        // POST   /channels/:channel_id/messages/:message_id/threads
+        .route(
+            expand_version!("channels/:channel_id/messages/:message_id/threads"),
+            post(create_thread_from_message),
+        )
+        // GET    /channels/:channel_id/members
+        .route(
+            expand_version!("channels/:channel_id/members"),
+            get(get_thread_members),
+        )
+        // PUT    /channels/:channel_id/members/me
+        // DELETE /channels/:channel_id/members/me
+        .route(
+            expand_version!("channels/:channel_id/members/me"),
+            put(join_thread).delete(leave_thread),
+        )
+}
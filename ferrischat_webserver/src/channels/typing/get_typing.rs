@@ -0,0 +1,25 @@
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::User;
+
+/// GET `/v0/channels/{channel_id}/typing`
+///
+/// Returns everyone currently typing in the channel.
+pub async fn get_typing(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(authorized_user, _): crate::Authorization,
+) -> Result<crate::Json<Vec<User>>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_channel_access(channel_id, authorized_user, GuildPermissions::READ_MESSAGES)
+        .await?;
+
+    let typers = super::get_typers(channel_id).await?;
+    let users = super::fetch_typing_users(db, &typers).await?;
+
+    Ok(crate::Json {
+        obj: users,
+        code: 200,
+    })
+}
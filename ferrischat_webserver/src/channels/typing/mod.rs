@@ -0,0 +1,220 @@
+//! Typing indicators, tracked in Redis so they end on their own.
+//!
+//! Every user typing in a channel has an expiry time, which [`typing_start`] pushes back.
+//! Once it passes, a background task removes them and fires a `TypingEnd` for them,
+//! so clients that go away without saying so don't leave others showing them as typing.
+
+mod get_typing;
+mod typing_end;
+mod typing_start;
+
+pub use get_typing::*;
+pub use typing_end::*;
+pub use typing_start::*;
+
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::routing::get;
+use axum::Router;
+use ferrischat_common::types::{Channel, Pronouns, User, UserFlags};
+use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_redis::deadpool_redis::Connection;
+use ferrischat_redis::redis::{self, AsyncCommands};
+use sqlx::{Pool, Postgres};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Seconds a user is shown as typing for after they last said they were.
+pub const TYPING_TTL_SECS: u64 = 10;
+
+/// How often expired typing indicators are looked for.
+pub const TYPING_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sorted set of `{channel_id}:{user_id}` for everyone typing anywhere, scored by when they stop.
+///
+/// This is the only key typing indicators are stored under,
+/// so every command and script touching them works on a single key.
+const TYPING_EXPIRIES_KEY: &str = "typing_expiries";
+
+/// Removes every expired entry from the expiries set and returns them,
+/// all at once so that only one node fires `TypingEnd` for each.
+const EXPIRE_TYPING_SCRIPT: &str = r"
+local expired = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1])
+for _, member in ipairs(expired) do
+    redis.call('ZREM', KEYS[1], member)
+end
+return expired
+";
+
+fn expiry_member(channel_id: u128, user_id: u128) -> String {
+    format!("{}:{}", channel_id, user_id)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+async fn get_redis() -> Result<Connection, WebServerError> {
+    Ok(ferrischat_redis::REDIS_MANAGER
+        .get()
+        .ok_or(WebServerError::MissingRedis)?
+        .get()
+        .await?)
+}
+
+/// Mark `user_id` as typing in `channel_id` for the next [`TYPING_TTL_SECS`] seconds.
+///
+/// Returns `false` if they were already typing, in which case only their expiry is pushed back
+/// and nobody needs to be told again.
+///
+/// # Errors
+/// Returns a HTTP 500 if Redis returns an error.
+pub async fn start_typing(channel_id: u128, user_id: u128) -> Result<bool, WebServerError> {
+    let added: u32 = get_redis()
+        .await?
+        .zadd(
+            TYPING_EXPIRIES_KEY,
+            expiry_member(channel_id, user_id),
+            now_secs() + TYPING_TTL_SECS,
+        )
+        .await?;
+    Ok(added > 0)
+}
+
+/// Mark `user_id` as no longer typing in `channel_id`.
+///
+/// Returns `false` if they weren't typing in the first place.
+///
+/// # Errors
+/// Returns a HTTP 500 if Redis returns an error.
+pub async fn stop_typing(channel_id: u128, user_id: u128) -> Result<bool, WebServerError> {
+    let removed: u32 = get_redis()
+        .await?
+        .zrem(TYPING_EXPIRIES_KEY, expiry_member(channel_id, user_id))
+        .await?;
+    Ok(removed > 0)
+}
+
+/// Fetch the IDs of everyone currently typing in `channel_id`.
+///
+/// Only so many people are ever typing at once, so this picks them out of everyone typing anywhere.
+///
+/// # Errors
+/// Returns a HTTP 500 if Redis returns an error.
+pub async fn get_typers(channel_id: u128) -> Result<Vec<u128>, WebServerError> {
+    let typing: Vec<String> = get_redis()
+        .await?
+        .zrangebyscore(TYPING_EXPIRIES_KEY, now_secs(), "+inf")
+        .await?;
+    let prefix = format!("{}:", channel_id);
+    Ok(typing
+        .iter()
+        .filter_map(|member| member.strip_prefix(&prefix)?.parse().ok())
+        .collect())
+}
+
+/// Stop showing `user` as typing in `channel`, and let clients know if they were.
+///
+/// # Errors
+/// Returns a HTTP 500 if Redis returns an error or the event couldn't be fired.
+pub async fn clear_typing(channel: &Channel, user: &User) -> Result<(), WebServerError> {
+    if stop_typing(channel.id, user.id).await? {
+        let event = WsOutboundEvent::TypingEnd {
+            channel: channel.clone(),
+            user: user.clone(),
+        };
+        fire_event(&event).await?;
+    }
+    Ok(())
+}
+
+/// Fetch the users in `user_ids`, as shown in typing events.
+pub async fn fetch_typing_users(
+    db: &Pool<Postgres>,
+    user_ids: &[u128],
+) -> Result<Vec<User>, WebServerError> {
+    let bigdecimal_user_ids: Vec<_> = user_ids.iter().map(|id| u128_to_bigdecimal!(*id)).collect();
+
+    let resp = sqlx::query!(
+        "SELECT * FROM users WHERE id = ANY($1)",
+        &bigdecimal_user_ids[..]
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut users = Vec::with_capacity(resp.len());
+    for user in resp {
+        let flags = UserFlags::from_bits_truncate(user.flags);
+        users.push(User {
+            id: bigdecimal_to_u128!(user.id),
+            name: user.name,
+            avatar: user.avatar,
+            guilds: None,
+            discriminator: user.discriminator,
+            flags,
+            pronouns: user.pronouns.and_then(Pronouns::from_i16),
+            is_bot: flags.contains(UserFlags::BOT_ACCOUNT),
+        });
+    }
+    Ok(users)
+}
+
+/// Remove every typing indicator that has expired, and fire a `TypingEnd` for each.
+async fn expire_typing() -> Result<(), WebServerError> {
+    let db = get_db_or_fail!();
+
+    let expired: Vec<String> = redis::Script::new(EXPIRE_TYPING_SCRIPT)
+        .key(TYPING_EXPIRIES_KEY)
+        .arg(now_secs())
+        .invoke_async(&mut get_redis().await?)
+        .await?;
+
+    for member in expired {
+        let (channel_id, user_id) = match member
+            .split_once(':')
+            .and_then(|(c, u)| Some((c.parse::<u128>().ok()?, u.parse::<u128>().ok()?)))
+        {
+            Some(ids) => ids,
+            None => continue,
+        };
+        let channel = match super::fetch_channel(db, channel_id).await? {
+            Some(c) => c,
+            None => continue,
+        };
+        let user = match fetch_typing_users(db, &[user_id]).await?.pop() {
+            Some(u) => u,
+            None => continue,
+        };
+
+        let event = WsOutboundEvent::TypingEnd { channel, user };
+        fire_event(&event).await?;
+    }
+
+    Ok(())
+}
+
+/// Start the background task that ends expired typing indicators.
+pub fn init_typing_sweeper() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(TYPING_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if expire_typing().await.is_err() {
+                warn!("failed to expire typing indicators");
+            }
+        }
+    });
+}
+
+pub fn generate_typing_routes() -> axum::Router {
+    debug!("generating routes for typing");
+    Router::new()
+        // GET    /channels/:channel_id/typing
+        // POST   /channels/:channel_id/typing
+        // DELETE /channels/:channel_id/typing
+        .route(
+            expand_version!("channels/:channel_id/typing"),
+            get(get_typing).post(typing_start).delete(typing_end),
+        )
+}
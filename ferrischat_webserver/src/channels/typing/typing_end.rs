@@ -0,0 +1,30 @@
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::ErrorJson;
+
+/// DELETE `/v0/channels/{channel_id}/typing`
+///
+/// Does nothing if the user wasn't typing.
+pub async fn typing_end(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(authorized_user, _): crate::Authorization,
+) -> Result<http::StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_channel_access(channel_id, authorized_user, GuildPermissions::SEND_MESSAGES)
+        .await?;
+
+    let user_obj = super::fetch_typing_users(db, &[authorized_user])
+        .await?
+        .pop()
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown user with ID {}", authorized_user)))?;
+
+    let channel = crate::channels::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+
+    super::clear_typing(&channel, &user_obj).await?;
+
+    Ok(http::StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,43 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::ErrorJson;
+use ferrischat_common::ws::WsOutboundEvent;
+
+/// POST `/v0/channels/{channel_id}/typing`
+///
+/// Shows the user as typing for the next [`super::TYPING_TTL_SECS`] seconds.
+/// Clients should call this again while the user keeps typing:
+/// only the first call fires a `TypingStart`, and the rest keep it from expiring.
+pub async fn typing_start(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(authorized_user, _): crate::Authorization,
+) -> Result<http::StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_channel_access(channel_id, authorized_user, GuildPermissions::SEND_MESSAGES)
+        .await?;
+
+    if !super::start_typing(channel_id, authorized_user).await? {
+        return Ok(http::StatusCode::NO_CONTENT);
+    }
+
+    let user_obj = super::fetch_typing_users(db, &[authorized_user])
+        .await?
+        .pop()
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown user with ID {}", authorized_user)))?;
+
+    let channel_obj = crate::channels::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+
+    let event = WsOutboundEvent::TypingStart {
+        channel: channel_obj,
+        user: user_obj,
+    };
+
+    fire_event(&event).await?;
+
+    Ok(http::StatusCode::NO_CONTENT)
+}
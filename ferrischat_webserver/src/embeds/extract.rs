@@ -0,0 +1,340 @@
+use serde::Deserialize;
+use url::Url;
+
+/// Maximum length of an embed's title, in bytes. Longer titles are cut off.
+pub const MAX_TITLE_LENGTH: usize = 256;
+
+/// Maximum length of an embed's description, in bytes. Longer descriptions are cut off.
+pub const MAX_DESCRIPTION_LENGTH: usize = 2048;
+
+/// Find the `http` and `https` links in message content, in the order they first appear.
+///
+/// Links wrapped in `<` and `>` are skipped, so users can post links without an embed.
+#[must_use]
+pub fn find_links(content: &str, max: usize) -> Vec<Url> {
+    let mut links: Vec<Url> = Vec::new();
+    for word in content.split(char::is_whitespace) {
+        if links.len() >= max {
+            break;
+        }
+        if word.starts_with('<') && word.ends_with('>') {
+            continue;
+        }
+        let start = match word.find("https://").or_else(|| word.find("http://")) {
+            Some(start) => start,
+            None => continue,
+        };
+        // punctuation right after a link is almost always part of the sentence, not the link
+        let candidate = word[start..]
+            .trim_end_matches(&['.', ',', ')', '!', '?', ';', ':', '\'', '"', '>'][..]);
+        if let Ok(url) = Url::parse(candidate) {
+            if !links.contains(&url) {
+                links.push(url);
+            }
+        }
+    }
+    links
+}
+
+/// Metadata pulled out of a page, from OpenGraph tags or failing that, the plain HTML.
+#[derive(Debug, Default)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    pub image: Option<String>,
+    pub canonical_url: Option<String>,
+    /// Where the page's oEmbed JSON can be fetched from, if it advertises one.
+    pub oembed_url: Option<String>,
+}
+
+impl PageMetadata {
+    /// Whether there's anything worth putting in an embed.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.image.is_none()
+    }
+}
+
+/// Pull the metadata out of the `<head>` of an HTML page.
+///
+/// This isn't a full HTML parser: it only understands well-formed `<meta>`, `<link>` and `<title>` tags,
+/// which is all that's needed for pages that want to be embedded.
+#[must_use]
+pub fn parse_html(html: &str, base: &Url) -> PageMetadata {
+    let mut meta = PageMetadata::default();
+    let mut html_title = None;
+    let lower = html.to_ascii_lowercase();
+
+    let mut pos = 0;
+    while let Some(offset) = lower[pos..].find('<') {
+        let start = pos + offset + 1;
+        let end = match lower[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        pos = end + 1;
+
+        let tag = &html[start..end];
+        let lower_tag = &lower[start..end];
+        if lower_tag.starts_with("/head") || lower_tag.starts_with("body") {
+            break;
+        }
+
+        if lower_tag.starts_with("title") && html_title.is_none() {
+            if let Some(close) = lower[pos..].find("</title") {
+                html_title = Some(decode_entities(html[pos..pos + close].trim()));
+            }
+        } else if lower_tag.starts_with("meta ") {
+            let attrs = parse_attributes(&tag[4..]);
+            let key = attr(&attrs, "property").or_else(|| attr(&attrs, "name"));
+            let content = attr(&attrs, "content").map(|c| decode_entities(c.trim()));
+            if let (Some(key), Some(content)) = (key, content) {
+                let slot = match key.to_ascii_lowercase().as_str() {
+                    "og:title" | "twitter:title" => &mut meta.title,
+                    "og:description" | "twitter:description" | "description" => {
+                        &mut meta.description
+                    }
+                    "og:site_name" => &mut meta.site_name,
+                    "og:image" | "og:image:url" | "twitter:image" => &mut meta.image,
+                    "og:url" => &mut meta.canonical_url,
+                    _ => continue,
+                };
+                // the first tag wins, and OpenGraph tags usually come first
+                if slot.is_none() && !content.is_empty() {
+                    *slot = Some(content);
+                }
+            }
+        } else if lower_tag.starts_with("link ") {
+            let attrs = parse_attributes(&tag[4..]);
+            let is_oembed = attr(&attrs, "type")
+                .map_or(false, |t| t.eq_ignore_ascii_case("application/json+oembed"));
+            if is_oembed && meta.oembed_url.is_none() {
+                meta.oembed_url = attr(&attrs, "href").map(|h| decode_entities(h.trim()));
+            }
+        }
+    }
+
+    if meta.title.is_none() {
+        meta.title = html_title.filter(|t| !t.is_empty());
+    }
+    meta.title = meta.title.map(|t| truncate(t, MAX_TITLE_LENGTH));
+    meta.description = meta
+        .description
+        .map(|d| truncate(d, MAX_DESCRIPTION_LENGTH));
+    // relative links are resolved against the page they were found on
+    meta.image = meta.image.and_then(|i| resolve(base, &i));
+    meta.canonical_url = meta.canonical_url.and_then(|u| resolve(base, &u));
+    meta.oembed_url = meta.oembed_url.and_then(|u| resolve(base, &u));
+    meta
+}
+
+/// The parts of an oEmbed response that embeds use.
+#[derive(Debug, Default, Deserialize)]
+pub struct OEmbed {
+    pub title: Option<String>,
+    pub author_name: Option<String>,
+    pub author_url: Option<String>,
+    pub provider_name: Option<String>,
+    pub thumbnail_url: Option<String>,
+}
+
+/// Parse an oEmbed JSON response, or `None` if it isn't valid.
+#[must_use]
+pub fn parse_oembed(json: &[u8]) -> Option<OEmbed> {
+    let mut oembed: OEmbed = serde_json::from_slice(json).ok()?;
+    oembed.title = oembed.title.map(|t| truncate(t, MAX_TITLE_LENGTH));
+    Some(oembed)
+}
+
+fn parse_attributes(tag: &str) -> Vec<(String, &str)> {
+    let mut attrs = Vec::new();
+    let mut rest = tag.trim_end_matches('/');
+    loop {
+        rest = rest.trim_start();
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or_else(|| rest.len());
+        if name_end == 0 {
+            break;
+        }
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let value = if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            match after_eq.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let body = &after_eq[1..];
+                    let close = body.find(quote).unwrap_or_else(|| body.len());
+                    rest = body.get(close + 1..).unwrap_or_default();
+                    &body[..close]
+                }
+                _ => {
+                    let close = after_eq
+                        .find(char::is_whitespace)
+                        .unwrap_or_else(|| after_eq.len());
+                    rest = &after_eq[close..];
+                    &after_eq[..close]
+                }
+            }
+        } else {
+            ""
+        };
+        attrs.push((name, value));
+    }
+    attrs
+}
+
+fn attr<'a>(attrs: &[(String, &'a str)], name: &str) -> Option<&'a str> {
+    attrs.iter().find(|(n, _)| n == name).map(|(_, v)| *v)
+}
+
+fn resolve(base: &Url, link: &str) -> Option<String> {
+    base.join(link)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+        .map(String::from)
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        // done last, so escaped entities like `&amp;lt;` aren't decoded twice
+        .replace("&amp;", "&")
+}
+
+fn truncate(mut s: String, max: usize) -> String {
+    if s.len() > max {
+        let mut end = max;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://example.com/posts/1").unwrap()
+    }
+
+    #[test]
+    fn finds_links_in_order_without_duplicates() {
+        let links = find_links(
+            "see https://a.example/x, and (http://b.example/y) or https://a.example/x!",
+            5,
+        );
+        let links: Vec<_> = links.iter().map(Url::as_str).collect();
+        assert_eq!(links, ["https://a.example/x", "http://b.example/y"]);
+    }
+
+    #[test]
+    fn skips_suppressed_links_and_stops_at_max() {
+        let links = find_links(
+            "<https://a.example/> https://b.example/ https://c.example/",
+            1,
+        );
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].as_str(), "https://b.example/");
+    }
+
+    #[test]
+    fn prefers_opengraph_over_plain_html() {
+        let meta = parse_html(
+            r#"<html><head>
+                <title>Plain title</title>
+                <meta name="description" content="Plain description">
+                <meta property="og:title" content="OG &amp; title">
+                <meta property="og:description" content='OG description'>
+                <meta property="og:site_name" content="Example">
+                <meta property="og:image" content="/img.png">
+                <meta property="og:url" content="https://example.com/canonical">
+            </head><body><meta property="og:title" content="not in head"></body></html>"#,
+            &base(),
+        );
+        assert_eq!(meta.title.as_deref(), Some("OG & title"));
+        // `description` came first, and the first tag wins
+        assert_eq!(meta.description.as_deref(), Some("Plain description"));
+        assert_eq!(meta.site_name.as_deref(), Some("Example"));
+        assert_eq!(meta.image.as_deref(), Some("https://example.com/img.png"));
+        assert_eq!(
+            meta.canonical_url.as_deref(),
+            Some("https://example.com/canonical")
+        );
+        assert!(meta.oembed_url.is_none());
+    }
+
+    #[test]
+    fn falls_back_to_the_title_tag() {
+        let meta = parse_html("<head><TITLE> Just a page </TITLE></head>", &base());
+        assert_eq!(meta.title.as_deref(), Some("Just a page"));
+        assert!(!meta.is_empty());
+        assert!(parse_html("<head></head>", &base()).is_empty());
+    }
+
+    #[test]
+    fn finds_oembed_link() {
+        let meta = parse_html(
+            r#"<head><link rel="alternate" type="application/json+oembed" href="/oembed?url=1&amp;format=json"></head>"#,
+            &base(),
+        );
+        assert_eq!(
+            meta.oembed_url.as_deref(),
+            Some("https://example.com/oembed?url=1&format=json")
+        );
+    }
+
+    #[test]
+    fn drops_non_http_links() {
+        let meta = parse_html(
+            r#"<head><meta property="og:image" content="javascript:alert(1)"></head>"#,
+            &base(),
+        );
+        assert!(meta.image.is_none());
+    }
+
+    #[test]
+    fn cuts_off_long_titles_on_char_boundaries() {
+        let title = "é".repeat(MAX_TITLE_LENGTH);
+        let meta = parse_html(
+            &format!(
+                r#"<head><meta property="og:title" content="{}"></head>"#,
+                title
+            ),
+            &base(),
+        );
+        let cut = meta.title.unwrap();
+        assert!(cut.len() <= MAX_TITLE_LENGTH);
+        assert_eq!(cut, "é".repeat(MAX_TITLE_LENGTH / 2));
+    }
+
+    #[test]
+    fn parses_oembed() {
+        let oembed = parse_oembed(
+            br#"{"type": "video", "title": "A video", "author_name": "Ferris", "author_url": "https://example.com/ferris", "provider_name": "Example", "thumbnail_url": "https://example.com/thumb.png"}"#,
+        )
+        .unwrap();
+        assert_eq!(oembed.title.as_deref(), Some("A video"));
+        assert_eq!(oembed.author_name.as_deref(), Some("Ferris"));
+        assert_eq!(
+            oembed.author_url.as_deref(),
+            Some("https://example.com/ferris")
+        );
+        assert_eq!(oembed.provider_name.as_deref(), Some("Example"));
+        assert_eq!(
+            oembed.thumbnail_url.as_deref(),
+            Some("https://example.com/thumb.png")
+        );
+        assert!(parse_oembed(b"<html>").is_none());
+    }
+}
@@ -0,0 +1,441 @@
+//! Link embeds, generated in the background for links in new messages.
+//!
+//! Messages are sent without embeds, and queued with [`queue_unfurl`].
+//! Once a worker has fetched the links and stored their embeds, it fires a `MessageUpdate`.
+
+mod extract;
+mod policy;
+mod source;
+
+pub use extract::*;
+pub use policy::*;
+pub use source::*;
+
+use crate::ws::fire_event;
+use crate::WebServerError;
+use ferrischat_common::types::{Embed, EmbedAuthor, EmbedMedia, EmbedProvider, ErrorJson, Message};
+use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_config::EmbedsConfig;
+use num_traits::ToPrimitive;
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::lazy::SyncOnceCell as OnceCell;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+use url::Url;
+
+/// Maximum number of redirects followed for a single link.
+pub const MAX_REDIRECTS: usize = 5;
+
+/// Maximum number of messages having their links fetched at once.
+pub const MAX_CONCURRENT_UNFURLS: usize = 16;
+
+/// Maximum number of messages waiting to have their links fetched.
+/// Messages sent while the queue is full don't get embeds.
+pub const UNFURL_QUEUE_SIZE: usize = 1024;
+
+static UNFURL_QUEUE: OnceCell<mpsc::Sender<Message>> = OnceCell::new();
+
+fn get_config() -> EmbedsConfig {
+    ferrischat_config::GLOBAL_CONFIG
+        .get()
+        .map_or_else(EmbedsConfig::default, |c| c.embeds.clone())
+}
+
+/// Start the background worker that generates embeds, fetching links from `source`.
+///
+/// # Panics
+/// If the worker was already started.
+pub fn init_embed_worker(source: Arc<dyn HttpSource>) {
+    let (tx, mut rx) = mpsc::channel::<Message>(UNFURL_QUEUE_SIZE);
+    if UNFURL_QUEUE.set(tx).is_err() {
+        panic!("embed worker was already started: did you call init_embed_worker() twice?");
+    }
+
+    tokio::spawn(async move {
+        let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_UNFURLS));
+        while let Some(message) = rx.recv().await {
+            let permit = match permits.clone().acquire_owned().await {
+                Ok(p) => p,
+                Err(_) => break,
+            };
+            let source = source.clone();
+            tokio::spawn(async move {
+                let message_id = message.id;
+                if add_embeds(&*source, message).await.is_err() {
+                    warn!("failed to add embeds to message {}", message_id);
+                }
+                drop(permit);
+            });
+        }
+    });
+}
+
+/// Queue `message` to have embeds generated for its links.
+///
+/// Does nothing if embeds are disabled, or the message has no links.
+pub fn queue_unfurl(message: &Message) {
+    let config = get_config();
+    let content = match message.content {
+        Some(ref c) => c,
+        None => return,
+    };
+    if !config.enabled || find_links(content, 1).is_empty() {
+        return;
+    }
+
+    let queue = match UNFURL_QUEUE.get() {
+        Some(q) => q,
+        None => return,
+    };
+    if queue.try_send(message.clone()).is_err() {
+        warn!(
+            "embed queue is full, message {} won't get embeds",
+            message.id
+        );
+    }
+}
+
+/// Generate and store embeds for the links in `message`, then let clients know about them.
+///
+/// If the message was edited or deleted while its links were being fetched, nothing is stored.
+async fn add_embeds(source: &dyn HttpSource, message: Message) -> Result<(), WebServerError> {
+    let config = get_config();
+    let content = message.content.clone().unwrap_or_default();
+    let links = find_links(&content, config.max_embeds_per_message as usize);
+    let timeout = Duration::from_secs(config.fetch_timeout_secs);
+
+    let embeds: Vec<Embed> = futures::future::join_all(
+        links
+            .into_iter()
+            .map(|link| tokio::time::timeout(timeout, unfurl(source, &config, link))),
+    )
+    .await
+    .into_iter()
+    .filter_map(|r| r.ok().flatten())
+    .collect();
+    if embeds.is_empty() {
+        return Ok(());
+    }
+
+    let db = get_db_or_fail!();
+    let bigdecimal_message_id = u128_to_bigdecimal!(message.id);
+
+    let mut tx = db.begin().await?;
+    let current = sqlx::query!(
+        "SELECT content FROM messages WHERE id = $1 FOR UPDATE",
+        bigdecimal_message_id
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+    if current.map_or(true, |c| c.content != message.content) {
+        return Ok(());
+    }
+    for (position, embed) in embeds.iter().enumerate() {
+        sqlx::query!(
+            "INSERT INTO message_embeds VALUES ($1, $2, $3)",
+            bigdecimal_message_id,
+            i16::try_from(position).unwrap_or(i16::MAX),
+            serde_json::to_value(embed).map_err(|e| ErrorJson::new_500(
+                format!("failed to serialize embed: {}", e),
+                false,
+                None
+            ))?
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    let event = WsOutboundEvent::MessageUpdate {
+        old: message.clone(),
+        new: Message { embeds, ..message },
+    };
+    fire_event(&event).await?;
+
+    Ok(())
+}
+
+/// Fetch `link` and build an embed from its metadata, or `None` if there's nothing to show.
+pub async fn unfurl(source: &dyn HttpSource, config: &EmbedsConfig, link: Url) -> Option<Embed> {
+    let (url, resp) = fetch(source, config, link).await?;
+    if !is_html(resp.content_type.as_deref()) {
+        return None;
+    }
+    let meta = parse_html(&String::from_utf8_lossy(&resp.body), &url);
+
+    let oembed = match meta.oembed_url.as_deref().and_then(|u| Url::parse(u).ok()) {
+        Some(oembed_url) => fetch(source, config, oembed_url)
+            .await
+            .and_then(|(_, resp)| parse_oembed(&resp.body)),
+        None => None,
+    }
+    .unwrap_or_default();
+
+    if meta.is_empty() && oembed.title.is_none() && oembed.thumbnail_url.is_none() {
+        return None;
+    }
+
+    let site_name = meta.site_name.or(oembed.provider_name);
+    Some(Embed {
+        url: Some(meta.canonical_url.unwrap_or_else(|| url.to_string())),
+        title: meta.title.or(oembed.title),
+        description: meta.description,
+        provider: site_name.map(|name| EmbedProvider {
+            name: Some(name),
+            url: Some(url.origin().ascii_serialization()),
+        }),
+        author: oembed.author_name.map(|name| EmbedAuthor {
+            name,
+            url: oembed.author_url,
+            ..EmbedAuthor::default()
+        }),
+        thumbnail: meta.image.or(oembed.thumbnail_url).map(|url| EmbedMedia {
+            url,
+            ..EmbedMedia::default()
+        }),
+        ..Embed::default()
+    })
+}
+
+/// Fetch `link`, following redirects and checking every hop against `config`.
+///
+/// Returns the URL that was finally fetched along with its response,
+/// or `None` if it couldn't be fetched or didn't succeed.
+async fn fetch(
+    source: &dyn HttpSource,
+    config: &EmbedsConfig,
+    mut link: Url,
+) -> Option<(Url, FetchedResponse)> {
+    for _ in 0..=MAX_REDIRECTS {
+        if let Err(e) = check_url(&link, config) {
+            debug!("not fetching {}: {:?}", link, e);
+            return None;
+        }
+        let resp = match source.fetch(&link, config.max_response_size).await {
+            Ok(r) => r,
+            Err(e) => {
+                debug!("failed to fetch {}: {}", link, e);
+                return None;
+            }
+        };
+
+        match resp.status {
+            200..=299 => return Some((link, resp)),
+            300..=399 => link = link.join(resp.location.as_deref()?).ok()?,
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn is_html(content_type: Option<&str>) -> bool {
+    content_type.map_or(false, |c| {
+        let c = c.to_ascii_lowercase();
+        c.starts_with("text/html") || c.starts_with("application/xhtml+xml")
+    })
+}
+
+/// Fetch the stored embeds on each of `message_ids`, in the order their links appear.
+///
+/// Messages without any embeds are left out.
+pub async fn get_message_embeds(
+    db: &Pool<Postgres>,
+    message_ids: &[u128],
+) -> Result<HashMap<u128, Vec<Embed>>, WebServerError> {
+    let bigdecimal_message_ids: Vec<_> = message_ids
+        .iter()
+        .map(|id| u128_to_bigdecimal!(*id))
+        .collect();
+
+    let resp = sqlx::query!(
+        "SELECT * FROM message_embeds WHERE message_id = ANY($1) ORDER BY message_id, position",
+        &bigdecimal_message_ids[..]
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut embeds: HashMap<u128, Vec<Embed>> = HashMap::new();
+    for x in resp {
+        let message_id = match x
+            .message_id
+            .with_scale(0)
+            .into_bigint_and_exponent()
+            .0
+            .to_u128()
+        {
+            Some(id) => id,
+            None => continue,
+        };
+        // embeds stored by older versions may not match the current shape, and are skipped
+        if let Ok(embed) = serde_json::from_value(x.embed) {
+            embeds.entry(message_id).or_default().push(embed);
+        }
+    }
+
+    Ok(embeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn hop(i: usize) -> String {
+        format!("https://example.com/{}", i)
+    }
+
+    /// A chain of `redirects` redirects, starting at `hop(0)` and ending at a page.
+    fn redirect_chain(redirects: usize) -> MockSource {
+        let mut source = MockSource::new();
+        for i in 0..redirects {
+            source = source.with_redirect(&hop(i), &hop(i + 1));
+        }
+        source.with_page(&hop(redirects), "text/html", "<title>end</title>")
+    }
+
+    #[tokio::test]
+    async fn follows_redirects_up_to_the_limit() {
+        let config = EmbedsConfig::default();
+        let (final_url, resp) = fetch(&redirect_chain(MAX_REDIRECTS), &config, url(&hop(0)))
+            .await
+            .unwrap();
+        assert_eq!(final_url.as_str(), hop(MAX_REDIRECTS));
+        assert_eq!(resp.status, 200);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_too_many_redirects() {
+        let config = EmbedsConfig::default();
+        assert!(
+            fetch(&redirect_chain(MAX_REDIRECTS + 1), &config, url(&hop(0)))
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_follow_redirects_to_private_addresses() {
+        let config = EmbedsConfig::default();
+        for target in [
+            "http://127.0.0.1/admin",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::ffff:127.0.0.1]/admin",
+            "http://[fc00::1]/admin",
+        ] {
+            // the target would answer, so only the policy can stop it being fetched
+            let source = MockSource::new()
+                .with_redirect("https://example.com/", target)
+                .with_page(target, "text/html", "<title>secret</title>");
+            assert!(
+                fetch(&source, &config, url("https://example.com/"))
+                    .await
+                    .is_none(),
+                "{}",
+                target
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn does_not_follow_redirects_to_denied_domains() {
+        let config = EmbedsConfig {
+            denied_domains: vec!["evil.example".to_string()],
+            ..EmbedsConfig::default()
+        };
+        let source = MockSource::new()
+            .with_redirect("https://example.com/", "https://www.evil.example/")
+            .with_page(
+                "https://www.evil.example/",
+                "text/html",
+                "<title>evil</title>",
+            );
+        assert!(fetch(&source, &config, url("https://example.com/"))
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn cuts_off_responses_at_max_size() {
+        let config = EmbedsConfig {
+            max_response_size: 64,
+            ..EmbedsConfig::default()
+        };
+        let body = format!(
+            r#"<head><title>kept</title>{}<meta property="og:description" content="lost"></head>"#,
+            " ".repeat(64)
+        );
+        let source = MockSource::new().with_page("https://example.com/", "text/html", body);
+
+        let (_, resp) = fetch(&source, &config, url("https://example.com/"))
+            .await
+            .unwrap();
+        assert_eq!(resp.body.len(), 64);
+
+        let embed = unfurl(&source, &config, url("https://example.com/"))
+            .await
+            .unwrap();
+        assert_eq!(embed.title.as_deref(), Some("kept"));
+        assert!(embed.description.is_none());
+    }
+
+    #[tokio::test]
+    async fn builds_embeds_from_opengraph_and_oembed() {
+        let config = EmbedsConfig::default();
+        let source = MockSource::new()
+            .with_page(
+                "https://example.com/video",
+                "text/html; charset=utf-8",
+                r#"<head>
+                    <meta property="og:title" content="A video">
+                    <meta property="og:description" content="Watch it">
+                    <link rel="alternate" type="application/json+oembed" href="/oembed">
+                </head>"#,
+            )
+            .with_page(
+                "https://example.com/oembed",
+                "application/json",
+                r#"{"title": "Ignored", "author_name": "Ferris", "provider_name": "Example", "thumbnail_url": "https://example.com/thumb.png"}"#,
+            );
+
+        let embed = unfurl(&source, &config, url("https://example.com/video"))
+            .await
+            .unwrap();
+        assert_eq!(embed.url.as_deref(), Some("https://example.com/video"));
+        // OpenGraph wins over oEmbed, which only fills in what's missing
+        assert_eq!(embed.title.as_deref(), Some("A video"));
+        assert_eq!(embed.description.as_deref(), Some("Watch it"));
+        assert_eq!(embed.author.map(|a| a.name).as_deref(), Some("Ferris"));
+        assert_eq!(
+            embed.provider.and_then(|p| p.name).as_deref(),
+            Some("Example")
+        );
+        assert_eq!(
+            embed.thumbnail.map(|t| t.url).as_deref(),
+            Some("https://example.com/thumb.png")
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_pages_that_are_not_html_or_have_nothing_to_show() {
+        let config = EmbedsConfig::default();
+        let source = MockSource::new()
+            .with_page(
+                "https://example.com/file",
+                "application/zip",
+                "<title>zip</title>",
+            )
+            .with_page("https://example.com/empty", "text/html", "<head></head>");
+        for link in ["https://example.com/file", "https://example.com/empty"] {
+            assert!(
+                unfurl(&source, &config, url(link)).await.is_none(),
+                "{}",
+                link
+            );
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use ferrischat_config::EmbedsConfig;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use url::{Host, Url};
+
+/// Why a link can't be fetched.
+#[derive(Debug)]
+pub enum PolicyViolation {
+    /// Only `http` and `https` links are fetched.
+    UnsupportedScheme,
+    /// The link has no host, or the host is neither a domain nor an IP address.
+    MissingHost,
+    /// The domain isn't in the allowlist, or is in the denylist.
+    DomainNotAllowed,
+    /// The host is, or only resolves to, an address that isn't publicly routable.
+    PrivateAddress,
+}
+
+/// Make sure `url` may be fetched under `config`.
+///
+/// Domains are only checked against the allow and deny lists here.
+/// The addresses they resolve to have to be checked with [`is_public_ip`] when connecting.
+///
+/// # Errors
+/// Returns why the link may not be fetched.
+pub fn check_url(url: &Url, config: &EmbedsConfig) -> Result<(), PolicyViolation> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(PolicyViolation::UnsupportedScheme);
+    }
+
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            let allowed = config.allowed_domains.is_empty()
+                || config
+                    .allowed_domains
+                    .iter()
+                    .any(|d| domain_matches(&domain, d));
+            let denied = config
+                .denied_domains
+                .iter()
+                .any(|d| domain_matches(&domain, d));
+            if allowed && !denied {
+                Ok(())
+            } else {
+                Err(PolicyViolation::DomainNotAllowed)
+            }
+        }
+        // addresses can't be allowlisted, since there's no domain to match
+        Some(Host::Ipv4(ip)) if config.allowed_domains.is_empty() => {
+            public_or_violation(IpAddr::V4(ip))
+        }
+        Some(Host::Ipv6(ip)) if config.allowed_domains.is_empty() => {
+            public_or_violation(IpAddr::V6(ip))
+        }
+        Some(_) => Err(PolicyViolation::DomainNotAllowed),
+        None => Err(PolicyViolation::MissingHost),
+    }
+}
+
+fn public_or_violation(ip: IpAddr) -> Result<(), PolicyViolation> {
+    if is_public_ip(ip) {
+        Ok(())
+    } else {
+        Err(PolicyViolation::PrivateAddress)
+    }
+}
+
+/// Whether `domain` is `pattern` or one of its subdomains.
+fn domain_matches(domain: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim_end_matches('.').to_ascii_lowercase();
+    domain == pattern
+        || domain
+            .strip_suffix(&pattern)
+            .map_or(false, |prefix| prefix.ends_with('.'))
+}
+
+/// Whether `ip` is publicly routable, and so safe to make requests to on behalf of users.
+#[must_use]
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => {
+            // IPv4 addresses embedded in IPv6 ones are treated as the IPv4 address
+            if let Some(v4) = ip.to_ipv4() {
+                return is_public_ipv4(v4);
+            }
+            is_public_ipv6(ip)
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // "this network"
+        || a == 0
+        // shared address space used for carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && ip.octets()[2] == 0)
+        // benchmarking
+        || (a == 198 && (18..20).contains(&b))
+        // reserved
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // unique local
+        || (first & 0xfe00) == 0xfc00
+        // link local
+        || (first & 0xffc0) == 0xfe80
+        // documentation
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn allowing(domains: &[&str]) -> EmbedsConfig {
+        EmbedsConfig {
+            allowed_domains: domains.iter().map(ToString::to_string).collect(),
+            ..EmbedsConfig::default()
+        }
+    }
+
+    #[test]
+    fn private_ipv4_addresses_are_not_public() {
+        for addr in [
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "0.1.2.3",
+            "100.64.0.1",
+            "100.127.255.255",
+            "192.0.0.8",
+            "198.18.0.1",
+            "224.0.0.1",
+            "240.0.0.1",
+            "255.255.255.255",
+        ] {
+            assert!(!is_public_ip(ip(addr)), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn public_ipv4_addresses_are_public() {
+        // either side of the shared address space is public
+        for addr in ["1.1.1.1", "8.8.8.8", "100.63.255.255", "100.128.0.0"] {
+            assert!(is_public_ip(ip(addr)), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn private_ipv6_addresses_are_not_public() {
+        for addr in [
+            "::1",
+            "::",
+            "fc00::1",
+            "fd12:3456:789a::1",
+            "fe80::1",
+            "ff02::1",
+            "2001:db8::1",
+            // IPv4 addresses inside IPv6 ones are checked as IPv4
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+            "::ffff:100.64.0.1",
+        ] {
+            assert!(!is_public_ip(ip(addr)), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn public_ipv6_addresses_are_public() {
+        for addr in ["2606:4700:4700::1111", "::ffff:1.1.1.1"] {
+            assert!(is_public_ip(ip(addr)), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn only_http_links_are_fetched() {
+        let config = EmbedsConfig::default();
+        assert!(check_url(&url("https://example.com/"), &config).is_ok());
+        assert!(check_url(&url("http://example.com/"), &config).is_ok());
+        assert!(matches!(
+            check_url(&url("ftp://example.com/"), &config),
+            Err(PolicyViolation::UnsupportedScheme)
+        ));
+        assert!(matches!(
+            check_url(&url("file:///etc/passwd"), &config),
+            Err(PolicyViolation::UnsupportedScheme)
+        ));
+    }
+
+    #[test]
+    fn private_ip_literals_are_refused() {
+        let config = EmbedsConfig::default();
+        for link in [
+            "http://127.0.0.1/",
+            "http://100.64.0.1/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[fc00::1]/",
+        ] {
+            assert!(
+                matches!(
+                    check_url(&url(link), &config),
+                    Err(PolicyViolation::PrivateAddress)
+                ),
+                "{}",
+                link
+            );
+        }
+        assert!(check_url(&url("http://1.1.1.1/"), &config).is_ok());
+    }
+
+    #[test]
+    fn allowlist_includes_subdomains_only() {
+        let config = allowing(&["example.com"]);
+        assert!(check_url(&url("https://example.com/"), &config).is_ok());
+        assert!(check_url(&url("https://www.EXAMPLE.com./"), &config).is_ok());
+        assert!(matches!(
+            check_url(&url("https://notexample.com/"), &config),
+            Err(PolicyViolation::DomainNotAllowed)
+        ));
+        assert!(matches!(
+            check_url(&url("https://example.com.evil.net/"), &config),
+            Err(PolicyViolation::DomainNotAllowed)
+        ));
+    }
+
+    #[test]
+    fn allowlist_refuses_ip_literals() {
+        // even public addresses, since there's no domain to match against the allowlist
+        let config = allowing(&["example.com"]);
+        for link in ["http://1.1.1.1/", "http://[2606:4700:4700::1111]/"] {
+            assert!(
+                matches!(
+                    check_url(&url(link), &config),
+                    Err(PolicyViolation::DomainNotAllowed)
+                ),
+                "{}",
+                link
+            );
+        }
+    }
+
+    #[test]
+    fn denylist_wins_over_allowlist() {
+        let config = EmbedsConfig {
+            denied_domains: vec!["internal.example.com".to_string()],
+            ..allowing(&["example.com"])
+        };
+        assert!(check_url(&url("https://example.com/"), &config).is_ok());
+        assert!(matches!(
+            check_url(&url("https://api.internal.example.com/"), &config),
+            Err(PolicyViolation::DomainNotAllowed)
+        ));
+    }
+}
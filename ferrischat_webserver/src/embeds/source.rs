@@ -0,0 +1,212 @@
+use super::policy::is_public_ip;
+use bytes::{Bytes, BytesMut};
+use hyper::client::connect::dns::Name;
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use hyper::{Body, Client, Request};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use url::Url;
+
+/// User agent sent when fetching links.
+pub const USER_AGENT: &str = "Mozilla/5.0 (compatible; FerrisChatBot/0.1; +https://ferris.chat)";
+
+/// A response to a single request, without any redirects followed.
+#[derive(Clone)]
+pub struct FetchedResponse {
+    pub status: u16,
+    /// Where the response redirects to, if it's a redirect.
+    pub location: Option<String>,
+    pub content_type: Option<String>,
+    /// The response body, cut off at the requested maximum size.
+    pub body: Bytes,
+}
+
+/// Somewhere links can be fetched from.
+#[async_trait::async_trait]
+pub trait HttpSource: Send + Sync {
+    /// Make a GET request to `url`, reading at most `max_size` bytes of the body.
+    ///
+    /// Redirects must not be followed, so every hop can be checked against the embed policy.
+    async fn fetch(&self, url: &Url, max_size: usize) -> std::io::Result<FetchedResponse>;
+}
+
+/// Fetches links over the internet, refusing to connect to addresses that aren't public.
+pub struct HyperSource {
+    client: Client<hyper_rustls::HttpsConnector<HttpConnector<PublicResolver>>>,
+}
+
+impl HyperSource {
+    #[must_use]
+    pub fn new() -> Self {
+        let mut http = HttpConnector::new_with_resolver(PublicResolver);
+        http.enforce_http(false);
+        let https = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http1()
+            .wrap_connector(http);
+
+        Self {
+            client: Client::builder().build(https),
+        }
+    }
+}
+
+impl Default for HyperSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpSource for HyperSource {
+    async fn fetch(&self, url: &Url, max_size: usize) -> std::io::Result<FetchedResponse> {
+        let req = Request::get(url.as_str())
+            .header(http::header::USER_AGENT, USER_AGENT)
+            .header(
+                http::header::ACCEPT,
+                "text/html, application/json;q=0.9, */*;q=0.1",
+            )
+            .body(Body::empty())
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let resp = self
+            .client
+            .request(req)
+            .await
+            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+
+        let status = resp.status().as_u16();
+        let location = header_string(resp.headers(), http::header::LOCATION);
+        let content_type = header_string(resp.headers(), http::header::CONTENT_TYPE);
+
+        let mut body = resp.into_body();
+        let mut data = BytesMut::new();
+        while let Some(chunk) = hyper::body::HttpBody::data(&mut body).await {
+            let chunk = chunk.map_err(|e| Error::new(ErrorKind::Other, e))?;
+            let remaining = max_size - data.len();
+            if chunk.len() >= remaining {
+                // the rest of the body is never read, and the connection is dropped with it
+                data.extend_from_slice(&chunk[..remaining]);
+                break;
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        Ok(FetchedResponse {
+            status,
+            location,
+            content_type,
+            body: data.freeze(),
+        })
+    }
+}
+
+fn header_string(headers: &http::HeaderMap, name: http::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string)
+}
+
+/// Resolves domains like the system resolver, but leaves out any address that isn't public.
+///
+/// Checking addresses here rather than before connecting means a domain can't resolve
+/// to a public address when checked and a private one when connected to.
+#[derive(Clone, Copy)]
+pub struct PublicResolver;
+
+impl Service<Name> for PublicResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        Box::pin(async move {
+            let addrs: Vec<_> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    format!("{} has no public addresses", name),
+                ));
+            }
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+/// Serves canned responses instead of making requests, so unfurling can be tested offline.
+///
+/// Links without a response set up fail as if the host couldn't be reached.
+#[derive(Default)]
+pub struct MockSource {
+    responses: HashMap<String, FetchedResponse>,
+}
+
+impl MockSource {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Respond to requests for `url` with a 200 and `body` of type `content_type`.
+    #[must_use]
+    pub fn with_page(self, url: &str, content_type: &str, body: impl Into<Bytes>) -> Self {
+        self.with_response(
+            url,
+            FetchedResponse {
+                status: 200,
+                location: None,
+                content_type: Some(content_type.to_string()),
+                body: body.into(),
+            },
+        )
+    }
+
+    /// Respond to requests for `url` with a redirect to `location`.
+    #[must_use]
+    pub fn with_redirect(self, url: &str, location: &str) -> Self {
+        self.with_response(
+            url,
+            FetchedResponse {
+                status: 302,
+                location: Some(location.to_string()),
+                content_type: None,
+                body: Bytes::new(),
+            },
+        )
+    }
+
+    /// Respond to requests for `url` with `response`.
+    #[must_use]
+    pub fn with_response(mut self, url: &str, response: FetchedResponse) -> Self {
+        // parse the URL so lookups match however `fetch` gets passed it
+        let key = Url::parse(url).map_or_else(|_| url.to_string(), String::from);
+        self.responses.insert(key, response);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpSource for MockSource {
+    async fn fetch(&self, url: &Url, max_size: usize) -> std::io::Result<FetchedResponse> {
+        let mut resp = self
+            .responses
+            .get(url.as_str())
+            .cloned()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no mock for {}", url)))?;
+        resp.body.truncate(max_size);
+        Ok(resp)
+    }
+}
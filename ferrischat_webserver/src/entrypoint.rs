@@ -8,6 +8,11 @@ use axum::Router;
 #[allow(clippy::expect_used)]
 pub async fn entrypoint() {
     init_rng();
+    crate::storage::init_storage();
+    crate::embeds::init_embed_worker(std::sync::Arc::new(crate::embeds::HyperSource::new()));
+    crate::channels::threads::init_thread_archiver();
+    crate::channels::typing::init_typing_sweeper();
+    crate::messages::init_revision_pruner();
 
     let router = Router::new()
         // GET    /teapot
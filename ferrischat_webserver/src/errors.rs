@@ -21,6 +21,8 @@ pub enum WebServerError {
     Http(ErrorJson),
     RandomGenerationFailure,
     MissingNodeId,
+    Storage(std::io::Error),
+    MissingStorage,
 }
 
 impl From<PoolError> for WebServerError {
@@ -47,6 +49,12 @@ impl From<RedisError> for WebServerError {
     }
 }
 
+impl From<std::io::Error> for WebServerError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Storage(e)
+    }
+}
+
 impl From<ErrorJson> for WebServerError {
     fn from(e: ErrorJson) -> Self {
         Self::Http(e)
@@ -172,6 +180,9 @@ impl IntoResponse for WebServerError {
                 ),
             ),
 
+            WebServerError::Storage(e) => ErrorJson::new_500(format!("Storage backend returned an error: {}", e), false, None),
+            WebServerError::MissingStorage => ErrorJson::new_500("Storage backend was not found".to_string(), false, None),
+
             WebServerError::MissingNodeId => ErrorJson::new_500(
                 "Redis has not been set up yet".to_string(),
                 true,
@@ -2,7 +2,7 @@ use crate::ws::fire_event;
 use crate::WebServerError;
 use axum::Json;
 use ferrischat_common::request_json::GuildCreateJson;
-use ferrischat_common::types::{ErrorJson, Guild, GuildFlags, Member, ModelType};
+use ferrischat_common::types::{ErrorJson, Guild, GuildFlags, Member, ModelType, Role};
 use ferrischat_common::ws::WsOutboundEvent;
 use ferrischat_snowflake_generator::generate_snowflake;
 
@@ -23,14 +23,14 @@ pub async fn create_guild(
     let bigdecimal_user_id = u128_to_bigdecimal!(user_id);
     let GuildCreateJson { name } = guild_info.0;
 
-    sqlx::query!(
-        "INSERT INTO guilds(id, owner_id, name, flags) VALUES ($1, $2, $3, $4)",
+    let guild = sqlx::query!(
+        "INSERT INTO guilds(id, owner_id, name, flags) VALUES ($1, $2, $3, $4) RETURNING revision_retention_days",
         bigdecimal_guild_id,
         bigdecimal_user_id,
         name,
         0
     )
-    .execute(db)
+    .fetch_one(db)
     .await?;
 
     sqlx::query!(
@@ -41,6 +41,17 @@ pub async fn create_guild(
     .execute(db)
     .await?;
 
+    let everyone_perms =
+        ferrischat_perms::permissions_to_bytes(ferrischat_perms::DEFAULT_PERMISSIONS);
+    sqlx::query!(
+        "INSERT INTO roles(id, name, position, permissions, parent_guild) VALUES ($1, $2, 0, $3, $1)",
+        bigdecimal_guild_id,
+        ferrischat_perms::EVERYONE_ROLE_NAME,
+        &everyone_perms[..]
+    )
+    .execute(db)
+    .await?;
+
     let guild_obj = Guild {
         id: guild_id,
         owner_id: user_id,
@@ -52,9 +63,18 @@ pub async fn create_guild(
             user_id: Some(user_id),
             user: None,
             guild: None,
+            nickname: None,
+        }]),
+        roles: Some(vec![Role {
+            id: guild_id,
+            name: ferrischat_perms::EVERYONE_ROLE_NAME.to_string(),
+            color: None,
+            position: 0,
+            guild_id,
+            guild_permissions: ferrischat_perms::DEFAULT_PERMISSIONS,
         }]),
-        roles: None,
         icon: None,
+        revision_retention_days: Some(guild.revision_retention_days),
     };
 
     let event = WsOutboundEvent::GuildCreate {
@@ -25,11 +25,12 @@ pub async fn delete_guild(
     }
 
     let guild_resp = sqlx::query!(
-        "DELETE FROM guilds WHERE id = $1 RETURNING *",
+        r#"DELETE FROM guilds WHERE id = $1 RETURNING *, ARRAY(SELECT a.id FROM attachments a INNER JOIN messages m ON m.id = a.message_id INNER JOIN channels c ON c.id = m.channel_id WHERE c.guild_id = guilds.id) AS "attachment_ids!""#,
         bigdecimal_guild_id,
     )
     .fetch_one(db)
     .await?;
+    crate::messages::attachments::delete_stored_attachments(guild_resp.attachment_ids).await;
     let guild_obj = Guild {
         id: guild_id,
         owner_id: auth_user,
@@ -41,9 +42,11 @@ pub async fn delete_guild(
             user_id: Some(auth_user),
             user: None,
             guild: None,
+            nickname: None,
         }]),
         roles: None,
         icon: guild_resp.icon,
+        revision_retention_days: Some(guild_resp.revision_retention_days),
     };
 
     let event = WsOutboundEvent::GuildDelete {
@@ -2,17 +2,29 @@ use crate::ws::fire_event;
 use crate::WebServerError;
 use axum::extract::Path;
 use axum::Json;
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::GuildUpdateJson;
-use ferrischat_common::types::{ErrorJson, Guild, GuildFlags};
+use ferrischat_common::types::{AuditLogAction, ErrorJson, Guild, GuildFlags};
 use ferrischat_common::ws::WsOutboundEvent;
 
+/// Maximum number of days a guild can keep message revisions for.
+pub const MAX_REVISION_RETENTION_DAYS: i32 = 3650;
+
+/// PATCH `/v0/guilds/{guild_id}`
 pub async fn edit_guild(
     Path(guild_id): Path<u128>,
-    Json(GuildUpdateJson { name, icon }): Json<GuildUpdateJson>,
-    _: crate::Authorization,
+    Json(GuildUpdateJson {
+        name,
+        icon,
+        revision_retention_days,
+    }): Json<GuildUpdateJson>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
 ) -> Result<crate::Json<Guild>, WebServerError> {
     let db = get_db_or_fail!();
 
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::MANAGE_GUILD).await?;
+
     let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
 
     let guild = sqlx::query!("SELECT * FROM guilds WHERE id = $1", bigdecimal_guild_id)
@@ -28,15 +40,18 @@ pub async fn edit_guild(
         members: None,
         roles: None,
         icon: guild.icon,
+        revision_retention_days: Some(guild.revision_retention_days),
     };
 
+    let mut tx = db.begin().await?;
+
     if let Some(name) = name {
         sqlx::query!(
             "UPDATE guilds SET name = $1 WHERE id = $2",
             name,
             bigdecimal_guild_id
         )
-        .execute(db)
+        .execute(&mut tx)
         .await?;
     }
 
@@ -46,12 +61,29 @@ pub async fn edit_guild(
             icon,
             bigdecimal_guild_id
         )
-        .execute(db)
+        .execute(&mut tx)
+        .await?;
+    }
+
+    if let Some(revision_retention_days) = revision_retention_days {
+        if !(0..=MAX_REVISION_RETENTION_DAYS).contains(&revision_retention_days) {
+            return Err(ErrorJson::new_400(format!(
+                "revision_retention_days must be between 0 and {}",
+                MAX_REVISION_RETENTION_DAYS
+            ))
+            .into());
+        }
+        sqlx::query!(
+            "UPDATE guilds SET revision_retention_days = $1 WHERE id = $2",
+            revision_retention_days,
+            bigdecimal_guild_id
+        )
+        .execute(&mut tx)
         .await?;
     }
 
     let guild = sqlx::query!("SELECT * FROM guilds WHERE id = $1", bigdecimal_guild_id)
-        .fetch_optional(db)
+        .fetch_optional(&mut tx)
         .await?
         .ok_or_else(|| ErrorJson::new_404(format!("Unknown guild with ID {}", guild_id)))?;
     let new_guild_obj = Guild {
@@ -63,11 +95,25 @@ pub async fn edit_guild(
         members: None,
         roles: None,
         icon: guild.icon,
+        revision_retention_days: Some(guild.revision_retention_days),
     };
 
     // TODO: impl Eq for all types
     // if old_guild_obj == new_guild_obj {}
 
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::GuildUpdate,
+        Some(guild_id),
+        Some(&old_guild_obj),
+        Some(&new_guild_obj),
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
     let event = WsOutboundEvent::GuildUpdate {
         old: old_guild_obj,
         new: new_guild_obj.clone(),
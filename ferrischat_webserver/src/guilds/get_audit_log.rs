@@ -0,0 +1,83 @@
+use crate::WebServerError;
+use axum::extract::{Path, Query};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::request_json::GetAuditLogParams;
+use ferrischat_common::types::{AuditLog, AuditLogAction, AuditLogEntry, ErrorJson};
+use num_traits::ToPrimitive;
+
+/// Maximum number of audit log entries fetched at once.
+pub const MAX_AUDIT_LOG_PAGE: i64 = 100;
+
+/// GET `/v0/guilds/{guild_id}/audit-log`
+pub async fn get_audit_log(
+    Path(guild_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    Query(GetAuditLogParams {
+        user_id,
+        action_type,
+        limit,
+        mut offset,
+    }): Query<GetAuditLogParams>,
+) -> Result<crate::Json<AuditLog>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::MANAGE_GUILD).await?;
+
+    let limit = limit.unwrap_or(50);
+    if !(1..=MAX_AUDIT_LOG_PAGE).contains(&limit) {
+        return Err(ErrorJson::new_400(format!(
+            "limit must be between 1 and {}",
+            MAX_AUDIT_LOG_PAGE
+        ))
+        .into());
+    }
+
+    if offset < Some(0) {
+        offset = Some(0);
+    }
+
+    let entries = sqlx::query!(
+        r#"
+SELECT *
+FROM audit_log
+WHERE guild_id = $1
+  AND ($2::numeric(39) IS NULL OR actor_id = $2)
+  AND ($3::smallint IS NULL OR action_type = $3)
+ORDER BY id DESC
+LIMIT $4 OFFSET $5
+"#,
+        u128_to_bigdecimal!(guild_id),
+        user_id.map(|id| u128_to_bigdecimal!(id)),
+        action_type.map(|a| a as i16),
+        limit,
+        offset,
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .filter_map(|x| {
+        Some(AuditLogEntry {
+            id: x.id.with_scale(0).into_bigint_and_exponent().0.to_u128()?,
+            guild_id,
+            user_id: x
+                .actor_id
+                .with_scale(0)
+                .into_bigint_and_exponent()
+                .0
+                .to_u128()?,
+            action_type: AuditLogAction::from_i16(x.action_type)?,
+            target_id: x
+                .target_id
+                .and_then(|id| id.with_scale(0).into_bigint_and_exponent().0.to_u128()),
+            before: x.before,
+            after: x.after,
+            reason: x.reason,
+        })
+    })
+    .collect();
+
+    Ok(crate::Json {
+        obj: AuditLog { entries },
+        code: 200,
+    })
+}
@@ -1,16 +1,21 @@
 use crate::WebServerError;
 use axum::extract::{Path, Query};
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::GetGuildUrlParams;
-use ferrischat_common::types::{Channel, ErrorJson, Guild, GuildFlags, Member, User, UserFlags};
+use ferrischat_common::types::{
+    Channel, ChannelType, ErrorJson, Guild, GuildFlags, Member, User, UserFlags,
+};
 use num_traits::ToPrimitive;
 
 /// GET `/v0/guilds/{guild_id}`
 pub async fn get_guild(
-    crate::Authorization(_, _): crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
     Path(guild_id): Path<u128>,
     Query(params): Query<GetGuildUrlParams>,
 ) -> Result<crate::Json<Guild>, WebServerError> {
     let db = get_db_or_fail!();
+
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::empty()).await?;
     let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
 
     let guild = sqlx::query!("SELECT * FROM guilds WHERE id = $1", bigdecimal_guild_id)
@@ -20,8 +25,9 @@ pub async fn get_guild(
 
     let channels: Option<Vec<Channel>> = if params.channels.unwrap_or(true) {
         let resp = sqlx::query!(
-            "SELECT * FROM channels WHERE guild_id = $1",
-            bigdecimal_guild_id
+            "SELECT * FROM channels WHERE guild_id = $1 AND channel_type != $2 ORDER BY position, id",
+            bigdecimal_guild_id,
+            ChannelType::Thread as i16
         )
         .fetch_all(db)
         .await?;
@@ -31,12 +37,19 @@ pub async fn get_guild(
                     Some(Channel {
                         id: x.id.with_scale(0).into_bigint_and_exponent().0.to_u128()?,
                         name: x.name.clone(),
-                        guild_id: x
-                            .guild_id
-                            .with_scale(0)
-                            .into_bigint_and_exponent()
-                            .0
-                            .to_u128()?,
+                        guild_id: Some(guild_id),
+                        channel_type: ChannelType::from_i16(x.channel_type).unwrap_or_default(),
+                        parent_id: x
+                            .parent_id
+                            .as_ref()
+                            .and_then(|p| p.with_scale(0).into_bigint_and_exponent().0.to_u128()),
+                        position: x.position,
+                        topic: x.topic.clone(),
+                        nsfw: x.nsfw,
+                        owner_id: None,
+                        recipients: None,
+                        thread_metadata: None,
+                        permission_overwrites: None,
                     })
                 })
                 .collect(),
@@ -95,6 +108,7 @@ pub async fn get_guild(
                         }),
                         guild_id: Some(guild_id),
                         guild: None,
+                        nickname: x.nickname.clone(),
                     })
                 })
                 .collect(),
@@ -111,8 +125,9 @@ pub async fn get_guild(
             flags: GuildFlags::from_bits_truncate(guild.flags),
             channels,
             members,
-            roles: None,
+            roles: Some(ferrischat_perms::get_guild_roles(db, guild_id).await?),
             icon: guild.icon,
+            revision_retention_days: Some(guild.revision_retention_days),
         },
         code: 200,
     })
@@ -1,13 +1,17 @@
 mod create_guild;
 mod delete_guild;
 mod edit_guild;
+mod get_audit_log;
 mod get_guild;
 pub mod roles;
+mod transfer_ownership;
 
 pub use create_guild::*;
 pub use delete_guild::*;
 pub use edit_guild::*;
+pub use get_audit_log::*;
 pub use get_guild::*;
+pub use transfer_ownership::*;
 
 use axum::routing::{get, post};
 use axum::Router;
@@ -24,6 +28,16 @@ pub fn generate_guilds_routes() -> axum::Router {
             expand_version!("guilds/:guild_id"),
             get(get_guild).patch(edit_guild).delete(delete_guild),
         )
+        // GET    /guilds/:guild_id/audit-log
+        .route(
+            expand_version!("guilds/:guild_id/audit-log"),
+            get(get_audit_log),
+        )
+        // POST   /guilds/:guild_id/transfer
+        .route(
+            expand_version!("guilds/:guild_id/transfer"),
+            post(transfer_ownership),
+        )
         // roles routes
         .merge(roles::generate_roles_routes())
 }
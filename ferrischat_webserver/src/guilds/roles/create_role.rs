@@ -4,19 +4,25 @@ use axum::extract::Path;
 use axum::Json;
 use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::RoleCreateJson;
-use ferrischat_common::types::{ModelType, Role};
+use ferrischat_common::types::{AuditLogAction, ModelType, Role};
 use ferrischat_common::ws::WsOutboundEvent;
 use ferrischat_macros::get_db_or_fail;
+use ferrischat_perms::Rank;
 use ferrischat_snowflake_generator::generate_snowflake;
 
 /// POST `/v0/guilds/{guild_id}/roles`
 pub async fn create_role(
-    _: crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
     role_info: Json<RoleCreateJson>,
     Path(guild_id): Path<u128>,
 ) -> Result<crate::Json<Role>, WebServerError> {
     let db = get_db_or_fail!();
 
+    let auth_perms =
+        crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::MANAGE_ROLES)
+            .await?;
+
     let RoleCreateJson {
         name,
         color,
@@ -28,23 +34,27 @@ pub async fn create_role(
     let position = position.unwrap_or(0);
     let permissions = guild_permissions.unwrap_or_else(GuildPermissions::empty);
 
+    crate::require_rank_above(guild_id, auth_user, Rank::of_role(position)).await?;
+    crate::require_can_grant(auth_perms, permissions)?;
+
     let node_id = get_node_id!();
     let role_id = generate_snowflake::<0>(ModelType::Role as u8, node_id);
     let bigdecimal_role_id = u128_to_bigdecimal!(role_id);
 
     let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
 
-    let perms = b"".as_slice();
+    let perms = ferrischat_perms::permissions_to_bytes(permissions);
+    let mut tx = db.begin().await?;
     sqlx::query!(
         "INSERT INTO roles VALUES ($1, $2, $3, $4, $5, $6)",
         bigdecimal_role_id,
         name,
         color,
         position,
-        perms,
+        &perms[..],
         bigdecimal_guild_id
     )
-    .execute(db)
+    .execute(&mut tx)
     .await?;
 
     let role_obj = Role {
@@ -56,6 +66,19 @@ pub async fn create_role(
         guild_permissions: permissions,
     };
 
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::RoleCreate,
+        Some(role_id),
+        None,
+        Some(&role_obj),
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
     let event = WsOutboundEvent::RoleCreate {
         role: role_obj.clone(),
     };
@@ -2,25 +2,46 @@ use crate::ws::fire_event;
 use crate::WebServerError;
 use axum::extract::Path;
 use ferrischat_common::perms::GuildPermissions;
-use ferrischat_common::types::{ErrorJson, Role};
+use ferrischat_common::types::{AuditLogAction, ErrorJson, Role};
 use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_perms::Rank;
 use http::StatusCode;
 
 /// DELETE `/v0/guilds/{guild_id/roles/{role_id}`
 pub async fn delete_role(
     Path((guild_id, role_id)): Path<(u128, u128)>,
-    _: crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
 ) -> Result<StatusCode, WebServerError> {
     let db = get_db_or_fail!();
+
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::MANAGE_ROLES).await?;
+
+    if ferrischat_perms::is_everyone_role(guild_id, role_id) {
+        return Err(ErrorJson::new_400("the everyone role cannot be deleted".to_string()).into());
+    }
+
     let bigdecimal_role_id = u128_to_bigdecimal!(role_id);
     let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
 
+    let position = sqlx::query!(
+        "SELECT position FROM roles WHERE id = $1 AND parent_guild = $2",
+        bigdecimal_role_id,
+        bigdecimal_guild_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown role with ID {}", role_id)))?
+    .position;
+    crate::require_rank_above(guild_id, auth_user, Rank::of_role(position)).await?;
+
+    let mut tx = db.begin().await?;
     let role = sqlx::query!(
         "DELETE FROM roles WHERE id = $1 AND parent_guild = $2 RETURNING *",
         bigdecimal_role_id,
         bigdecimal_guild_id
     )
-    .fetch_optional(db)
+    .fetch_optional(&mut tx)
     .await?
     .ok_or_else(|| ErrorJson::new_404(format!("Unknown role with ID {}", role_id)))?;
     let role_obj = Role {
@@ -29,9 +50,22 @@ pub async fn delete_role(
         name: role.name,
         color: role.color,
         position: role.position,
-        guild_permissions: GuildPermissions::empty(),
+        guild_permissions: ferrischat_perms::permissions_from_bytes(&role.permissions),
     };
 
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::RoleDelete,
+        Some(role_id),
+        Some(&role_obj),
+        None,
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
     let event = WsOutboundEvent::RoleDelete {
         role: role_obj.clone(),
     };
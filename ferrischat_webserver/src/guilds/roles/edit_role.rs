@@ -4,43 +4,69 @@ use axum::extract::Path;
 use axum::Json;
 use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::RoleUpdateJson;
-use ferrischat_common::types::{ErrorJson, Role};
+use ferrischat_common::types::{AuditLogAction, ErrorJson, Role};
 use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_perms::Rank;
 
+/// PATCH `/v0/guilds/{guild_id}/roles/{role_id}`
 pub async fn edit_role(
-    Path((_, role_id)): Path<(u128, u128)>,
+    Path((guild_id, role_id)): Path<(u128, u128)>,
     Json(RoleUpdateJson {
         name,
         color,
         position,
         permissions,
     }): Json<RoleUpdateJson>,
-    _: crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
 ) -> Result<crate::Json<Role>, WebServerError> {
     let bigdecimal_role_id = u128_to_bigdecimal!(role_id);
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
 
     let db = get_db_or_fail!();
 
-    let role = sqlx::query!("SELECT * FROM roles WHERE id = $1", bigdecimal_role_id)
-        .fetch_optional(db)
-        .await?
-        .ok_or_else(|| ErrorJson::new_404(format!("Unknown role with ID {}", role_id)))?;
+    let auth_perms =
+        crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::MANAGE_ROLES)
+            .await?;
+
+    let role = sqlx::query!(
+        "SELECT * FROM roles WHERE id = $1 AND parent_guild = $2",
+        bigdecimal_role_id,
+        bigdecimal_guild_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown role with ID {}", role_id)))?;
+
+    crate::require_rank_above(guild_id, auth_user, Rank::of_role(role.position)).await?;
+    if let Some(position) = position {
+        if ferrischat_perms::is_everyone_role(guild_id, role_id) {
+            return Err(ErrorJson::new_400("the everyone role cannot be moved".to_string()).into());
+        }
+        crate::require_rank_above(guild_id, auth_user, Rank::of_role(position)).await?;
+    }
+    if let Some(permissions) = permissions {
+        crate::require_can_grant(auth_perms, permissions)?;
+    }
+
     let old_role_obj = Role {
         id: bigdecimal_to_u128!(role.id),
         name: role.name,
         color: role.color,
         position: role.position,
         guild_id: bigdecimal_to_u128!(role.parent_guild),
-        guild_permissions: GuildPermissions::empty(),
+        guild_permissions: ferrischat_perms::role_permissions(guild_id, role_id, &role.permissions),
     };
 
+    let mut tx = db.begin().await?;
+
     if let Some(name) = name {
         sqlx::query!(
             "UPDATE roles SET name = $1 WHERE id = $2",
             name,
             bigdecimal_role_id
         )
-        .execute(db)
+        .execute(&mut tx)
         .await?;
     };
 
@@ -50,7 +76,7 @@ pub async fn edit_role(
             color,
             bigdecimal_role_id
         )
-        .execute(db)
+        .execute(&mut tx)
         .await?;
     }
 
@@ -60,23 +86,23 @@ pub async fn edit_role(
             position,
             bigdecimal_role_id
         )
-        .execute(db)
+        .execute(&mut tx)
         .await?;
     }
 
-    if let Some(_permissions) = permissions {
-        let perms = b"".as_slice();
+    if let Some(permissions) = permissions {
+        let perms = ferrischat_perms::permissions_to_bytes(permissions);
         sqlx::query!(
             "UPDATE roles SET permissions = $1 WHERE id = $2",
-            perms,
+            &perms[..],
             bigdecimal_role_id
         )
-        .execute(db)
+        .execute(&mut tx)
         .await?;
     }
 
     let role = sqlx::query!("SELECT * FROM roles WHERE id = $1", bigdecimal_role_id)
-        .fetch_optional(db)
+        .fetch_optional(&mut tx)
         .await?
         .ok_or_else(|| ErrorJson::new_404(format!("Unknown role with ID {}", role_id)))?;
     let new_role_obj = Role {
@@ -85,9 +111,22 @@ pub async fn edit_role(
         color: role.color,
         position: role.position,
         guild_id: bigdecimal_to_u128!(role.parent_guild),
-        guild_permissions: GuildPermissions::empty(),
+        guild_permissions: ferrischat_perms::role_permissions(guild_id, role_id, &role.permissions),
     };
 
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::RoleUpdate,
+        Some(role_id),
+        Some(&old_role_obj),
+        Some(&new_role_obj),
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
     let event = WsOutboundEvent::RoleUpdate {
         old: old_role_obj,
         new: new_role_obj.clone(),
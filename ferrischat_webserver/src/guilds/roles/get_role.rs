@@ -5,14 +5,21 @@ use ferrischat_common::types::{ErrorJson, Role};
 
 /// GET `/v0/guilds/{guild_id/roles/{role_id}`
 pub async fn get_role(
-    Path(role_id): Path<u128>,
-    _: crate::Authorization,
+    Path((guild_id, role_id)): Path<(u128, u128)>,
+    crate::Authorization(auth_user, _): crate::Authorization,
 ) -> Result<crate::Json<Role>, WebServerError> {
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::empty()).await?;
+
     let bigdecimal_role_id = u128_to_bigdecimal!(role_id);
-    let r = sqlx::query!("SELECT * FROM roles where id = $1", bigdecimal_role_id)
-        .fetch_optional(get_db_or_fail!())
-        .await?
-        .ok_or_else(|| ErrorJson::new_404(format!("Unknown role with ID {}", role_id)))?;
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+    let r = sqlx::query!(
+        "SELECT * FROM roles where id = $1 AND parent_guild = $2",
+        bigdecimal_role_id,
+        bigdecimal_guild_id
+    )
+    .fetch_optional(get_db_or_fail!())
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown role with ID {}", role_id)))?;
     Ok(crate::Json {
         obj: Role {
             id: role_id,
@@ -20,7 +27,11 @@ pub async fn get_role(
             color: r.color,
             position: r.position,
             guild_id: bigdecimal_to_u128!(r.parent_guild),
-            guild_permissions: GuildPermissions::empty(),
+            guild_permissions: ferrischat_perms::role_permissions(
+                guild_id,
+                role_id,
+                &r.permissions,
+            ),
         },
         code: 200,
     })
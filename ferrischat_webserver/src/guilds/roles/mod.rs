@@ -4,6 +4,7 @@ mod delete_role;
 mod edit_role;
 mod get_role;
 mod remove_member_role;
+mod reorder_roles;
 
 pub use add_member_role::*;
 pub use create_role::*;
@@ -11,6 +12,7 @@ pub use delete_role::*;
 pub use edit_role::*;
 pub use get_role::*;
 pub use remove_member_role::*;
+pub use reorder_roles::*;
 
 use axum::routing::{get, post};
 use axum::Router;
@@ -19,7 +21,11 @@ pub fn generate_roles_routes() -> axum::Router {
     debug!("generating routes for roles");
     Router::new()
         // POST   /guilds/:guild_id/roles
-        .route(expand_version!("guilds/:guild_id/roles"), post(create_role))
+        // PATCH  /guilds/:guild_id/roles
+        .route(
+            expand_version!("guilds/:guild_id/roles"),
+            post(create_role).patch(reorder_roles),
+        )
         // GET    /guilds/:guild_id/roles/:role_id
         // DELETE /guilds/:guild_id/roles/:role_id
         // PATCH  /guilds/:guild_id/roles/:role_id
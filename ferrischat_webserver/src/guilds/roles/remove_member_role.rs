@@ -1,25 +1,80 @@
+use crate::ws::fire_event;
 use crate::WebServerError;
 use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{AuditLogAction, ErrorJson, Role};
+use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_perms::Rank;
 use http::StatusCode;
 
 /// DELETE `/v0/guilds/{guild_id}/members/{user_id}/role/{role_id}`
 pub async fn remove_member_role(
     Path((guild_id, user_id, role_id)): Path<(u128, u128, u128)>,
-    _: crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
 ) -> Result<StatusCode, WebServerError> {
     let db = get_db_or_fail!();
-    let guild_id = u128_to_bigdecimal!(guild_id);
-    let user_id = u128_to_bigdecimal!(user_id);
-    let role_id = u128_to_bigdecimal!(role_id);
 
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::MANAGE_ROLES).await?;
+
+    if ferrischat_perms::is_everyone_role(guild_id, role_id) {
+        return Err(ErrorJson::new_400(
+            "every member implicitly holds the everyone role".to_string(),
+        )
+        .into());
+    }
+
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+    let bigdecimal_user_id = u128_to_bigdecimal!(user_id);
+    let bigdecimal_role_id = u128_to_bigdecimal!(role_id);
+
+    let role = sqlx::query!(
+        "SELECT * FROM roles WHERE id = $1 AND parent_guild = $2",
+        bigdecimal_role_id,
+        bigdecimal_guild_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown role with ID {}", role_id)))?;
+    crate::require_rank_above(guild_id, auth_user, Rank::of_role(role.position)).await?;
+
+    let mut tx = db.begin().await?;
     sqlx::query!(
         "DELETE FROM role_data WHERE guild_id = $1 AND user_id = $2 AND role_id = $3",
+        bigdecimal_guild_id,
+        bigdecimal_user_id,
+        bigdecimal_role_id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    let role_obj = Role {
+        id: role_id,
+        name: role.name,
+        color: role.color,
+        position: role.position,
         guild_id,
-        user_id,
-        role_id
+        guild_permissions: ferrischat_perms::permissions_from_bytes(&role.permissions),
+    };
+
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::MemberRoleRemove,
+        Some(user_id),
+        Some(&role_obj),
+        None,
+        reason,
     )
-    .execute(db)
     .await?;
+    tx.commit().await?;
+
+    let event = WsOutboundEvent::MemberRoleDelete {
+        user_id,
+        role: role_obj,
+    };
 
+    fire_event(&event).await?;
     Ok(StatusCode::NO_CONTENT)
 }
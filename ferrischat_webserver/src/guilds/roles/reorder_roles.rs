@@ -0,0 +1,109 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use axum::Json;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::request_json::RolePositionJson;
+use ferrischat_common::types::{AuditLogAction, ErrorJson, Role};
+use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_perms::Rank;
+
+/// PATCH `/v0/guilds/{guild_id}/roles`
+pub async fn reorder_roles(
+    Path(guild_id): Path<u128>,
+    Json(positions): Json<Vec<RolePositionJson>>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
+) -> Result<crate::Json<Vec<Role>>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::MANAGE_ROLES).await?;
+    let rank = ferrischat_perms::get_rank(db, guild_id, auth_user)
+        .await?
+        .ok_or_else(|| ErrorJson::new_403("you are not a member of this guild".to_string()))?;
+
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+
+    let mut tx = db.begin().await?;
+    let mut changes = Vec::with_capacity(positions.len());
+    for RolePositionJson { id, position } in positions {
+        if ferrischat_perms::is_everyone_role(guild_id, id) {
+            return Err(ErrorJson::new_400("the everyone role cannot be moved".to_string()).into());
+        }
+
+        let old = sqlx::query!(
+            "SELECT * FROM roles WHERE id = $1 AND parent_guild = $2 FOR UPDATE",
+            u128_to_bigdecimal!(id),
+            bigdecimal_guild_id
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown role with ID {}", id)))?;
+
+        // both where the role is now and where it's going must be below the caller
+        if rank <= Rank::of_role(old.position) || rank <= Rank::of_role(position) {
+            return Err(ErrorJson::new_403(
+                "you cannot manage roles or members ranked at or above your highest role"
+                    .to_string(),
+            )
+            .into());
+        }
+
+        if old.position == position {
+            continue;
+        }
+
+        let new = sqlx::query!(
+            "UPDATE roles SET position = $1 WHERE id = $2 RETURNING *",
+            position,
+            old.id
+        )
+        .fetch_one(&mut tx)
+        .await?;
+
+        let permissions = ferrischat_perms::role_permissions(guild_id, id, &old.permissions);
+        changes.push((
+            Role {
+                id,
+                name: old.name,
+                color: old.color,
+                position: old.position,
+                guild_id,
+                guild_permissions: permissions,
+            },
+            Role {
+                id,
+                name: new.name,
+                color: new.color,
+                position: new.position,
+                guild_id,
+                guild_permissions: permissions,
+            },
+        ));
+    }
+    for (old, new) in &changes {
+        crate::add_audit_log_entry(
+            &mut tx,
+            guild_id,
+            auth_user,
+            AuditLogAction::RoleUpdate,
+            Some(new.id),
+            Some(old),
+            Some(new),
+            reason.clone(),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    let mut roles = Vec::with_capacity(changes.len());
+    for (old, new) in changes {
+        roles.push(new.clone());
+        fire_event(&WsOutboundEvent::RoleUpdate { old, new }).await?;
+    }
+
+    Ok(crate::Json {
+        obj: roles,
+        code: 200,
+    })
+}
@@ -0,0 +1,131 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use axum::Json;
+use ferrischat_common::request_json::TransferOwnershipJson;
+use ferrischat_common::types::{AuditLogAction, ErrorJson, Guild, GuildFlags, UserFlags};
+use ferrischat_common::ws::WsOutboundEvent;
+
+/// POST `/v0/guilds/{guild_id}/transfer`
+pub async fn transfer_ownership(
+    Path(guild_id): Path<u128>,
+    Json(TransferOwnershipJson {
+        new_owner_id,
+        password,
+    }): Json<TransferOwnershipJson>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
+) -> Result<crate::Json<Guild>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+    let bigdecimal_new_owner_id = u128_to_bigdecimal!(new_owner_id);
+
+    let guild = sqlx::query!("SELECT * FROM guilds WHERE id = $1", bigdecimal_guild_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown guild with ID {}", guild_id)))?;
+    if bigdecimal_to_u128!(guild.owner_id) != auth_user {
+        return Err(
+            ErrorJson::new_403("only the guild owner can transfer ownership".to_string()).into(),
+        );
+    }
+    if new_owner_id == auth_user {
+        return Err(ErrorJson::new_400("you already own this guild".to_string()).into());
+    }
+
+    if let Some(password) = password {
+        let hashed_password = sqlx::query!(
+            "SELECT password FROM users WHERE id = $1",
+            u128_to_bigdecimal!(auth_user)
+        )
+        .fetch_one(db)
+        .await?
+        .password;
+        if !ferrischat_auth::verify(password, hashed_password).await? {
+            return Err(ErrorJson::new_401("Your credentials are not correct".to_string()).into());
+        }
+    }
+
+    let new_owner = sqlx::query!(
+        "SELECT u.flags FROM users u INNER JOIN members m ON m.user_id = u.id WHERE m.guild_id = $1 AND m.user_id = $2",
+        bigdecimal_guild_id,
+        bigdecimal_new_owner_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| {
+        ErrorJson::new_404(format!(
+            "Unknown member with ID {} in {}",
+            new_owner_id, guild_id
+        ))
+    })?;
+    if UserFlags::from_bits_truncate(new_owner.flags).contains(UserFlags::BOT_ACCOUNT) {
+        return Err(ErrorJson::new_400("bots cannot own guilds".to_string()).into());
+    }
+
+    let old_guild_obj = Guild {
+        id: guild_id,
+        owner_id: auth_user,
+        name: guild.name,
+        flags: GuildFlags::from_bits_truncate(guild.flags),
+        channels: None,
+        members: None,
+        roles: None,
+        icon: guild.icon,
+        revision_retention_days: Some(guild.revision_retention_days),
+    };
+
+    // the owner and the new owner's membership are checked again here,
+    // in case either changed while the password was being verified
+    let mut tx = db.begin().await?;
+    let guild = sqlx::query!(
+        "UPDATE guilds SET owner_id = $1 WHERE id = $2 AND owner_id = $3 AND EXISTS (SELECT 1 FROM members WHERE guild_id = $2 AND user_id = $1) RETURNING *",
+        bigdecimal_new_owner_id,
+        bigdecimal_guild_id,
+        u128_to_bigdecimal!(auth_user)
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or_else(|| {
+        ErrorJson::new_409(
+            "the guild's owner or the new owner's membership changed during the transfer"
+                .to_string(),
+        )
+    })?;
+    let new_guild_obj = Guild {
+        id: guild_id,
+        owner_id: new_owner_id,
+        name: guild.name,
+        flags: GuildFlags::from_bits_truncate(guild.flags),
+        channels: None,
+        members: None,
+        roles: None,
+        icon: guild.icon,
+        revision_retention_days: Some(guild.revision_retention_days),
+    };
+
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::GuildOwnerTransfer,
+        Some(new_owner_id),
+        Some(&old_guild_obj),
+        Some(&new_guild_obj),
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
+    let event = WsOutboundEvent::GuildUpdate {
+        old: old_guild_obj,
+        new: new_guild_obj.clone(),
+    };
+
+    fire_event(&event).await?;
+    Ok(crate::Json {
+        obj: new_guild_obj,
+        code: 200,
+    })
+}
@@ -2,14 +2,16 @@ use crate::ws::fire_event;
 use crate::WebServerError;
 use axum::extract::Path;
 use axum::Json;
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::InviteCreateJson;
-use ferrischat_common::types::{ErrorJson, Invite};
+use ferrischat_common::types::{AuditLogAction, Invite};
 use ferrischat_common::ws::WsOutboundEvent;
 use sqlx::types::time::OffsetDateTime;
 
 /// POST `/v0/guilds/{guild_id}/invites`
 pub async fn create_invite(
     auth: crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
     Path(guild_id): Path<u128>,
     Json(InviteCreateJson { max_age, max_uses }): Json<InviteCreateJson>,
 ) -> Result<crate::Json<Invite>, WebServerError> {
@@ -20,19 +22,10 @@ pub async fn create_invite(
     let owner_id = auth.0;
     let bigdecimal_owner_id = u128_to_bigdecimal!(owner_id);
 
-    if sqlx::query!(
-        "SELECT user_id FROM members WHERE user_id = $1 AND guild_id = $2",
-        bigdecimal_owner_id,
-        bigdecimal_guild_id
-    )
-    .fetch_optional(db)
-    .await?
-    .is_none()
-    {
-        return Err(ErrorJson::new_403("you are not a member of this guild".to_string()).into());
-    }
+    crate::require_guild_permissions(guild_id, owner_id, GuildPermissions::CREATE_INVITES).await?;
 
     let now = OffsetDateTime::now_utc().unix_timestamp();
+    let mut tx = db.begin().await?;
     let resp = sqlx::query!(
         "INSERT INTO invites VALUES ((SELECT array_to_string( \
             ARRAY(SELECT substr( \
@@ -46,7 +39,7 @@ pub async fn create_invite(
         max_uses,
         max_age
     )
-    .fetch_one(db)
+    .fetch_one(&mut tx)
     .await?;
 
     let invite_obj = Invite {
@@ -59,6 +52,19 @@ pub async fn create_invite(
         max_age,
     };
 
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        owner_id,
+        AuditLogAction::InviteCreate,
+        None,
+        None,
+        Some(&invite_obj),
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
     let event = WsOutboundEvent::InviteCreate {
         invite: invite_obj.clone(),
     };
@@ -1,6 +1,7 @@
 use crate::WebServerError;
 use axum::extract::Path;
-use ferrischat_common::types::{ErrorJson, Invite};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::Invite;
 
 /// GET `/v0/guilds/{guild_id}/invites`
 pub async fn get_guild_invites(
@@ -9,19 +10,9 @@ pub async fn get_guild_invites(
 ) -> Result<crate::Json<Vec<Invite>>, WebServerError> {
     let db = get_db_or_fail!();
     let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
-    let bigdecimal_authed_user = u128_to_bigdecimal!(authorized_user);
 
-    if sqlx::query!(
-        "SELECT * FROM members WHERE user_id = $1 AND guild_id = $2",
-        bigdecimal_authed_user,
-        bigdecimal_guild_id
-    )
-    .fetch_optional(db)
-    .await?
-    .is_none()
-    {
-        return Err(ErrorJson::new_403("you are not a member of this guild".to_string()).into());
-    }
+    crate::require_guild_permissions(guild_id, authorized_user, GuildPermissions::MANAGE_INVITES)
+        .await?;
 
     let res_invites = sqlx::query!(
         "SELECT * FROM invites WHERE guild_id = $1",
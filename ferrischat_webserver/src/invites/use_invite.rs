@@ -6,8 +6,6 @@ use ferrischat_common::ws::WsOutboundEvent;
 use sqlx::types::time::OffsetDateTime;
 use sqlx::types::BigDecimal;
 
-const FERRIS_EPOCH: i64 = 1_640_995_200_000;
-
 pub async fn use_invite(
     Path(invite_code): Path<String>,
     crate::Authorization(user_id, is_bot): crate::Authorization,
@@ -31,7 +29,7 @@ pub async fn use_invite(
     let guild_id = bigdecimal_to_u128!(bigdecimal_guild_id);
     let uses = invite.uses + 1;
     let unix_timestamp = OffsetDateTime::now_utc().unix_timestamp();
-    let now = unix_timestamp - FERRIS_EPOCH;
+    let now = unix_timestamp - crate::FERRIS_EPOCH;
     let mut to_delete = false;
     if let Some(max_uses) = invite.max_uses {
         if uses > max_uses.into() {
@@ -106,6 +104,7 @@ pub async fn use_invite(
         }),
         guild_id: Some(guild_id),
         guild: None,
+        nickname: None,
     };
 
     sqlx::query!(
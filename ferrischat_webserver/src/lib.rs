@@ -18,8 +18,10 @@ extern crate ferrischat_macros;
 #[macro_use]
 extern crate tracing;
 
+mod audit_log;
 mod auth;
 mod channels;
+pub mod embeds;
 mod entrypoint;
 mod errors;
 mod guilds;
@@ -27,15 +29,23 @@ mod invites;
 mod json_response;
 mod members;
 mod messages;
-mod not_implemented;
+mod perms;
+mod storage;
 mod users;
 mod ws;
 
 pub const API_VERSION: u8 = 0;
+/// Start of the timestamps embedded in snowflake IDs, in milliseconds since the Unix epoch.
+pub const FERRIS_EPOCH: i64 = 1_640_995_200_000;
 pub static RNG_CORE: std::lazy::SyncOnceCell<ring::rand::SystemRandom> =
     std::lazy::SyncOnceCell::new();
 
 pub(crate) use crate::auth::Authorization;
+pub(crate) use audit_log::{add_audit_log_entry, AuditLogReason};
 pub use entrypoint::*;
 pub(crate) use errors::WebServerError;
 pub(crate) use json_response::Json;
+pub(crate) use perms::{
+    require_can_grant, require_channel_access, require_channel_permissions,
+    require_guild_permissions, require_rank_above,
+};
@@ -1,19 +1,29 @@
 use crate::ws::fire_event;
 use crate::WebServerError;
 use axum::extract::Path;
-use ferrischat_common::types::{ErrorJson, Member};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{AuditLogAction, ErrorJson, Member};
 use ferrischat_common::ws::WsOutboundEvent;
 
 /// DELETE `/v0/guilds/{guild_id}/members/{member_id}`
 pub async fn delete_member(
     Path((guild_id, member_id)): Path<(u128, u128)>,
-    _: crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
 ) -> Result<http::StatusCode, WebServerError> {
     let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
     let bigdecimal_member_id = u128_to_bigdecimal!(member_id);
 
     let db = get_db_or_fail!();
 
+    // anyone can leave a guild, but only those who can kick members can remove others
+    let required = if auth_user == member_id {
+        GuildPermissions::empty()
+    } else {
+        GuildPermissions::KICK_MEMBERS
+    };
+    crate::require_guild_permissions(guild_id, auth_user, required).await?;
+
     let owner_id = sqlx::query!(
         "SELECT owner_id FROM guilds WHERE id = $1",
         bigdecimal_guild_id
@@ -28,18 +38,32 @@ pub async fn delete_member(
         .into());
     }
 
+    if auth_user != member_id {
+        let target_rank = ferrischat_perms::get_rank(db, guild_id, member_id)
+            .await?
+            .ok_or_else(|| {
+                ErrorJson::new_404(format!(
+                    "Unknown member with ID {} in {}",
+                    member_id, guild_id
+                ))
+            })?;
+        crate::require_rank_above(guild_id, auth_user, target_rank).await?;
+    }
+
+    let mut tx = db.begin().await?;
     let member_obj = sqlx::query!(
         "DELETE FROM members WHERE user_id = $1 AND guild_id = $2 RETURNING *",
         bigdecimal_member_id,
         bigdecimal_guild_id
     )
-    .fetch_optional(db)
+    .fetch_optional(&mut tx)
     .await?
-    .map(|_| Member {
+    .map(|m| Member {
         user_id: Some(member_id),
         user: None,
         guild_id: Some(guild_id),
         guild: None,
+        nickname: m.nickname,
     })
     .ok_or_else(|| {
         ErrorJson::new_404(format!(
@@ -48,6 +72,22 @@ pub async fn delete_member(
         ))
     })?;
 
+    // members leaving of their own accord aren't moderation actions
+    if auth_user != member_id {
+        crate::add_audit_log_entry(
+            &mut tx,
+            guild_id,
+            auth_user,
+            AuditLogAction::MemberKick,
+            Some(member_id),
+            Some(&member_obj),
+            None,
+            reason,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
     let event = WsOutboundEvent::MemberDelete { member: member_obj };
 
     fire_event(&event).await?;
@@ -0,0 +1,295 @@
+use crate::ws::fire_event;
+use crate::{Json, WebServerError};
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::request_json::MemberUpdateJson;
+use ferrischat_common::types::{AuditLogAction, ErrorJson, Member, ModelType, Role};
+use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_perms::Rank;
+use ferrischat_snowflake_generator::generate_snowflake;
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+
+/// PATCH `/v0/guilds/{guild_id}/members/{member_id}`
+///
+/// Members with `CHANGE_NICKNAME` can change their own nickname,
+/// and those with `MANAGE_NICKNAMES` can change anyone's who ranks below them.
+/// An empty nickname clears it.
+///
+/// Passing `roles` replaces every role the member holds, which needs `MANAGE_ROLES`,
+/// and for anyone but yourself, that the member ranks below you.
+/// Every role given or taken away must rank below the caller, and roles given can't have permissions
+/// the caller lacks, the same as when they're changed one by one.
+///
+/// An edit that changes nothing returns the member as they are.
+pub async fn edit_member(
+    Path((guild_id, member_id)): Path<(u128, u128)>,
+    member_info: axum::extract::Json<MemberUpdateJson>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
+) -> Result<Json<Member>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let perms =
+        crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::empty()).await?;
+
+    let MemberUpdateJson { nickname, roles } = member_info.0;
+
+    let old = super::fetch_member(db, guild_id, member_id)
+        .await?
+        .ok_or_else(|| {
+            ErrorJson::new_404(format!(
+                "Unknown member with ID {} in {}",
+                member_id, guild_id
+            ))
+        })?;
+
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+    let bigdecimal_member_id = u128_to_bigdecimal!(member_id);
+
+    if let Some(ref nickname) = nickname {
+        if member_id == auth_user {
+            if !perms
+                .intersects(GuildPermissions::CHANGE_NICKNAME | GuildPermissions::MANAGE_NICKNAMES)
+            {
+                return Err(ErrorJson::new_403(format!(
+                    "missing permissions: {:?}",
+                    GuildPermissions::CHANGE_NICKNAME
+                ))
+                .into());
+            }
+        } else {
+            if !perms.contains(GuildPermissions::MANAGE_NICKNAMES) {
+                return Err(ErrorJson::new_403(format!(
+                    "missing permissions: {:?}",
+                    GuildPermissions::MANAGE_NICKNAMES
+                ))
+                .into());
+            }
+            require_outranks(guild_id, auth_user, member_id).await?;
+        }
+
+        if nickname.chars().count() > super::MAX_NICKNAME_LENGTH {
+            return Err(ErrorJson::new_400(format!(
+                "nicknames must be at most {} characters",
+                super::MAX_NICKNAME_LENGTH
+            ))
+            .into());
+        }
+    }
+
+    let mut added: Vec<Role> = vec![];
+    let mut removed: Vec<Role> = vec![];
+    if let Some(mut roles) = roles {
+        if !perms.contains(GuildPermissions::MANAGE_ROLES) {
+            return Err(ErrorJson::new_403(format!(
+                "missing permissions: {:?}",
+                GuildPermissions::MANAGE_ROLES
+            ))
+            .into());
+        }
+        if member_id != auth_user {
+            require_outranks(guild_id, auth_user, member_id).await?;
+        }
+        roles.sort_unstable();
+        roles.dedup();
+        if roles
+            .iter()
+            .any(|r| ferrischat_perms::is_everyone_role(guild_id, *r))
+        {
+            return Err(ErrorJson::new_400(
+                "every member implicitly holds the everyone role".to_string(),
+            )
+            .into());
+        }
+
+        let mut guild_roles: HashMap<u128, Role> = ferrischat_perms::get_guild_roles(db, guild_id)
+            .await?
+            .into_iter()
+            .map(|r| (r.id, r))
+            .collect();
+        if let Some(unknown) = roles.iter().find(|r| !guild_roles.contains_key(r)) {
+            return Err(ErrorJson::new_404(format!("Unknown role with ID {}", unknown)).into());
+        }
+
+        let current: Vec<u128> = sqlx::query!(
+            "SELECT role_id FROM role_data WHERE guild_id = $1 AND user_id = $2",
+            bigdecimal_guild_id,
+            bigdecimal_member_id
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .filter_map(|x| {
+            x.role_id
+                .with_scale(0)
+                .into_bigint_and_exponent()
+                .0
+                .to_u128()
+        })
+        .collect();
+
+        added = roles
+            .iter()
+            .filter(|r| !current.contains(r))
+            .filter_map(|r| guild_roles.remove(r))
+            .collect();
+        removed = current
+            .iter()
+            .filter(|r| !roles.contains(r))
+            .filter_map(|r| guild_roles.remove(r))
+            .collect();
+        for role in added.iter().chain(removed.iter()) {
+            crate::require_rank_above(guild_id, auth_user, Rank::of_role(role.position)).await?;
+        }
+        for role in &added {
+            crate::require_can_grant(perms, role.guild_permissions)?;
+        }
+    }
+
+    // an empty nickname is the same as none at all
+    let nickname = nickname
+        .map(|n| Some(n).filter(|n| !n.is_empty()))
+        .filter(|n| *n != old.nickname);
+    if nickname.is_none() && added.is_empty() && removed.is_empty() {
+        return Ok(Json {
+            obj: old,
+            code: 200,
+        });
+    }
+
+    let mut tx = db.begin().await?;
+    if let Some(nickname) = nickname {
+        sqlx::query!(
+            "UPDATE members SET nickname = $1 WHERE guild_id = $2 AND user_id = $3",
+            nickname,
+            bigdecimal_guild_id,
+            bigdecimal_member_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    for role in &removed {
+        sqlx::query!(
+            "DELETE FROM role_data WHERE guild_id = $1 AND user_id = $2 AND role_id = $3",
+            bigdecimal_guild_id,
+            bigdecimal_member_id,
+            u128_to_bigdecimal!(role.id)
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    for role in &added {
+        sqlx::query!(
+            "INSERT INTO role_data VALUES ($1, $2, $3, $4)",
+            u128_to_bigdecimal!(generate_snowflake::<0>(
+                ModelType::InternalUse as u8,
+                get_node_id!()
+            )),
+            bigdecimal_guild_id,
+            bigdecimal_member_id,
+            u128_to_bigdecimal!(role.id)
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    // role changes are logged and go out the same way as when they're made one at a time
+    for role in &added {
+        crate::add_audit_log_entry(
+            &mut tx,
+            guild_id,
+            auth_user,
+            AuditLogAction::MemberRoleAdd,
+            Some(member_id),
+            None,
+            Some(role),
+            reason.clone(),
+        )
+        .await?;
+    }
+    for role in &removed {
+        crate::add_audit_log_entry(
+            &mut tx,
+            guild_id,
+            auth_user,
+            AuditLogAction::MemberRoleRemove,
+            Some(member_id),
+            Some(role),
+            None,
+            reason.clone(),
+        )
+        .await?;
+    }
+
+    let new = super::fetch_member(&mut tx, guild_id, member_id)
+        .await?
+        .ok_or_else(|| {
+            ErrorJson::new_404(format!(
+                "Unknown member with ID {} in {}",
+                member_id, guild_id
+            ))
+        })?;
+
+    if old.nickname != new.nickname {
+        crate::add_audit_log_entry(
+            &mut tx,
+            guild_id,
+            auth_user,
+            AuditLogAction::MemberUpdate,
+            Some(member_id),
+            Some(&old),
+            Some(&new),
+            reason,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    for role in added {
+        let event = WsOutboundEvent::MemberRoleAdd {
+            user_id: member_id,
+            role,
+        };
+        fire_event(&event).await?;
+    }
+    for role in removed {
+        let event = WsOutboundEvent::MemberRoleDelete {
+            user_id: member_id,
+            role,
+        };
+        fire_event(&event).await?;
+    }
+
+    let event = WsOutboundEvent::MemberUpdate {
+        old,
+        new: new.clone(),
+    };
+    fire_event(&event).await?;
+
+    Ok(Json {
+        obj: new,
+        code: 200,
+    })
+}
+
+/// Make sure `user_id` ranks strictly above `member_id` in `guild_id`.
+///
+/// # Errors
+/// Returns a HTTP 404 if `member_id` isn't a member of the guild,
+/// and a HTTP 403 if `user_id` doesn't outrank them.
+async fn require_outranks(
+    guild_id: u128,
+    user_id: u128,
+    member_id: u128,
+) -> Result<(), WebServerError> {
+    let target_rank = ferrischat_perms::get_rank(get_db_or_fail!(), guild_id, member_id)
+        .await?
+        .ok_or_else(|| {
+            ErrorJson::new_404(format!(
+                "Unknown member with ID {} in {}",
+                member_id, guild_id
+            ))
+        })?;
+    crate::require_rank_above(guild_id, user_id, target_rank).await
+}
@@ -1,51 +1,20 @@
 use crate::WebServerError;
 use axum::extract::Path;
-use ferrischat_common::types::{ErrorJson, Member, User, UserFlags};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{ErrorJson, Member};
 
 /// GET `/v0/guilds/{guild_id}/members/{member_id}`
 pub async fn get_member(
     Path((guild_id, member_id)): Path<(u128, u128)>,
-    crate::Authorization(_, _): crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
 ) -> Result<crate::Json<Member>, WebServerError> {
-    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
-    let bigdecimal_member_id = u128_to_bigdecimal!(member_id);
-
     let db = get_db_or_fail!();
 
-    if !(sqlx::query!(
-        r#"SELECT EXISTS(SELECT * FROM members WHERE user_id = $1 AND guild_id = $2) AS "exists!""#,
-        bigdecimal_member_id,
-        bigdecimal_guild_id
-    )
-    .fetch_one(db)
-    .await?
-    .exists)
-    {
-        return Err(ErrorJson::new_404(format!("Unknown member with ID {}", member_id)).into());
-    };
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::empty()).await?;
 
-    let user = sqlx::query!("SELECT * FROM users WHERE id = $1", bigdecimal_member_id)
-        .fetch_optional(db)
+    let member_obj = super::fetch_member(db, guild_id, member_id)
         .await?
-        .map(|u| User {
-            id: member_id,
-            name: u.name,
-            avatar: u.avatar,
-            discriminator: u.discriminator,
-            flags: UserFlags::from_bits_truncate(u.flags),
-            guilds: None,
-            pronouns: u
-                .pronouns
-                .and_then(ferrischat_common::types::Pronouns::from_i16),
-            is_bot: { UserFlags::from_bits_truncate(u.flags).contains(UserFlags::BOT_ACCOUNT) },
-        });
-
-    let member_obj = Member {
-        user_id: Some(member_id),
-        user,
-        guild_id: Some(guild_id),
-        guild: None,
-    };
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown member with ID {}", member_id)))?;
 
     Ok(crate::Json {
         obj: member_obj,
@@ -1,13 +1,58 @@
 // TODO: add arguments for guild to each function here
 
 mod delete_member;
+mod edit_member;
 mod get_member;
 
 pub use delete_member::*;
+pub use edit_member::*;
 pub use get_member::*;
 
+use crate::WebServerError;
 use axum::routing::get;
 use axum::Router;
+use ferrischat_common::types::{Member, Pronouns, User, UserFlags};
+use sqlx::{Executor, Postgres};
+
+/// Maximum length of a member's nickname, in characters.
+pub const MAX_NICKNAME_LENGTH: usize = 32;
+
+/// Fetch `member_id`'s membership of `guild_id` along with their user, or `None` if they aren't a member.
+pub async fn fetch_member<'e, E: Executor<'e, Database = Postgres>>(
+    db: E,
+    guild_id: u128,
+    member_id: u128,
+) -> Result<Option<Member>, WebServerError> {
+    let m = match sqlx::query!(
+        "SELECT m.nickname, u.name, u.avatar, u.discriminator, u.flags, u.pronouns FROM members m INNER JOIN users u ON u.id = m.user_id WHERE m.user_id = $1 AND m.guild_id = $2",
+        u128_to_bigdecimal!(member_id),
+        u128_to_bigdecimal!(guild_id)
+    )
+    .fetch_optional(db)
+    .await?
+    {
+        Some(m) => m,
+        None => return Ok(None),
+    };
+
+    let flags = UserFlags::from_bits_truncate(m.flags);
+    Ok(Some(Member {
+        user_id: Some(member_id),
+        user: Some(User {
+            id: member_id,
+            name: m.name,
+            avatar: m.avatar,
+            discriminator: m.discriminator,
+            flags,
+            guilds: None,
+            pronouns: m.pronouns.and_then(Pronouns::from_i16),
+            is_bot: flags.contains(UserFlags::BOT_ACCOUNT),
+        }),
+        guild_id: Some(guild_id),
+        guild: None,
+        nickname: m.nickname,
+    }))
+}
 
 pub fn generate_members_routes() -> axum::Router {
     debug!("generating routes for members");
@@ -17,8 +62,6 @@ pub fn generate_members_routes() -> axum::Router {
         // DELETE /guilds/:guild_id/members/:member_id
         .route(
             expand_version!("guilds/:guild_id/members/:member_id"),
-            get(get_member)
-                .patch(crate::not_implemented::not_implemented)
-                .delete(delete_member),
+            get(get_member).patch(edit_member).delete(delete_member),
         )
 }
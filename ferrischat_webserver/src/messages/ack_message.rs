@@ -0,0 +1,64 @@
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{ErrorJson, ReadState};
+use ferrischat_common::ws::WsOutboundEvent;
+
+/// POST `/v0/channels/{channel_id}/messages/{message_id}/ack`
+///
+/// Marks the channel as read up to and including the message.
+/// Acking an older message marks the messages after it as unread again.
+pub async fn ack_message(
+    Path((channel_id, message_id)): Path<(u128, u128)>,
+    crate::Authorization(user_id, _): crate::Authorization,
+) -> Result<crate::Json<ReadState>, WebServerError> {
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+    let bigdecimal_message_id = u128_to_bigdecimal!(message_id);
+
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) =
+        crate::require_channel_access(channel_id, user_id, GuildPermissions::READ_MESSAGES).await?;
+
+    sqlx::query!(
+        "SELECT id FROM messages WHERE id = $1 AND channel_id = $2",
+        bigdecimal_message_id,
+        bigdecimal_channel_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown message with ID {}", message_id)))?;
+
+    let mention_count =
+        super::read_states::count_unread_mentions(db, channel_id, guild_id, user_id, message_id)
+            .await?;
+
+    sqlx::query!(
+        "INSERT INTO read_states VALUES ($1, $2, $3, $4) ON CONFLICT (user_id, channel_id) DO UPDATE SET last_read_id = EXCLUDED.last_read_id, mention_count = EXCLUDED.mention_count",
+        u128_to_bigdecimal!(user_id),
+        bigdecimal_channel_id,
+        bigdecimal_message_id,
+        mention_count
+    )
+    .execute(db)
+    .await?;
+
+    let read_state = ReadState {
+        channel_id,
+        last_read_id: Some(message_id),
+        mention_count,
+    };
+
+    // the user's other sessions move their read position along with this one
+    let event = WsOutboundEvent::ReadStateUpdate {
+        user_id,
+        read_state: read_state.clone(),
+    };
+    fire_event(&event).await?;
+
+    Ok(crate::Json {
+        obj: read_state,
+        code: 200,
+    })
+}
@@ -0,0 +1,63 @@
+use crate::WebServerError;
+use axum::body::{self, BoxBody};
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::ErrorJson;
+use http::header::{CONTENT_DISPOSITION, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS};
+use http::{HeaderValue, Response};
+
+/// GET `/v0/channels/{channel_id}/messages/{message_id}/attachments/{attachment_id}`
+///
+/// Responds with the raw file, rather than JSON.
+pub async fn download_attachment(
+    Path((channel_id, message_id, attachment_id)): Path<(u128, u128, u128)>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<Response<BoxBody>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_channel_access(channel_id, auth_user, GuildPermissions::READ_MESSAGES).await?;
+
+    let attachment = sqlx::query!(
+        "SELECT a.* FROM attachments a INNER JOIN messages m ON m.id = a.message_id WHERE a.id = $1 AND a.message_id = $2 AND m.channel_id = $3",
+        u128_to_bigdecimal!(attachment_id),
+        u128_to_bigdecimal!(message_id),
+        u128_to_bigdecimal!(channel_id)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown attachment with ID {}", attachment_id)))?;
+
+    let data = crate::storage::get_storage()?
+        .get(&attachment_id.to_string())
+        .await?
+        .ok_or_else(|| {
+            ErrorJson::new_500(
+                format!("file for attachment {} is missing", attachment_id),
+                false,
+                None,
+            )
+        })?;
+
+    let content_type = HeaderValue::from_str(&attachment.content_type)
+        .unwrap_or_else(|_| HeaderValue::from_static("application/octet-stream"));
+    // the filename is percent-encoded so any name makes a valid header
+    let content_disposition = HeaderValue::from_str(&format!(
+        "attachment; filename*=UTF-8''{}",
+        urlencoding::encode(&attachment.filename)
+    ))
+    .unwrap_or_else(|_| HeaderValue::from_static("attachment"));
+
+    Ok(Response::builder()
+        .status(http::StatusCode::OK)
+        .header(CONTENT_TYPE, content_type)
+        .header(CONTENT_DISPOSITION, content_disposition)
+        // browsers must not guess at a more dangerous type than the one uploaded
+        .header(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"))
+        .body(body::boxed(body::Full::from(data)))
+        .unwrap_or_else(|e| {
+            unreachable!(
+                "got an error while attempting to construct HTTP response for attachment: {}",
+                e
+            )
+        }))
+}
@@ -0,0 +1,91 @@
+mod download_attachment;
+mod upload;
+
+pub use download_attachment::*;
+pub use upload::*;
+
+use crate::WebServerError;
+use axum::routing::get;
+use axum::Router;
+use ferrischat_common::types::Attachment;
+use num_traits::ToPrimitive;
+use sqlx::types::BigDecimal;
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+
+pub fn generate_attachments_routes() -> axum::Router {
+    debug!("generating routes for attachments");
+    Router::new()
+        // GET    /channels/:channel_id/messages/:message_id/attachments/:attachment_id
+        .route(
+            expand_version!("channels/:channel_id/messages/:message_id/attachments/:attachment_id"),
+            get(download_attachment),
+        )
+}
+
+/// Fetch the attachments on each of `message_ids`, in the order they were uploaded.
+///
+/// Messages without any attachments are left out.
+pub async fn get_message_attachments(
+    db: &Pool<Postgres>,
+    message_ids: &[u128],
+) -> Result<HashMap<u128, Vec<Attachment>>, WebServerError> {
+    let bigdecimal_message_ids: Vec<_> = message_ids
+        .iter()
+        .map(|id| u128_to_bigdecimal!(*id))
+        .collect();
+
+    let resp = sqlx::query!(
+        "SELECT * FROM attachments WHERE message_id = ANY($1) ORDER BY id ASC",
+        &bigdecimal_message_ids[..]
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut attachments: HashMap<u128, Vec<Attachment>> = HashMap::new();
+    for x in resp {
+        let (id, message_id) = match (
+            x.id.with_scale(0).into_bigint_and_exponent().0.to_u128(),
+            x.message_id
+                .with_scale(0)
+                .into_bigint_and_exponent()
+                .0
+                .to_u128(),
+        ) {
+            (Some(id), Some(message_id)) => (id, message_id),
+            _ => continue,
+        };
+        attachments.entry(message_id).or_default().push(Attachment {
+            id,
+            message_id,
+            filename: x.filename,
+            content_type: x.content_type,
+            size: x.size,
+        });
+    }
+
+    Ok(attachments)
+}
+
+/// Remove the stored files of attachments whose rows have already been deleted.
+///
+/// Failures are only logged: the attachments are already gone as far as users can tell,
+/// so a leftover file shouldn't fail the request that deleted them.
+pub async fn delete_stored_attachments(attachment_ids: Vec<BigDecimal>) {
+    let storage = match crate::storage::get_storage() {
+        Ok(s) => s,
+        Err(_) => {
+            warn!("storage backend missing, leaving attachment files behind");
+            return;
+        }
+    };
+    for id in attachment_ids {
+        let id = match id.with_scale(0).into_bigint_and_exponent().0.to_u128() {
+            Some(id) => id,
+            None => continue,
+        };
+        if let Err(e) = storage.delete(&id.to_string()).await {
+            warn!("failed to delete stored attachment {}: {}", id, e);
+        }
+    }
+}
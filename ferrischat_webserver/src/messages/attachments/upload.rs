@@ -0,0 +1,175 @@
+use crate::WebServerError;
+use axum::body::{Body, BoxBody};
+use axum::extract::{FromRequest, Multipart, RequestParts};
+use axum::response::IntoResponse;
+use bytes::{Bytes, BytesMut};
+use ferrischat_common::request_json::MessageCreateJson;
+use ferrischat_common::types::ErrorJson;
+use ferrischat_config::LimitsConfig;
+use futures::StreamExt;
+
+/// Name of the multipart field holding the JSON part of a message.
+pub const PAYLOAD_JSON_FIELD: &str = "payload_json";
+
+/// Maximum length of an attachment's filename, in bytes. Longer names are cut off.
+pub const MAX_FILENAME_LENGTH: usize = 256;
+
+/// A file uploaded alongside a message, that hasn't been stored yet.
+pub struct PendingAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Bytes,
+}
+
+/// Request body for creating a message.
+///
+/// This is either plain JSON, or `multipart/form-data` where the [`PAYLOAD_JSON_FIELD`] field
+/// holds the JSON and every other field with a filename is a file to attach.
+pub struct MessageCreateBody {
+    pub json: MessageCreateJson,
+    pub attachments: Vec<PendingAttachment>,
+}
+
+#[async_trait::async_trait]
+impl FromRequest<Body> for MessageCreateBody {
+    type Rejection = http::Response<BoxBody>;
+
+    async fn from_request(req: &mut RequestParts<Body>) -> Result<Self, Self::Rejection> {
+        let is_multipart = req
+            .headers()
+            .and_then(|h| h.get(http::header::CONTENT_TYPE))
+            .and_then(|v| v.to_str().ok())
+            .map_or(false, |v| v.starts_with("multipart/form-data"));
+
+        if !is_multipart {
+            let axum::Json(json) = axum::Json::<MessageCreateJson>::from_request(req)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(Self {
+                json,
+                attachments: vec![],
+            });
+        }
+
+        let multipart = Multipart::from_request(req)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        read_multipart(multipart)
+            .await
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+async fn read_multipart(mut multipart: Multipart) -> Result<MessageCreateBody, WebServerError> {
+    let limits = ferrischat_config::GLOBAL_CONFIG
+        .get()
+        .map_or_else(LimitsConfig::default, |c| c.limits.clone());
+
+    let mut json = None;
+    let mut attachments = vec![];
+    while let Some(mut field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() == Some(PAYLOAD_JSON_FIELD) {
+            let data = field.bytes().await.map_err(multipart_error)?;
+            json = Some(serde_json::from_slice(&data).map_err(|e| {
+                ErrorJson::new_400(format!("{} is not valid JSON: {}", PAYLOAD_JSON_FIELD, e))
+            })?);
+            continue;
+        }
+
+        let filename = match field.file_name() {
+            Some(name) => sanitize_filename(name),
+            // fields that are neither files nor the payload are ignored
+            None => continue,
+        };
+        let content_type = field.content_type().map_or_else(
+            || "application/octet-stream".to_string(),
+            ToString::to_string,
+        );
+
+        if attachments.len() >= limits.max_attachments_per_message as usize {
+            return Err(ErrorJson::new_400(format!(
+                "messages can have at most {} attachments",
+                limits.max_attachments_per_message
+            ))
+            .into());
+        }
+        if !is_allowed_type(&limits.allowed_attachment_types, &content_type) {
+            return Err(ErrorJson::new_400(format!(
+                "attachments of type {} are not allowed",
+                content_type
+            ))
+            .into());
+        }
+
+        // read the file chunk by chunk so oversized uploads are cut off early
+        let mut data = BytesMut::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(multipart_error)?;
+            if (data.len() + chunk.len()) as u64 > limits.max_attachment_size {
+                return Err(ErrorJson::new(
+                    format!(
+                        "attachments must be at most {} bytes",
+                        limits.max_attachment_size
+                    ),
+                    413,
+                )
+                .into());
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        attachments.push(PendingAttachment {
+            filename,
+            content_type,
+            data: data.freeze(),
+        });
+    }
+
+    let json =
+        json.ok_or_else(|| ErrorJson::new_400(format!("missing {} field", PAYLOAD_JSON_FIELD)))?;
+    Ok(MessageCreateBody { json, attachments })
+}
+
+fn multipart_error(e: axum::extract::multipart::MultipartError) -> WebServerError {
+    ErrorJson::new_400(format!("invalid multipart body: {}", e)).into()
+}
+
+/// Whether `content_type` matches any of `allowed`, which may end in `/*` to match any subtype.
+/// An empty list allows every type.
+fn is_allowed_type(allowed: &[String], content_type: &str) -> bool {
+    // parameters such as `; charset=utf-8` don't affect the type
+    let content_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    allowed.is_empty()
+        || allowed.iter().any(|a| {
+            let a = a.to_ascii_lowercase();
+            match a.strip_suffix("/*") {
+                Some(main_type) => content_type
+                    .split_once('/')
+                    .map_or(false, |(t, _)| t == main_type),
+                None => a == content_type,
+            }
+        })
+}
+
+/// Strip any directories off `name` and cut it down to [`MAX_FILENAME_LENGTH`].
+fn sanitize_filename(name: &str) -> String {
+    let name = name
+        .rsplit(&['/', '\\'][..])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let mut end = name.len().min(MAX_FILENAME_LENGTH);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    match &name[..end] {
+        "" | "." | ".." => "unknown".to_string(),
+        name => name.to_string(),
+    }
+}
@@ -0,0 +1,133 @@
+use super::{now_millis, snowflake_at};
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::{Json, Path};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::request_json::MessageBulkDeleteJson;
+use ferrischat_common::types::{AuditLogAction, ErrorJson};
+use ferrischat_common::ws::WsOutboundEvent;
+use num_traits::ToPrimitive;
+
+/// Maximum number of message IDs that can be passed to a single bulk delete.
+pub const MAX_BULK_DELETE_MESSAGES: usize = 300;
+
+/// Maximum number of minutes back a bulk delete by author can reach.
+pub const MAX_BULK_DELETE_MINUTES: u32 = 1440;
+
+/// POST `/v0/channels/{channel_id}/messages/bulk-delete`
+///
+/// Deletes either an explicit list of messages, or every message by one author in the last few minutes.
+pub async fn bulk_delete_messages(
+    Path(channel_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
+    Json(MessageBulkDeleteJson {
+        message_ids,
+        author_id,
+        within_minutes,
+    }): Json<MessageBulkDeleteJson>,
+) -> Result<http::StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) = crate::require_channel_permissions(
+        channel_id,
+        auth_user,
+        GuildPermissions::READ_MESSAGES | GuildPermissions::MANAGE_MESSAGES,
+    )
+    .await?;
+
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+
+    let mut tx = db.begin().await?;
+    // the IDs of attachments are collected while deleting, so their files can be removed after
+    let deleted = match (message_ids, author_id, within_minutes) {
+        (Some(message_ids), None, None) => {
+            if message_ids.is_empty() || message_ids.len() > MAX_BULK_DELETE_MESSAGES {
+                return Err(ErrorJson::new_400(format!(
+                    "between 1 and {} message IDs must be passed",
+                    MAX_BULK_DELETE_MESSAGES
+                ))
+                .into());
+            }
+            let message_ids: Vec<_> = message_ids
+                .into_iter()
+                .map(|id| u128_to_bigdecimal!(id))
+                .collect();
+
+            sqlx::query!(
+                "DELETE FROM messages WHERE channel_id = $1 AND id = ANY($2) RETURNING id, ARRAY(SELECT a.id FROM attachments a WHERE a.message_id = messages.id) AS \"attachment_ids!\"",
+                bigdecimal_channel_id,
+                &message_ids[..]
+            )
+            .fetch_all(&mut tx)
+            .await?
+            .into_iter()
+            .map(|x| (x.id, x.attachment_ids))
+            .collect::<Vec<_>>()
+        }
+        (None, Some(author_id), Some(within_minutes)) => {
+            if within_minutes == 0 || within_minutes > MAX_BULK_DELETE_MINUTES {
+                return Err(ErrorJson::new_400(format!(
+                    "within_minutes must be between 1 and {}",
+                    MAX_BULK_DELETE_MINUTES
+                ))
+                .into());
+            }
+            let since = now_millis() - i64::from(within_minutes) * 60_000;
+
+            sqlx::query!(
+                "DELETE FROM messages WHERE channel_id = $1 AND author_id = $2 AND id >= $3 RETURNING id, ARRAY(SELECT a.id FROM attachments a WHERE a.message_id = messages.id) AS \"attachment_ids!\"",
+                bigdecimal_channel_id,
+                u128_to_bigdecimal!(author_id),
+                u128_to_bigdecimal!(snowflake_at(since))
+            )
+            .fetch_all(&mut tx)
+            .await?
+            .into_iter()
+            .map(|x| (x.id, x.attachment_ids))
+            .collect::<Vec<_>>()
+        }
+        _ => {
+            return Err(ErrorJson::new_400(
+                "pass either message_ids, or both author_id and within_minutes".to_string(),
+            )
+            .into())
+        }
+    };
+
+    let (deleted, attachment_ids): (Vec<_>, Vec<_>) = deleted.into_iter().unzip();
+    let message_ids: Vec<u128> = deleted
+        .into_iter()
+        .filter_map(|id| id.with_scale(0).into_bigint_and_exponent().0.to_u128())
+        .collect();
+
+    // nothing matched, so there's nothing to log or announce
+    if message_ids.is_empty() {
+        return Ok(http::StatusCode::NO_CONTENT);
+    }
+
+    crate::add_audit_log_entry(
+        &mut tx,
+        guild_id,
+        auth_user,
+        AuditLogAction::MessageBulkDelete,
+        Some(channel_id),
+        Some(&message_ids),
+        None,
+        reason,
+    )
+    .await?;
+    tx.commit().await?;
+
+    super::attachments::delete_stored_attachments(attachment_ids.into_iter().flatten().collect())
+        .await;
+
+    let event = WsOutboundEvent::MessageBulkDelete {
+        guild_id,
+        channel_id,
+        message_ids,
+    };
+
+    fire_event(&event).await?;
+    Ok(http::StatusCode::NO_CONTENT)
+}
@@ -1,26 +1,29 @@
 use crate::ws::fire_event;
 use crate::WebServerError;
 use axum::extract::Path;
-use ferrischat_common::types::{Channel, ErrorJson, Message, User, UserFlags};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{AuditLogAction, ErrorJson, Message, User, UserFlags};
 use ferrischat_common::ws::WsOutboundEvent;
 
 /// DELETE `/v0/channels/{channel_id}/messages/{message_id}`
+///
+/// Messages deleted by someone other than their author are recorded in the guild's audit log.
 pub async fn delete_message(
     Path((channel_id, message_id)): Path<(u128, u128)>,
-    crate::Authorization(_, is_bot): crate::Authorization,
+    crate::Authorization(auth_user, is_bot): crate::Authorization,
+    crate::AuditLogReason(reason): crate::AuditLogReason,
 ) -> Result<http::StatusCode, WebServerError> {
     let bigdecimal_message_id = u128_to_bigdecimal!(message_id);
     let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
 
     let db = get_db_or_fail!();
 
-    let channel = sqlx::query!(
-        "SELECT * FROM channels WHERE id = $1",
-        bigdecimal_channel_id
-    )
-    .fetch_optional(db)
-    .await?
-    .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+    let (guild_id, perms) =
+        crate::require_channel_access(channel_id, auth_user, GuildPermissions::empty()).await?;
+
+    let channel_obj = crate::channels::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
 
     let message = sqlx::query!(
         r#"
@@ -46,13 +49,28 @@ WHERE m.id = $1
     .await?
     .ok_or_else(|| ErrorJson::new_404(format!("Unknown message with ID {}", message_id)))?;
 
-    let channel_obj = Channel {
-        name: channel.name,
-        id: channel_id,
-        guild_id: bigdecimal_to_u128!(channel.guild_id),
+    let author_id = bigdecimal_to_u128!(message.author_id);
+    if author_id != auth_user && !perms.contains(GuildPermissions::MANAGE_MESSAGES) {
+        return Err(ErrorJson::new_403("missing permissions: MANAGE_MESSAGES".to_string()).into());
+    }
+
+    let reference_id = match message.reference_id {
+        Some(r) => Some(bigdecimal_to_u128!(r)),
+        None => None,
     };
 
-    let author_id = bigdecimal_to_u128!(message.author_id);
+    let embeds = crate::embeds::get_message_embeds(db, &[message_id])
+        .await?
+        .remove(&message_id)
+        .unwrap_or_default();
+    let attachments = super::attachments::get_message_attachments(db, &[message_id])
+        .await?
+        .remove(&message_id)
+        .unwrap_or_default();
+    let attachment_ids = attachments
+        .iter()
+        .map(|a| u128_to_bigdecimal!(a.id))
+        .collect();
 
     let msg_obj = Message {
         id: message_id,
@@ -61,7 +79,7 @@ WHERE m.id = $1
         author_id,
         content: message.content,
         edited_at: message.edited_at,
-        embeds: vec![],
+        embeds,
         author: Some(User {
             id: author_id,
             name: message.author_name,
@@ -75,15 +93,39 @@ WHERE m.id = $1
             is_bot,
         }),
         nonce: None,
+        reactions: None,
+        reference_id,
+        referenced_message: None,
+        mentions: super::mentions::from_bigdecimals(message.mentions),
+        mention_roles: super::mentions::from_bigdecimals(message.mention_roles),
+        mention_everyone: message.mention_everyone,
+        attachments: Some(attachments),
     };
 
+    let mut tx = db.begin().await?;
     sqlx::query!(
         "DELETE FROM messages WHERE id = $1 AND channel_id = $2",
         bigdecimal_message_id,
         bigdecimal_channel_id
     )
-    .execute(db)
+    .execute(&mut tx)
     .await?;
+    // authors deleting their own messages aren't moderation actions
+    if let Some(guild_id) = guild_id.filter(|_| author_id != auth_user) {
+        crate::add_audit_log_entry(
+            &mut tx,
+            guild_id,
+            auth_user,
+            AuditLogAction::MessageDelete,
+            Some(message_id),
+            Some(&msg_obj),
+            None,
+            reason,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    super::attachments::delete_stored_attachments(attachment_ids).await;
 
     let event = WsOutboundEvent::MessageDelete {
         message: msg_obj.clone(),
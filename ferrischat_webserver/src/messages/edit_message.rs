@@ -2,10 +2,13 @@ use crate::ws::fire_event;
 use crate::WebServerError;
 use axum::extract::Path;
 use axum::Json;
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::MessageUpdateJson;
-use ferrischat_common::types::{Channel, ErrorJson, Message, User, UserFlags};
+use ferrischat_common::types::{ErrorJson, Message, ModelType, User, UserFlags};
 use ferrischat_common::ws::WsOutboundEvent;
+use ferrischat_snowflake_generator::generate_snowflake;
 
+/// PATCH `/v0/channels/{channel_id}/messages/{message_id}`
 pub async fn edit_message(
     Path((channel_id, message_id)): Path<(u128, u128)>,
     Json(MessageUpdateJson { content }): Json<MessageUpdateJson>,
@@ -16,6 +19,8 @@ pub async fn edit_message(
 
     let db = get_db_or_fail!();
 
+    crate::require_channel_access(channel_id, user_id, GuildPermissions::SEND_MESSAGES).await?;
+
     if let Some(ref content) = content {
         if content.len() > 10240 {
             return Err(ErrorJson::new_400(
@@ -25,19 +30,9 @@ pub async fn edit_message(
         }
     }
 
-    let channel = sqlx::query!(
-        "SELECT * FROM channels WHERE id = $1",
-        bigdecimal_channel_id
-    )
-    .fetch_optional(db)
-    .await?
-    .ok_or_else(|| ErrorJson::new_404("channel not found".to_string()))?;
-
-    let channel_obj = Channel {
-        id: channel_id,
-        name: channel.name,
-        guild_id: bigdecimal_to_u128!(channel.guild_id),
-    };
+    let channel_obj = crate::channels::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404("channel not found".to_string()))?;
 
     let resp = sqlx::query!(
         "SELECT m.*, a.avatar AS avatar, a.name AS author_name, a.flags AS author_flags, a.discriminator AS author_discriminator, a.pronouns AS author_pronouns FROM messages m CROSS JOIN LATERAL (SELECT * FROM users WHERE id = m.author_id) AS a WHERE m.id = $1 AND m.channel_id = $2",
@@ -49,6 +44,22 @@ pub async fn edit_message(
         .ok_or_else(|| ErrorJson::new_404(format!("Unknown message with ID {}", message_id)),
         )?;
 
+    let reference_id = match resp.reference_id {
+        Some(r) => Some(bigdecimal_to_u128!(r)),
+        None => None,
+    };
+    let referenced_message =
+        super::references::get_referenced_message(db, &channel_obj, reference_id).await?;
+
+    let embeds = crate::embeds::get_message_embeds(db, &[message_id])
+        .await?
+        .remove(&message_id)
+        .unwrap_or_default();
+    let attachments = super::attachments::get_message_attachments(db, &[message_id])
+        .await?
+        .remove(&message_id)
+        .unwrap_or_default();
+
     let old_message_obj = {
         let author_id = bigdecimal_to_u128!(resp.author_id);
         if author_id != user_id {
@@ -67,7 +78,7 @@ pub async fn edit_message(
             author_id,
             content: resp.content,
             edited_at: resp.edited_at,
-            embeds: vec![],
+            embeds,
             author: Some(User {
                 id: author_id,
                 name: resp.author_name,
@@ -84,16 +95,83 @@ pub async fn edit_message(
                 },
             }),
             nonce: None,
+            reactions: None,
+            reference_id,
+            referenced_message: referenced_message.clone(),
+            mentions: super::mentions::from_bigdecimals(resp.mentions),
+            mention_roles: super::mentions::from_bigdecimals(resp.mention_roles),
+            mention_everyone: resp.mention_everyone,
+            attachments: Some(attachments.clone()),
         }
     };
 
-    let message = sqlx::query!("UPDATE messages SET content = $1, edited_at = now()::timestamp without time zone WHERE channel_id = $2 AND id = $3 RETURNING *", content, bigdecimal_channel_id, bigdecimal_message_id)
-        .fetch_optional(db)
+    // the content is all that can be edited, so an edit leaving it as it was changes nothing:
+    // no revision is kept, and the message isn't marked as edited
+    let content = match content {
+        Some(content) if Some(&content) != old_message_obj.content.as_ref() => content,
+        _ => {
+            return Ok(crate::Json {
+                obj: old_message_obj,
+                code: 200,
+            })
+        }
+    };
+
+    let mentions = super::mentions::check_mentions(db, &channel_obj, user_id, &content).await?;
+
+    // private channels have no guild to set a retention period, so they don't keep revisions
+    let revision_guild_id = match channel_obj.guild_id {
+        Some(guild_id) => {
+            let retention_days = sqlx::query!(
+                "SELECT revision_retention_days FROM guilds WHERE id = $1",
+                u128_to_bigdecimal!(guild_id)
+            )
+            .fetch_one(db)
+            .await?
+            .revision_retention_days;
+            (retention_days > 0).then(|| guild_id)
+        }
+        None => None,
+    };
+
+    // the revision is only kept if the edit goes through
+    let mut tx = db.begin().await?;
+    if let Some(guild_id) = revision_guild_id {
+        let revision_id = generate_snowflake::<0>(ModelType::InternalUse as u8, get_node_id!());
+        sqlx::query!(
+            "INSERT INTO message_revisions VALUES ($1, $2, $3, $4)",
+            u128_to_bigdecimal!(revision_id),
+            bigdecimal_message_id,
+            u128_to_bigdecimal!(guild_id),
+            old_message_obj.content
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    let message = sqlx::query!(
+        "UPDATE messages SET content = $1, edited_at = now()::timestamp without time zone, mentions = $4, mention_roles = $5, mention_everyone = $6 WHERE channel_id = $2 AND id = $3 RETURNING *",
+        content,
+        bigdecimal_channel_id,
+        bigdecimal_message_id,
+        &super::mentions::to_bigdecimals(&mentions.users)[..],
+        &super::mentions::to_bigdecimals(&mentions.roles)[..],
+        mentions.everyone
+    )
+        .fetch_optional(&mut tx)
         .await?
         .ok_or_else(|| ErrorJson::new_404(
             format!("Unknown message with ID {}", message_id)
         )
         )?;
+    // embeds were generated from the links in the old content, so they go along with it
+    sqlx::query!(
+        "DELETE FROM message_embeds WHERE message_id = $1",
+        bigdecimal_message_id
+    )
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
     let new_msg_obj = Message {
         id: message_id,
         channel: channel_obj,
@@ -101,9 +179,16 @@ pub async fn edit_message(
         author_id: bigdecimal_to_u128!(message.author_id),
         content: message.content,
         edited_at: message.edited_at,
-        embeds: vec![],
+        embeds: Vec::new(),
         author: old_message_obj.author.clone(),
         nonce: None,
+        reactions: None,
+        reference_id,
+        referenced_message,
+        mentions: super::mentions::from_bigdecimals(message.mentions),
+        mention_roles: super::mentions::from_bigdecimals(message.mention_roles),
+        mention_everyone: message.mention_everyone,
+        attachments: Some(attachments),
     };
 
     let event = WsOutboundEvent::MessageUpdate {
@@ -112,6 +197,8 @@ pub async fn edit_message(
     };
 
     fire_event(&event).await?;
+    crate::embeds::queue_unfurl(&new_msg_obj);
+
     Ok(crate::Json {
         obj: new_msg_obj,
         code: 200,
@@ -0,0 +1,57 @@
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{ErrorJson, MessageRevision};
+use num_traits::ToPrimitive;
+
+/// GET `/v0/channels/{channel_id}/messages/{message_id}/revisions`
+///
+/// Revisions are ordered oldest first, and only those within the guild's retention period are returned.
+pub async fn get_message_revisions(
+    Path((channel_id, message_id)): Path<(u128, u128)>,
+    crate::Authorization(user_id, _): crate::Authorization,
+) -> Result<crate::Json<Vec<MessageRevision>>, WebServerError> {
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+    let bigdecimal_message_id = u128_to_bigdecimal!(message_id);
+
+    let db = get_db_or_fail!();
+
+    crate::require_channel_permissions(
+        channel_id,
+        user_id,
+        GuildPermissions::READ_MESSAGES | GuildPermissions::MANAGE_MESSAGES,
+    )
+    .await?;
+
+    let guild = sqlx::query!(
+        "SELECT g.id, g.revision_retention_days FROM messages m INNER JOIN channels c ON c.id = m.channel_id INNER JOIN guilds g ON g.id = c.guild_id WHERE m.id = $1 AND m.channel_id = $2",
+        bigdecimal_message_id,
+        bigdecimal_channel_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown message with ID {}", message_id)))?;
+
+    let revisions = sqlx::query!(
+        "SELECT * FROM message_revisions WHERE message_id = $1 AND guild_id = $2 AND id >= $3 ORDER BY id ASC",
+        bigdecimal_message_id,
+        guild.id,
+        u128_to_bigdecimal!(super::revision_cutoff(guild.revision_retention_days))
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .filter_map(|x| {
+        Some(MessageRevision {
+            id: x.id.with_scale(0).into_bigint_and_exponent().0.to_u128()?,
+            message_id,
+            content: x.content,
+        })
+    })
+    .collect();
+
+    Ok(crate::Json {
+        obj: revisions,
+        code: 200,
+    })
+}
@@ -1,27 +1,38 @@
 use crate::WebServerError;
 use axum::extract::Path;
-use ferrischat_common::types::{Channel, ErrorJson, Message, User, UserFlags};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{ErrorJson, Message, User, UserFlags};
 
 /// GET `/v0/guilds/{guild_id}/channels/{channel_id}/messages/{message_id}`
 pub async fn get_message(
     Path((channel_id, message_id)): Path<(u128, u128)>,
-    crate::Authorization(_, _): crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
 ) -> Result<crate::Json<Message>, WebServerError> {
+    crate::require_channel_access(channel_id, auth_user, GuildPermissions::READ_MESSAGES).await?;
+
+    Ok(crate::Json {
+        obj: fetch_message(channel_id, message_id, auth_user).await?,
+        code: 200,
+    })
+}
+
+/// Fetch a single message, with its reactions as seen by `auth_user`.
+///
+/// This doesn't check whether `auth_user` can read the channel.
+pub(crate) async fn fetch_message(
+    channel_id: u128,
+    message_id: u128,
+    auth_user: u128,
+) -> Result<Message, WebServerError> {
     let db = get_db_or_fail!();
+
     let bigint_message_id = u128_to_bigdecimal!(message_id);
     let bigint_channel_id = u128_to_bigdecimal!(channel_id);
 
-    let channel = sqlx::query!("SELECT * FROM channels WHERE id = $1", bigint_channel_id)
-        .fetch_optional(db)
+    let channel_obj = crate::channels::fetch_channel(db, channel_id)
         .await?
         .ok_or_else(|| ErrorJson::new_404("channel not found".to_string()))?;
 
-    let channel_obj = Channel {
-        id: channel_id,
-        name: channel.name,
-        guild_id: bigdecimal_to_u128!(channel.guild_id),
-    };
-
     let m =
         sqlx::query!(
             "SELECT m.*, a.pronouns AS author_pronouns, a.avatar AS author_avatar, a.name AS author_name, a.flags AS author_flags, a.discriminator AS author_discriminator FROM messages m CROSS JOIN LATERAL (SELECT * FROM users WHERE id = m.author_id) AS a WHERE m.id = $1 AND m.channel_id = $2",
@@ -35,31 +46,57 @@ pub async fn get_message(
                     format!("Unknown message with ID {}", message_id),
                 )
             })?;
-    Ok(crate::Json {
-        obj: Message {
-            id: message_id,
-            content: m.content,
-            channel: channel_obj,
-            channel_id,
-            author_id: bigdecimal_to_u128!(m.author_id),
-            edited_at: m.edited_at,
-            embeds: vec![],
-            author: Some(User {
-                id: bigdecimal_to_u128!(m.author_id),
-                name: m.author_name,
-                avatar: m.author_avatar,
-                guilds: None,
-                flags: UserFlags::from_bits_truncate(m.author_flags),
-                discriminator: m.author_discriminator,
-                pronouns: m
-                    .author_pronouns
-                    .and_then(ferrischat_common::types::Pronouns::from_i16),
-                is_bot: {
-                    UserFlags::from_bits_truncate(m.author_flags).contains(UserFlags::BOT_ACCOUNT)
-                },
-            }),
-            nonce: None,
-        },
-        code: 200,
+
+    let reference_id = match m.reference_id {
+        Some(r) => Some(bigdecimal_to_u128!(r)),
+        None => None,
+    };
+    let referenced_message =
+        super::references::get_referenced_message(db, &channel_obj, reference_id).await?;
+
+    let reactions = super::reactions::get_message_reactions(db, &[message_id], auth_user)
+        .await?
+        .remove(&message_id)
+        .unwrap_or_default();
+
+    let embeds = crate::embeds::get_message_embeds(db, &[message_id])
+        .await?
+        .remove(&message_id)
+        .unwrap_or_default();
+    let attachments = super::attachments::get_message_attachments(db, &[message_id])
+        .await?
+        .remove(&message_id)
+        .unwrap_or_default();
+
+    Ok(Message {
+        id: message_id,
+        content: m.content,
+        channel: channel_obj,
+        channel_id,
+        author_id: bigdecimal_to_u128!(m.author_id),
+        edited_at: m.edited_at,
+        embeds,
+        author: Some(User {
+            id: bigdecimal_to_u128!(m.author_id),
+            name: m.author_name,
+            avatar: m.author_avatar,
+            guilds: None,
+            flags: UserFlags::from_bits_truncate(m.author_flags),
+            discriminator: m.author_discriminator,
+            pronouns: m
+                .author_pronouns
+                .and_then(ferrischat_common::types::Pronouns::from_i16),
+            is_bot: {
+                UserFlags::from_bits_truncate(m.author_flags).contains(UserFlags::BOT_ACCOUNT)
+            },
+        }),
+        nonce: None,
+        reactions: Some(reactions),
+        reference_id,
+        referenced_message,
+        mentions: super::mentions::from_bigdecimals(m.mentions),
+        mention_roles: super::mentions::from_bigdecimals(m.mention_roles),
+        mention_everyone: m.mention_everyone,
+        attachments: Some(attachments),
     })
 }
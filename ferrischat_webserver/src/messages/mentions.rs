@@ -0,0 +1,301 @@
+use crate::WebServerError;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::Channel;
+use num_traits::ToPrimitive;
+use sqlx::types::BigDecimal;
+use sqlx::{Pool, Postgres};
+
+/// The text that mentions every member of a guild.
+pub const EVERYONE_MENTION: &str = "@everyone";
+
+/// The mentions found in a message's content.
+///
+/// Users are mentioned with `<@user_id>`, roles with `<@&role_id>`,
+/// channels with `<#channel_id>` and everyone with [`EVERYONE_MENTION`] standing on its own.
+/// Nothing inside inline code or code blocks counts as a mention.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Mentions {
+    pub users: Vec<u128>,
+    pub roles: Vec<u128>,
+    pub channels: Vec<u128>,
+    pub everyone: bool,
+}
+
+impl Mentions {
+    /// Parse the mentions out of `content`.
+    ///
+    /// Each ID is only listed once, and anything that looks like a mention but doesn't parse is ignored.
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let mut mentions = Self::default();
+        for text in outside_code(content) {
+            mentions.parse_text(text);
+        }
+
+        for list in [
+            &mut mentions.users,
+            &mut mentions.roles,
+            &mut mentions.channels,
+        ] {
+            list.sort_unstable();
+            list.dedup();
+        }
+        mentions
+    }
+
+    fn parse_text(&mut self, text: &str) {
+        self.everyone |= mentions_everyone(text);
+
+        let mut rest = text;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start + 1..];
+            let (list, id_start) = if let Some(r) = rest.strip_prefix("@&") {
+                (&mut self.roles, r)
+            } else if let Some(r) = rest.strip_prefix('@') {
+                (&mut self.users, r)
+            } else if let Some(r) = rest.strip_prefix('#') {
+                (&mut self.channels, r)
+            } else {
+                continue;
+            };
+            let end = match id_start.find('>') {
+                Some(end) => end,
+                None => break,
+            };
+            let id = &id_start[..end];
+            if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) {
+                if let Ok(id) = id.parse() {
+                    list.push(id);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `text` mentions everyone, with [`EVERYONE_MENTION`] not part of a longer word
+/// such as an email address.
+fn mentions_everyone(text: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(EVERYONE_MENTION).any(|(i, m)| {
+        !text[..i].chars().next_back().map_or(false, is_word)
+            && !text[i + m.len()..].chars().next().map_or(false, is_word)
+    })
+}
+
+/// Split `content` into the parts outside of inline code and code blocks.
+///
+/// Code starts with a run of backticks and ends at the next run of the same length,
+/// and a run that is never closed is just text.
+fn outside_code(content: &str) -> Vec<&str> {
+    let backticks_at = |i: usize| content[i..].bytes().take_while(|b| *b == b'`').count();
+
+    let mut parts = Vec::new();
+    let mut part_start = 0;
+    let mut i = 0;
+    while let Some(offset) = content[i..].find('`') {
+        let open = i + offset;
+        let run = backticks_at(open);
+        let fence = &content[open..open + run];
+
+        let mut close = None;
+        let mut j = open + run;
+        while let Some(offset) = content[j..].find(fence) {
+            let at = j + offset;
+            let len = backticks_at(at);
+            if len == run {
+                close = Some(at);
+                break;
+            }
+            j = at + len;
+        }
+
+        match close {
+            Some(close) => {
+                parts.push(&content[part_start..open]);
+                i = close + run;
+                part_start = i;
+            }
+            None => i = open + run,
+        }
+    }
+    parts.push(&content[part_start..]);
+    parts
+}
+
+/// Parse the mentions out of `content`, and make sure they can be sent by `user_id` in `channel`.
+///
+/// Mentions of the guild's everyone role are treated as mentioning everyone.
+/// Mentions of users who aren't in the guild, or roles or channels that don't belong to it,
+/// are dropped rather than refused, since quoting an older message may well include some.
+/// In private channels, only mentions of the channel's recipients are kept.
+///
+/// # Errors
+/// Returns a HTTP 403 if everyone is mentioned without `MENTION_EVERYONE`.
+/// Returns a HTTP 500 if the database returns an error.
+pub async fn check_mentions(
+    db: &Pool<Postgres>,
+    channel: &Channel,
+    user_id: u128,
+    content: &str,
+) -> Result<Mentions, WebServerError> {
+    let mut mentions = Mentions::parse(content);
+    let guild_id = match channel.guild_id {
+        Some(g) => g,
+        None => return check_private_mentions(db, channel, mentions).await,
+    };
+    let bigdecimal_guild_id = u128_to_bigdecimal!(guild_id);
+
+    if let Some(pos) = mentions.roles.iter().position(|r| *r == guild_id) {
+        mentions.roles.remove(pos);
+        mentions.everyone = true;
+    }
+
+    if !mentions.users.is_empty() {
+        let found = sqlx::query!(
+            "SELECT user_id FROM members WHERE guild_id = $1 AND user_id = ANY($2)",
+            bigdecimal_guild_id,
+            &to_bigdecimals(&mentions.users)[..]
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|x| x.user_id)
+        .collect();
+        retain_found(&mut mentions.users, found);
+    }
+
+    if !mentions.roles.is_empty() {
+        let found = sqlx::query!(
+            "SELECT id FROM roles WHERE parent_guild = $1 AND id = ANY($2)",
+            bigdecimal_guild_id,
+            &to_bigdecimals(&mentions.roles)[..]
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+        retain_found(&mut mentions.roles, found);
+    }
+
+    if !mentions.channels.is_empty() {
+        let found = sqlx::query!(
+            "SELECT id FROM channels WHERE guild_id = $1 AND id = ANY($2)",
+            bigdecimal_guild_id,
+            &to_bigdecimals(&mentions.channels)[..]
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+        retain_found(&mut mentions.channels, found);
+    }
+
+    if mentions.everyone {
+        crate::require_channel_permissions(channel.id, user_id, GuildPermissions::MENTION_EVERYONE)
+            .await?;
+    }
+
+    Ok(mentions)
+}
+
+/// Private channels have no roles, channels or everyone to mention, so only user mentions are kept,
+/// and mentions of anyone who isn't a recipient of the channel are dropped.
+async fn check_private_mentions(
+    db: &Pool<Postgres>,
+    channel: &Channel,
+    mentions: Mentions,
+) -> Result<Mentions, WebServerError> {
+    let mut mentions = Mentions {
+        users: mentions.users,
+        ..Mentions::default()
+    };
+
+    if !mentions.users.is_empty() {
+        let found = sqlx::query!(
+            "SELECT user_id FROM channel_recipients WHERE channel_id = $1 AND user_id = ANY($2)",
+            u128_to_bigdecimal!(channel.id),
+            &to_bigdecimals(&mentions.users)[..]
+        )
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|x| x.user_id)
+        .collect();
+        retain_found(&mut mentions.users, found);
+    }
+
+    Ok(mentions)
+}
+
+/// Convert a list of IDs into the form they're stored in.
+#[must_use]
+pub fn to_bigdecimals(ids: &[u128]) -> Vec<BigDecimal> {
+    ids.iter().map(|id| u128_to_bigdecimal!(*id)).collect()
+}
+
+/// Convert a list of stored IDs back, dropping any that are out of range.
+#[must_use]
+pub fn from_bigdecimals(ids: Vec<BigDecimal>) -> Vec<u128> {
+    ids.into_iter()
+        .filter_map(|id| id.with_scale(0).into_bigint_and_exponent().0.to_u128())
+        .collect()
+}
+
+/// Keep only the IDs in `ids` that were `found`.
+fn retain_found(ids: &mut Vec<u128>, found: Vec<BigDecimal>) {
+    let found = from_bigdecimals(found);
+    ids.retain(|id| found.contains(id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_kind_once() {
+        let mentions = Mentions::parse("<@1> <@&2> <#3> <@1> @everyone");
+        assert_eq!(
+            mentions,
+            Mentions {
+                users: vec![1],
+                roles: vec![2],
+                channels: vec![3],
+                everyone: true,
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_malformed_mentions() {
+        assert_eq!(Mentions::parse("<@> <@abc> <#-1> <@1"), Mentions::default());
+    }
+
+    #[test]
+    fn everyone_must_stand_on_its_own() {
+        assert!(Mentions::parse("@everyone").everyone);
+        assert!(Mentions::parse("hey @everyone, look").everyone);
+        assert!(Mentions::parse("(@everyone)").everyone);
+        assert!(!Mentions::parse("mail foo@everyone.com").everyone);
+        assert!(!Mentions::parse("@everyones").everyone);
+        assert!(!Mentions::parse("@everyone_else").everyone);
+    }
+
+    #[test]
+    fn ignores_mentions_in_code() {
+        assert_eq!(
+            Mentions::parse("`@everyone <@1>` and <@2>"),
+            Mentions {
+                users: vec![2],
+                ..Mentions::default()
+            }
+        );
+        assert!(!Mentions::parse("```\n@everyone\n```").everyone);
+        // a fence is only closed by a run of the same length
+        assert!(!Mentions::parse("``a ` @everyone``").everyone);
+        // unclosed backticks are just text
+        assert!(Mentions::parse("` @everyone").everyone);
+        assert_eq!(Mentions::parse("``` <@3> ``` <@4>").users, vec![4]);
+    }
+}
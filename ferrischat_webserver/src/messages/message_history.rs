@@ -1,46 +1,191 @@
 use crate::WebServerError;
 use axum::extract::{Path, Query};
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::GetMessageHistoryParams;
 use ferrischat_common::types::{Channel, ErrorJson, Message, MessageHistory, User, UserFlags};
+use num_traits::ToPrimitive;
+use sqlx::types::BigDecimal;
+use sqlx::{Pool, Postgres};
+
+/// Number of messages fetched when no limit is passed.
+pub const DEFAULT_HISTORY_PAGE: i64 = 50;
+
+/// Maximum number of messages fetched at once.
+pub const MAX_HISTORY_PAGE: i64 = 100;
 
 /// GET `/v0/channels/{channel_id}/messages`
+///
+/// Pages can be selected with at most one of the `before`, `after` and `around` message ID cursors.
+/// The response includes the cursors to pass to fetch the pages on either side of it.
+/// Pages hold [`DEFAULT_HISTORY_PAGE`] messages unless `limit` says otherwise,
+/// and `around` pages always include the message at the cursor if it exists.
 pub async fn get_message_history(
     Path(channel_id): Path<u128>,
-    crate::Authorization(_, _): crate::Authorization,
+    crate::Authorization(auth_user, _): crate::Authorization,
     Query(GetMessageHistoryParams {
         limit,
         oldest_first,
         mut offset,
+        before,
+        after,
+        around,
     }): Query<GetMessageHistoryParams>,
 ) -> Result<crate::Json<MessageHistory>, WebServerError> {
     let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
     let db = get_db_or_fail!();
 
+    crate::require_channel_access(channel_id, auth_user, GuildPermissions::READ_MESSAGES).await?;
+
     let oldest_first = oldest_first.unwrap_or(false);
 
-    if limit < Some(0) {
-        return Err(ErrorJson::new_400("limit must be > 0".to_string()).into());
+    let limit = limit.unwrap_or(DEFAULT_HISTORY_PAGE);
+    if !(1..=MAX_HISTORY_PAGE).contains(&limit) {
+        return Err(ErrorJson::new_400(format!(
+            "limit must be between 1 and {}",
+            MAX_HISTORY_PAGE
+        ))
+        .into());
     }
 
     if offset < Some(0) {
         offset = Some(0);
     }
 
-    let channel = sqlx::query!(
-        "SELECT * FROM channels WHERE id = $1",
-        bigdecimal_channel_id
-    )
-    .fetch_optional(db)
-    .await?
-    .ok_or_else(|| ErrorJson::new_404("channel not found".to_string()))?;
-
-    let channel_obj = Channel {
-        id: channel_id,
-        name: channel.name,
-        guild_id: bigdecimal_to_u128!(channel.guild_id),
+    if [before, after, around]
+        .iter()
+        .filter(|c| c.is_some())
+        .count()
+        > 1
+    {
+        return Err(ErrorJson::new_400(
+            "only one of before, after and around may be passed".to_string(),
+        )
+        .into());
+    }
+
+    let channel_obj = crate::channels::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404("channel not found".to_string()))?;
+
+    // every branch builds the page newest message first, and it is flipped afterwards if needed
+    let mut messages = match (before, after, around) {
+        (_, Some(after), _) => {
+            // fetch oldest first so the page starts right after the cursor
+            let mut page = fetch_page(
+                db,
+                &channel_obj,
+                auth_user,
+                None,
+                Some(u128_to_bigdecimal!(after)),
+                true,
+                limit,
+                offset,
+            )
+            .await?;
+            page.reverse();
+            page
+        }
+        (_, _, Some(around)) => {
+            // the message at the cursor always takes one slot,
+            // and the rest are split between either side of it, favouring older messages
+            let newer_limit = (limit - 1) / 2;
+            let older_limit = limit - 1 - newer_limit;
+            let mut page = fetch_page(
+                db,
+                &channel_obj,
+                auth_user,
+                None,
+                Some(u128_to_bigdecimal!(around)),
+                true,
+                newer_limit,
+                None,
+            )
+            .await?;
+            page.reverse();
+            page.extend(
+                fetch_page(
+                    db,
+                    &channel_obj,
+                    auth_user,
+                    Some(u128_to_bigdecimal!(around.saturating_add(1))),
+                    around.checked_sub(1).map(|a| u128_to_bigdecimal!(a)),
+                    false,
+                    1,
+                    None,
+                )
+                .await?,
+            );
+            page.extend(
+                fetch_page(
+                    db,
+                    &channel_obj,
+                    auth_user,
+                    Some(u128_to_bigdecimal!(around)),
+                    None,
+                    false,
+                    older_limit,
+                    None,
+                )
+                .await?,
+            );
+            page
+        }
+        (before, _, _) => {
+            // without a cursor, oldest_first picks which end of the channel the page starts at
+            let ascending = oldest_first && before.is_none();
+            let mut page = fetch_page(
+                db,
+                &channel_obj,
+                auth_user,
+                before.map(|b| u128_to_bigdecimal!(b)),
+                None,
+                ascending,
+                limit,
+                offset,
+            )
+            .await?;
+            if ascending {
+                page.reverse();
+            }
+            page
+        }
     };
+    if oldest_first {
+        messages.reverse();
+    }
+
+    let oldest_id = messages.iter().map(|m| m.id).min();
+    let newest_id = messages.iter().map(|m| m.id).max();
+
+    Ok(crate::Json {
+        obj: MessageHistory {
+            messages,
+            before: oldest_id,
+            after: newest_id,
+        },
+        code: 200,
+    })
+}
+
+/// Fetch up to `limit` messages from `channel`, skipping the first `offset`.
+///
+/// Only messages with IDs strictly below `before` and strictly above `after` are included.
+/// Messages are ordered by ID, ascending if `ascending` is set or descending otherwise,
+/// and their reactions are as seen by `user_id`.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_page(
+    db: &Pool<Postgres>,
+    channel: &Channel,
+    user_id: u128,
+    before: Option<BigDecimal>,
+    after: Option<BigDecimal>,
+    ascending: bool,
+    limit: i64,
+    offset: Option<i64>,
+) -> Result<Vec<Message>, WebServerError> {
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel.id);
 
-    let messages: Vec<_> = if oldest_first {
+    let messages: Vec<_> = if ascending {
         let resp = sqlx::query!(
             r#"
 SELECT m.*,
@@ -56,10 +201,14 @@ FROM messages m
         WHERE id = m.author_id
         ) as a
 WHERE channel_id = $1
+  AND ($2::numeric(39) IS NULL OR m.id < $2)
+  AND ($3::numeric(39) IS NULL OR m.id > $3)
 ORDER BY id ASC
-LIMIT $2 OFFSET $3
+LIMIT $4 OFFSET $5
 "#,
             bigdecimal_channel_id,
+            before,
+            after,
             limit,
             offset,
         )
@@ -79,6 +228,10 @@ LIMIT $2 OFFSET $3
                     x.author_discriminator,
                     x.author_pronouns,
                     x.edited_at,
+                    x.reference_id,
+                    x.mentions,
+                    x.mention_roles,
+                    x.mention_everyone,
                 )
             })
             .collect()
@@ -98,10 +251,14 @@ FROM messages m
         WHERE id = m.author_id
         ) as a
 WHERE channel_id = $1
+  AND ($2::numeric(39) IS NULL OR m.id < $2)
+  AND ($3::numeric(39) IS NULL OR m.id > $3)
 ORDER BY id DESC
-LIMIT $2 OFFSET $3
+LIMIT $4 OFFSET $5
 "#,
             bigdecimal_channel_id,
+            before,
+            after,
             limit,
             offset,
         )
@@ -121,11 +278,30 @@ LIMIT $2 OFFSET $3
                     x.author_discriminator,
                     x.author_pronouns,
                     x.edited_at,
+                    x.reference_id,
+                    x.mentions,
+                    x.mention_roles,
+                    x.mention_everyone,
                 )
             })
             .collect()
     };
 
+    let message_ids: Vec<_> = messages
+        .iter()
+        .filter_map(|m| m.0.with_scale(0).into_bigint_and_exponent().0.to_u128())
+        .collect();
+    let mut reactions = super::reactions::get_message_reactions(db, &message_ids, user_id).await?;
+    let mut attachments = super::attachments::get_message_attachments(db, &message_ids).await?;
+    let mut embeds = crate::embeds::get_message_embeds(db, &message_ids).await?;
+    let reference_ids: Vec<_> = messages
+        .iter()
+        .filter_map(|m| m.10.as_ref())
+        .filter_map(|r| r.with_scale(0).into_bigint_and_exponent().0.to_u128())
+        .collect();
+    let referenced_messages =
+        super::references::get_referenced_messages(db, channel, &reference_ids).await?;
+
     let mut output_messages = Vec::with_capacity(messages.len());
     for (
         id,
@@ -138,16 +314,28 @@ LIMIT $2 OFFSET $3
         author_discriminator,
         author_pronouns,
         edited_at,
+        reference_id,
+        mentions,
+        mention_roles,
+        mention_everyone,
     ) in messages
     {
         let author_id = bigdecimal_to_u128!(author_id);
         let id = bigdecimal_to_u128!(id);
         let channel_id = bigdecimal_to_u128!(channel_id);
+        let reference_id = match reference_id {
+            Some(r) => Some(bigdecimal_to_u128!(r)),
+            None => None,
+        };
+        // a reference to a deleted message keeps its ID but resolves to nothing
+        let referenced_message = reference_id
+            .and_then(|r| referenced_messages.get(&r).cloned())
+            .map(Box::new);
 
         output_messages.push(Message {
             id,
             content,
-            channel: channel_obj.clone(),
+            channel: channel.clone(),
             channel_id,
             author_id,
             author: Some(User {
@@ -163,15 +351,17 @@ LIMIT $2 OFFSET $3
                 },
             }),
             edited_at,
-            embeds: vec![],
+            embeds: embeds.remove(&id).unwrap_or_default(),
             nonce: None,
+            reactions: Some(reactions.remove(&id).unwrap_or_default()),
+            reference_id,
+            referenced_message,
+            mentions: super::mentions::from_bigdecimals(mentions),
+            mention_roles: super::mentions::from_bigdecimals(mention_roles),
+            mention_everyone,
+            attachments: Some(attachments.remove(&id).unwrap_or_default()),
         });
     }
 
-    Ok(crate::Json {
-        obj: MessageHistory {
-            messages: output_messages,
-        },
-        code: 200,
-    })
+    Ok(output_messages)
 }
@@ -1,17 +1,89 @@
+mod ack_message;
+pub mod attachments;
+mod bulk_delete_messages;
 mod delete_message;
 mod edit_message;
+mod get_message_revisions;
 mod get_messages;
+pub mod mentions;
 mod message_history;
+mod nonce;
+pub mod reactions;
+mod read_states;
+pub mod references;
+mod search_messages;
 mod send_message;
 
+pub use ack_message::*;
+pub use bulk_delete_messages::*;
 pub use delete_message::*;
 pub use edit_message::*;
+pub use get_message_revisions::*;
 pub use get_messages::*;
 pub use message_history::*;
+pub use search_messages::*;
 pub use send_message::*;
 
+use crate::WebServerError;
 use axum::routing::{get, post};
 use axum::Router;
+use sqlx::types::time::OffsetDateTime;
+use std::time::Duration;
+
+/// How often revisions older than their guild keeps them for are deleted.
+pub const REVISION_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Convert a Unix timestamp, in milliseconds, to the lowest snowflake ID that could be generated at that time.
+fn snowflake_at(unix_millis: i64) -> u128 {
+    let millis = unix_millis - crate::FERRIS_EPOCH;
+    // timestamps are stored in the upper 64 bits of snowflake IDs
+    u128::from(millis.max(0).unsigned_abs()) << 64
+}
+
+/// The current Unix timestamp, in milliseconds.
+fn now_millis() -> i64 {
+    let now = OffsetDateTime::now_utc();
+    now.unix_timestamp() * 1000 + i64::from(now.millisecond())
+}
+
+/// The lowest revision ID a guild keeping revisions for `retention_days` still keeps.
+fn revision_cutoff(retention_days: i32) -> u128 {
+    snowflake_at(now_millis() - i64::from(retention_days) * 86_400_000)
+}
+
+/// Delete every revision older than its guild keeps revisions for.
+async fn prune_revisions() -> Result<(), WebServerError> {
+    let db = get_db_or_fail!();
+
+    // guilds with the same retention period share a cutoff, so each period is pruned at once
+    let retention_periods = sqlx::query!("SELECT DISTINCT revision_retention_days FROM guilds")
+        .fetch_all(db)
+        .await?;
+    for period in retention_periods {
+        sqlx::query!(
+            "DELETE FROM message_revisions r USING guilds g WHERE g.id = r.guild_id AND g.revision_retention_days = $1 AND r.id < $2",
+            period.revision_retention_days,
+            u128_to_bigdecimal!(revision_cutoff(period.revision_retention_days))
+        )
+        .execute(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Start the background task that deletes expired message revisions.
+pub fn init_revision_pruner() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(REVISION_PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if prune_revisions().await.is_err() {
+                warn!("failed to prune message revisions");
+            }
+        }
+    });
+}
 
 pub fn generate_messages_route() -> axum::Router {
     debug!("generating routes for messages");
@@ -29,4 +101,28 @@ pub fn generate_messages_route() -> axum::Router {
             expand_version!("channels/:channel_id/messages/:message_id"),
             get(get_message).patch(edit_message).delete(delete_message),
         )
+        // GET    /channels/:channel_id/messages/:message_id/revisions
+        .route(
+            expand_version!("channels/:channel_id/messages/:message_id/revisions"),
+            get(get_message_revisions),
+        )
+        // POST   /channels/:channel_id/messages/:message_id/ack
+        .route(
+            expand_version!("channels/:channel_id/messages/:message_id/ack"),
+            post(ack_message),
+        )
+        // POST   /channels/:channel_id/messages/bulk-delete
+        .route(
+            expand_version!("channels/:channel_id/messages/bulk-delete"),
+            post(bulk_delete_messages),
+        )
+        // GET    /guilds/:guild_id/messages/search
+        .route(
+            expand_version!("guilds/:guild_id/messages/search"),
+            get(search_messages),
+        )
+        // reactions routes
+        .merge(reactions::generate_reactions_routes())
+        // attachments routes
+        .merge(attachments::generate_attachments_routes())
 }
@@ -0,0 +1,118 @@
+use crate::WebServerError;
+use ferrischat_common::types::ErrorJson;
+use ferrischat_redis::deadpool_redis::Connection;
+use ferrischat_redis::redis::{self, AsyncCommands};
+
+/// Seconds a nonce is remembered for after its message was created.
+/// Replaying the nonce within this time returns the original message instead of creating a new one.
+pub const NONCE_TTL_SECS: usize = 600;
+
+/// Seconds a nonce stays claimed while its message is being created,
+/// so a request that dies part way through doesn't block the nonce for long.
+pub const PENDING_NONCE_TTL_SECS: usize = 30;
+
+/// Maximum length of a message nonce, in bytes.
+pub const MAX_NONCE_LENGTH: usize = 64;
+
+const PENDING: &str = "pending";
+
+/// The outcome of trying to claim a nonce for a new message.
+pub enum NonceClaim {
+    /// The nonce is new, and the message should be created.
+    Claimed,
+    /// A message was already created with the nonce.
+    Created(u128),
+    /// Another request with the nonce is still creating its message.
+    Pending,
+}
+
+fn nonce_key(author_id: u128, channel_id: u128, nonce: &str) -> String {
+    format!("message_nonce:{}:{}:{}", author_id, channel_id, nonce)
+}
+
+async fn get_redis() -> Result<Connection, WebServerError> {
+    Ok(ferrischat_redis::REDIS_MANAGER
+        .get()
+        .ok_or(WebServerError::MissingRedis)?
+        .get()
+        .await?)
+}
+
+/// Claim `nonce` for a message `author_id` is creating in `channel_id`.
+///
+/// If the nonce is claimed, it must later be completed with [`complete_nonce`] or given up with [`release_nonce`].
+///
+/// # Errors
+/// Returns a HTTP 400 if the nonce is too long.
+/// Returns a HTTP 500 if Redis returns an error.
+pub async fn claim_nonce(
+    author_id: u128,
+    channel_id: u128,
+    nonce: &str,
+) -> Result<NonceClaim, WebServerError> {
+    if nonce.len() > MAX_NONCE_LENGTH {
+        return Err(ErrorJson::new_400(format!(
+            "nonce must be at most {} bytes",
+            MAX_NONCE_LENGTH
+        ))
+        .into());
+    }
+
+    let key = nonce_key(author_id, channel_id, nonce);
+    let mut conn = get_redis().await?;
+
+    // SET NX makes sure only one of several concurrent retries gets to create the message
+    let claimed: Option<String> = redis::cmd("SET")
+        .arg(&key)
+        .arg(PENDING)
+        .arg("NX")
+        .arg("EX")
+        .arg(PENDING_NONCE_TTL_SECS)
+        .query_async(&mut conn)
+        .await?;
+    if claimed.is_some() {
+        return Ok(NonceClaim::Claimed);
+    }
+
+    let existing: Option<String> = conn.get(&key).await?;
+    Ok(existing
+        .and_then(|v| v.parse().ok())
+        .map_or(NonceClaim::Pending, NonceClaim::Created))
+}
+
+/// Record that the message with `nonce` was created as `message_id`.
+///
+/// # Errors
+/// Returns a HTTP 500 if Redis returns an error.
+pub async fn complete_nonce(
+    author_id: u128,
+    channel_id: u128,
+    nonce: &str,
+    message_id: u128,
+) -> Result<(), WebServerError> {
+    get_redis()
+        .await?
+        .set_ex::<_, _, ()>(
+            nonce_key(author_id, channel_id, nonce),
+            message_id.to_string(),
+            NONCE_TTL_SECS,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Give up a claimed `nonce` because its message couldn't be created, so it can be retried.
+///
+/// # Errors
+/// Returns a HTTP 500 if Redis returns an error.
+pub async fn release_nonce(
+    author_id: u128,
+    channel_id: u128,
+    nonce: &str,
+) -> Result<(), WebServerError> {
+    get_redis()
+        .await?
+        .del::<_, ()>(nonce_key(author_id, channel_id, nonce))
+        .await?;
+    Ok(())
+}
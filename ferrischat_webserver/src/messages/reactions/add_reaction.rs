@@ -0,0 +1,54 @@
+use super::{require_message, validate_emoji};
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::MessageReaction;
+use ferrischat_common::ws::WsOutboundEvent;
+use http::StatusCode;
+
+/// PUT `/v0/channels/{channel_id}/messages/{message_id}/reactions/{emoji}/me`
+pub async fn add_reaction(
+    Path((channel_id, message_id, emoji)): Path<(u128, u128, String)>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) = crate::require_channel_permissions(
+        channel_id,
+        auth_user,
+        GuildPermissions::READ_MESSAGES | GuildPermissions::SEND_MESSAGES,
+    )
+    .await?;
+
+    validate_emoji(&emoji)?;
+    require_message(db, channel_id, message_id).await?;
+
+    let added = sqlx::query!(
+        "INSERT INTO reactions (message_id, user_id, emoji) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        u128_to_bigdecimal!(message_id),
+        u128_to_bigdecimal!(auth_user),
+        emoji
+    )
+    .execute(db)
+    .await?
+    .rows_affected()
+        > 0;
+
+    // reacting twice with the same emoji is a no-op
+    if added {
+        let event = WsOutboundEvent::MessageReactionAdd {
+            reaction: MessageReaction {
+                guild_id,
+                channel_id,
+                message_id,
+                user_id: auth_user,
+                emoji,
+            },
+        };
+
+        fire_event(&event).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
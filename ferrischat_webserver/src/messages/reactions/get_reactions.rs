@@ -0,0 +1,49 @@
+use super::{require_message, validate_emoji};
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{Pronouns, User, UserFlags};
+use num_traits::ToPrimitive;
+
+/// GET `/v0/channels/{channel_id}/messages/{message_id}/reactions/{emoji}`
+///
+/// Lists everyone who reacted to the message with `emoji`, in the order they reacted.
+pub async fn get_reactions(
+    Path((channel_id, message_id, emoji)): Path<(u128, u128, String)>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<crate::Json<Vec<User>>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_channel_permissions(channel_id, auth_user, GuildPermissions::READ_MESSAGES)
+        .await?;
+
+    validate_emoji(&emoji)?;
+    require_message(db, channel_id, message_id).await?;
+
+    let users = sqlx::query!(
+        "SELECT u.* FROM reactions r INNER JOIN users u ON u.id = r.user_id WHERE r.message_id = $1 AND r.emoji = $2 ORDER BY r.created_at",
+        u128_to_bigdecimal!(message_id),
+        emoji
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .filter_map(|x| {
+        Some(User {
+            id: x.id.with_scale(0).into_bigint_and_exponent().0.to_u128()?,
+            name: x.name,
+            avatar: x.avatar,
+            guilds: None,
+            flags: UserFlags::from_bits_truncate(x.flags),
+            discriminator: x.discriminator,
+            pronouns: x.pronouns.and_then(Pronouns::from_i16),
+            is_bot: UserFlags::from_bits_truncate(x.flags).contains(UserFlags::BOT_ACCOUNT),
+        })
+    })
+    .collect();
+
+    Ok(crate::Json {
+        obj: users,
+        code: 200,
+    })
+}
@@ -0,0 +1,116 @@
+mod add_reaction;
+mod get_reactions;
+mod remove_reaction;
+
+pub use add_reaction::*;
+pub use get_reactions::*;
+pub use remove_reaction::*;
+
+use crate::WebServerError;
+use axum::routing::{get, put};
+use axum::Router;
+use ferrischat_common::types::{ErrorJson, Reaction};
+use num_traits::ToPrimitive;
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+
+/// Maximum length of a reaction emoji, in bytes.
+pub const MAX_EMOJI_LENGTH: usize = 64;
+
+pub fn generate_reactions_routes() -> axum::Router {
+    debug!("generating routes for reactions");
+    Router::new()
+        // GET    /channels/:channel_id/messages/:message_id/reactions/:emoji
+        .route(
+            expand_version!("channels/:channel_id/messages/:message_id/reactions/:emoji"),
+            get(get_reactions),
+        )
+        // PUT    /channels/:channel_id/messages/:message_id/reactions/:emoji/me
+        // DELETE /channels/:channel_id/messages/:message_id/reactions/:emoji/me
+        .route(
+            expand_version!("channels/:channel_id/messages/:message_id/reactions/:emoji/me"),
+            put(add_reaction).delete(remove_reaction),
+        )
+}
+
+/// Make sure `emoji` is something that can be reacted with.
+fn validate_emoji(emoji: &str) -> Result<(), WebServerError> {
+    if emoji.is_empty() || emoji.len() > MAX_EMOJI_LENGTH || emoji.contains(char::is_whitespace) {
+        return Err(ErrorJson::new_400(format!(
+            "emoji must be between 1 and {} bytes long and may not contain whitespace",
+            MAX_EMOJI_LENGTH
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Make sure `message_id` exists in `channel_id`.
+async fn require_message(
+    db: &Pool<Postgres>,
+    channel_id: u128,
+    message_id: u128,
+) -> Result<(), WebServerError> {
+    sqlx::query!(
+        "SELECT id FROM messages WHERE id = $1 AND channel_id = $2",
+        u128_to_bigdecimal!(message_id),
+        u128_to_bigdecimal!(channel_id)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown message with ID {}", message_id)))?;
+    Ok(())
+}
+
+/// Fetch the reactions on each of `message_ids`, as seen by `user_id`.
+///
+/// Reactions are ordered by when the first of them was added.
+/// Messages without any reactions are left out.
+pub async fn get_message_reactions(
+    db: &Pool<Postgres>,
+    message_ids: &[u128],
+    user_id: u128,
+) -> Result<HashMap<u128, Vec<Reaction>>, WebServerError> {
+    let bigdecimal_message_ids: Vec<_> = message_ids
+        .iter()
+        .map(|id| u128_to_bigdecimal!(*id))
+        .collect();
+
+    let resp = sqlx::query!(
+        r#"
+SELECT message_id,
+       emoji,
+       COUNT(*) AS "count!",
+       bool_or(user_id = $2) AS "me!"
+FROM reactions
+WHERE message_id = ANY($1)
+GROUP BY message_id, emoji
+ORDER BY MIN(created_at)
+"#,
+        &bigdecimal_message_ids[..],
+        u128_to_bigdecimal!(user_id)
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut reactions: HashMap<u128, Vec<Reaction>> = HashMap::new();
+    for x in resp {
+        let message_id = match x
+            .message_id
+            .with_scale(0)
+            .into_bigint_and_exponent()
+            .0
+            .to_u128()
+        {
+            Some(id) => id,
+            None => continue,
+        };
+        reactions.entry(message_id).or_default().push(Reaction {
+            emoji: x.emoji,
+            count: x.count,
+            me: x.me,
+        });
+    }
+
+    Ok(reactions)
+}
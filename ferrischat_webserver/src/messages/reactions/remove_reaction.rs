@@ -0,0 +1,51 @@
+use super::{require_message, validate_emoji};
+use crate::ws::fire_event;
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{ErrorJson, MessageReaction};
+use ferrischat_common::ws::WsOutboundEvent;
+use http::StatusCode;
+
+/// DELETE `/v0/channels/{channel_id}/messages/{message_id}/reactions/{emoji}/me`
+pub async fn remove_reaction(
+    Path((channel_id, message_id, emoji)): Path<(u128, u128, String)>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let (guild_id, _) =
+        crate::require_channel_permissions(channel_id, auth_user, GuildPermissions::READ_MESSAGES)
+            .await?;
+
+    validate_emoji(&emoji)?;
+    require_message(db, channel_id, message_id).await?;
+
+    sqlx::query!(
+        "DELETE FROM reactions WHERE message_id = $1 AND user_id = $2 AND emoji = $3 RETURNING emoji",
+        u128_to_bigdecimal!(message_id),
+        u128_to_bigdecimal!(auth_user),
+        emoji
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| {
+        ErrorJson::new_404(format!(
+            "Unknown reaction {} on message {}",
+            emoji, message_id
+        ))
+    })?;
+
+    let event = WsOutboundEvent::MessageReactionRemove {
+        reaction: MessageReaction {
+            guild_id,
+            channel_id,
+            message_id,
+            user_id: auth_user,
+            emoji,
+        },
+    };
+
+    fire_event(&event).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,85 @@
+use super::mentions::{to_bigdecimals, Mentions};
+use crate::WebServerError;
+use sqlx::{Pool, Postgres, Transaction};
+
+/// Count the messages in `channel_id` after `after` that mention `user_id`,
+/// either directly, through one of their roles in `guild_id`, or by mentioning everyone.
+///
+/// In private channels, which have no `guild_id`, every message counts as a mention.
+/// The user's own messages are never counted.
+pub async fn count_unread_mentions(
+    db: &Pool<Postgres>,
+    channel_id: u128,
+    guild_id: Option<u128>,
+    user_id: u128,
+    after: u128,
+) -> Result<i32, WebServerError> {
+    let count = sqlx::query!(
+        r#"SELECT COUNT(*)::int4 AS "count!" FROM messages WHERE channel_id = $1 AND id > $2 AND author_id != $3 AND ($4::numeric(39) IS NULL OR $3 = ANY(mentions) OR mention_everyone OR mention_roles && ARRAY(SELECT role_id FROM role_data WHERE user_id = $3 AND guild_id = $4))"#,
+        u128_to_bigdecimal!(channel_id),
+        u128_to_bigdecimal!(after),
+        u128_to_bigdecimal!(user_id),
+        guild_id.map(|g| u128_to_bigdecimal!(g))
+    )
+    .fetch_one(db)
+    .await?
+    .count;
+
+    Ok(count)
+}
+
+/// Update read states for a new message: everyone it mentions gets another unread mention,
+/// and the author has read up to it.
+///
+/// In private channels, which have no `guild_id`, every other recipient is mentioned.
+pub async fn record_new_message(
+    tx: &mut Transaction<'_, Postgres>,
+    channel_id: u128,
+    guild_id: Option<u128>,
+    author_id: u128,
+    message_id: u128,
+    mentions: &Mentions,
+) -> Result<(), WebServerError> {
+    let bigdecimal_channel_id = u128_to_bigdecimal!(channel_id);
+    let bigdecimal_author_id = u128_to_bigdecimal!(author_id);
+
+    match guild_id {
+        Some(guild_id)
+            if mentions.everyone || !mentions.users.is_empty() || !mentions.roles.is_empty() =>
+        {
+            sqlx::query!(
+                "INSERT INTO read_states (user_id, channel_id, mention_count) SELECT m.user_id, $1, 1 FROM members m WHERE m.guild_id = $2 AND m.user_id != $3 AND ($4 OR m.user_id = ANY($5) OR EXISTS (SELECT 1 FROM role_data r WHERE r.guild_id = $2 AND r.user_id = m.user_id AND r.role_id = ANY($6))) ON CONFLICT (user_id, channel_id) DO UPDATE SET mention_count = read_states.mention_count + 1",
+                bigdecimal_channel_id,
+                u128_to_bigdecimal!(guild_id),
+                bigdecimal_author_id,
+                mentions.everyone,
+                &to_bigdecimals(&mentions.users)[..],
+                &to_bigdecimals(&mentions.roles)[..]
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        Some(_) => {}
+        None => {
+            sqlx::query!(
+                "INSERT INTO read_states (user_id, channel_id, mention_count) SELECT user_id, channel_id, 1 FROM channel_recipients WHERE channel_id = $1 AND user_id != $2 ON CONFLICT (user_id, channel_id) DO UPDATE SET mention_count = read_states.mention_count + 1",
+                bigdecimal_channel_id,
+                bigdecimal_author_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    // nobody needs to be told about their own messages
+    sqlx::query!(
+        "INSERT INTO read_states VALUES ($1, $2, $3, 0) ON CONFLICT (user_id, channel_id) DO UPDATE SET last_read_id = EXCLUDED.last_read_id, mention_count = 0",
+        bigdecimal_author_id,
+        bigdecimal_channel_id,
+        u128_to_bigdecimal!(message_id)
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(())
+}
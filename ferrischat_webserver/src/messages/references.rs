@@ -0,0 +1,132 @@
+use crate::WebServerError;
+use ferrischat_common::types::{Channel, Message, Pronouns, User, UserFlags};
+use num_traits::ToPrimitive;
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+
+/// Maximum length of the content of a referenced message embedded in a reply, in bytes.
+///
+/// Longer content is cut off, and clients should fetch the full message if they need it.
+pub const MAX_REFERENCED_CONTENT_LENGTH: usize = 256;
+
+fn truncate_content(mut content: String) -> String {
+    if content.len() > MAX_REFERENCED_CONTENT_LENGTH {
+        let mut end = MAX_REFERENCED_CONTENT_LENGTH;
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        content.truncate(end);
+    }
+    content
+}
+
+/// Resolve the messages in `channel` that replies refer to.
+///
+/// Resolved messages have their content truncated to [`MAX_REFERENCED_CONTENT_LENGTH`],
+/// and do not have their own references resolved.
+/// References to messages that have since been deleted are left out,
+/// so callers should keep the reference ID and leave the referenced message empty.
+///
+/// # Errors
+/// Returns a HTTP 500 if the database returns an error.
+pub async fn get_referenced_messages(
+    db: &Pool<Postgres>,
+    channel: &Channel,
+    reference_ids: &[u128],
+) -> Result<HashMap<u128, Message>, WebServerError> {
+    if reference_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let bigdecimal_reference_ids: Vec<_> = reference_ids
+        .iter()
+        .map(|id| u128_to_bigdecimal!(*id))
+        .collect();
+
+    let resp = sqlx::query!(
+        r#"
+SELECT m.*,
+       a.name AS author_name,
+       a.avatar AS avatar,
+       a.flags AS author_flags,
+       a.discriminator AS author_discriminator,
+       a.pronouns AS author_pronouns
+FROM messages m
+    CROSS JOIN LATERAL (
+        SELECT *
+        FROM users
+        WHERE id = m.author_id
+        ) AS a
+WHERE m.id = ANY($1)
+  AND m.channel_id = $2
+"#,
+        &bigdecimal_reference_ids[..],
+        u128_to_bigdecimal!(channel.id)
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(resp
+        .into_iter()
+        .filter_map(|x| {
+            let id = x.id.with_scale(0).into_bigint_and_exponent().0.to_u128()?;
+            let author_id = x
+                .author_id
+                .with_scale(0)
+                .into_bigint_and_exponent()
+                .0
+                .to_u128()?;
+            let message = Message {
+                id,
+                content: x.content.map(truncate_content),
+                channel: channel.clone(),
+                channel_id: channel.id,
+                author_id,
+                author: Some(User {
+                    id: author_id,
+                    name: x.author_name,
+                    avatar: x.avatar,
+                    guilds: None,
+                    flags: UserFlags::from_bits_truncate(x.author_flags),
+                    discriminator: x.author_discriminator,
+                    pronouns: x.author_pronouns.and_then(Pronouns::from_i16),
+                    is_bot: UserFlags::from_bits_truncate(x.author_flags)
+                        .contains(UserFlags::BOT_ACCOUNT),
+                }),
+                edited_at: x.edited_at,
+                embeds: vec![],
+                nonce: None,
+                reactions: None,
+                reference_id: x
+                    .reference_id
+                    .and_then(|r| r.with_scale(0).into_bigint_and_exponent().0.to_u128()),
+                referenced_message: None,
+                mentions: super::mentions::from_bigdecimals(x.mentions),
+                mention_roles: super::mentions::from_bigdecimals(x.mention_roles),
+                mention_everyone: x.mention_everyone,
+                attachments: None,
+            };
+            Some((id, message))
+        })
+        .collect())
+}
+
+/// Resolve the message a single reply in `channel` refers to, if it still exists.
+///
+/// See [`get_referenced_messages`] for how the message is resolved.
+///
+/// # Errors
+/// Returns a HTTP 500 if the database returns an error.
+pub async fn get_referenced_message(
+    db: &Pool<Postgres>,
+    channel: &Channel,
+    reference_id: Option<u128>,
+) -> Result<Option<Box<Message>>, WebServerError> {
+    Ok(match reference_id {
+        Some(id) => get_referenced_messages(db, channel, &[id])
+            .await?
+            .remove(&id)
+            .map(Box::new),
+        None => None,
+    })
+}
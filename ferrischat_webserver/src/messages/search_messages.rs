@@ -0,0 +1,246 @@
+use super::attachments::get_message_attachments;
+use super::reactions::get_message_reactions;
+use super::references::get_referenced_messages;
+use super::snowflake_at;
+use crate::embeds::get_message_embeds;
+use crate::WebServerError;
+use axum::extract::{Path, Query};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::request_json::{MessageSearchHas, MessageSearchParams};
+use ferrischat_common::types::{
+    Channel, ChannelType, ErrorJson, Message, MessageSearchResults, Pronouns, User, UserFlags,
+};
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+
+/// Maximum number of results returned in a single page of search results.
+const MAX_SEARCH_LIMIT: i64 = 100;
+
+/// GET `/v0/guilds/{guild_id}/messages/search`
+///
+/// Results only include messages in channels the caller can read, newest first.
+/// Passing `mentions` only returns messages that mention that user.
+pub async fn search_messages(
+    Path(guild_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+    Query(MessageSearchParams {
+        query,
+        author_id,
+        channel_id,
+        mentions: mentioned_id,
+        before,
+        after,
+        has,
+        limit,
+        offset,
+    }): Query<MessageSearchParams>,
+) -> Result<crate::Json<MessageSearchResults>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    crate::require_guild_permissions(guild_id, auth_user, GuildPermissions::empty()).await?;
+
+    if query.trim().is_empty() {
+        return Err(ErrorJson::new_400("search query must not be empty".to_string()).into());
+    }
+    let limit = limit.unwrap_or(25);
+    if !(1..=MAX_SEARCH_LIMIT).contains(&limit) {
+        return Err(ErrorJson::new_400(format!(
+            "limit must be between 1 and {}",
+            MAX_SEARCH_LIMIT
+        ))
+        .into());
+    }
+    let offset = offset.unwrap_or(0).max(0);
+
+    // work out which channels the caller can read, narrowed down to the one they asked for if any
+    let channel_perms = ferrischat_perms::get_all_channel_permissions(db, guild_id, auth_user)
+        .await?
+        .unwrap_or_default();
+    let mut channels = HashMap::new();
+    for c in sqlx::query!(
+        "SELECT * FROM channels WHERE guild_id = $1",
+        u128_to_bigdecimal!(guild_id)
+    )
+    .fetch_all(db)
+    .await?
+    {
+        let id = bigdecimal_to_u128!(c.id);
+        if channel_id.map_or(false, |channel_id| channel_id != id) {
+            continue;
+        }
+        let readable = channel_perms
+            .get(&id)
+            .map_or(false, |p| p.contains(GuildPermissions::READ_MESSAGES));
+        if readable {
+            let channel_type = ChannelType::from_i16(c.channel_type).unwrap_or_default();
+            channels.insert(
+                id,
+                Channel {
+                    id,
+                    name: c.name,
+                    guild_id: Some(guild_id),
+                    channel_type,
+                    parent_id: match c.parent_id {
+                        Some(p) => Some(bigdecimal_to_u128!(p)),
+                        None => None,
+                    },
+                    position: c.position,
+                    topic: c.topic,
+                    nsfw: c.nsfw,
+                    owner_id: match c.owner_id {
+                        Some(o) => Some(bigdecimal_to_u128!(o)),
+                        None => None,
+                    },
+                    recipients: None,
+                    thread_metadata: crate::channels::threads::thread_metadata(
+                        channel_type,
+                        c.archived,
+                        c.auto_archive_minutes,
+                        c.starter_message_id,
+                    ),
+                    permission_overwrites: None,
+                },
+            );
+        }
+    }
+    if let Some(channel_id) = channel_id {
+        if !channels.contains_key(&channel_id) {
+            return Err(
+                ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)).into(),
+            );
+        }
+    }
+    let channel_ids: Vec<_> = channels.keys().map(|id| u128_to_bigdecimal!(*id)).collect();
+
+    let resp = sqlx::query!(
+        r#"
+SELECT m.*,
+       a.name AS author_name,
+       a.avatar AS avatar,
+       a.flags AS author_flags,
+       a.discriminator AS author_discriminator,
+       a.pronouns AS author_pronouns,
+       COUNT(*) OVER () AS "total!"
+FROM messages m
+    CROSS JOIN LATERAL (
+        SELECT *
+        FROM users
+        WHERE id = m.author_id
+        ) AS a
+WHERE m.channel_id = ANY($1)
+  AND to_tsvector('english', coalesce(m.content, '')) @@ websearch_to_tsquery('english', $2)
+  AND ($3::numeric(39) IS NULL OR m.author_id = $3)
+  AND ($4::numeric(39) IS NULL OR m.id < $4)
+  AND ($5::numeric(39) IS NULL OR m.id >= $5)
+  AND (NOT $6 OR m.content ~* 'https?://')
+  AND (NOT $7 OR m.reference_id IS NOT NULL)
+  AND (NOT $8 OR EXISTS(SELECT * FROM reactions r WHERE r.message_id = m.id))
+  AND (NOT $9 OR EXISTS(SELECT * FROM pins p WHERE p.message_id = m.id))
+  AND ($12::numeric(39) IS NULL OR $12 = ANY(m.mentions))
+ORDER BY m.id DESC
+LIMIT $10 OFFSET $11
+"#,
+        &channel_ids[..],
+        query,
+        author_id.map(|id| u128_to_bigdecimal!(id)),
+        before.map(|t| u128_to_bigdecimal!(snowflake_at(t.saturating_mul(1000)))),
+        after.map(|t| u128_to_bigdecimal!(snowflake_at(t.saturating_mul(1000)))),
+        has == Some(MessageSearchHas::Link),
+        has == Some(MessageSearchHas::Reply),
+        has == Some(MessageSearchHas::Reaction),
+        has == Some(MessageSearchHas::Pin),
+        limit,
+        offset,
+        mentioned_id.map(|id| u128_to_bigdecimal!(id)),
+    )
+    .fetch_all(db)
+    .await?;
+
+    let total = resp.first().map_or(0, |x| x.total);
+
+    let message_ids: Vec<_> = resp
+        .iter()
+        .filter_map(|x| x.id.with_scale(0).into_bigint_and_exponent().0.to_u128())
+        .collect();
+    let mut reactions = get_message_reactions(db, &message_ids, auth_user).await?;
+    let mut attachments = get_message_attachments(db, &message_ids).await?;
+    let mut embeds = get_message_embeds(db, &message_ids).await?;
+
+    // references are always to messages in the same channel, so resolve them channel by channel
+    let mut reference_ids: HashMap<u128, Vec<u128>> = HashMap::new();
+    for x in &resp {
+        let channel_id = x
+            .channel_id
+            .with_scale(0)
+            .into_bigint_and_exponent()
+            .0
+            .to_u128();
+        let reference_id = x
+            .reference_id
+            .as_ref()
+            .and_then(|r| r.with_scale(0).into_bigint_and_exponent().0.to_u128());
+        if let (Some(channel_id), Some(reference_id)) = (channel_id, reference_id) {
+            reference_ids
+                .entry(channel_id)
+                .or_default()
+                .push(reference_id);
+        }
+    }
+    let mut referenced_messages = HashMap::new();
+    for (channel_id, ids) in reference_ids {
+        if let Some(channel) = channels.get(&channel_id) {
+            referenced_messages.extend(get_referenced_messages(db, channel, &ids).await?);
+        }
+    }
+
+    let mut messages = Vec::with_capacity(resp.len());
+    for x in resp {
+        let id = bigdecimal_to_u128!(x.id);
+        let channel_id = bigdecimal_to_u128!(x.channel_id);
+        let author_id = bigdecimal_to_u128!(x.author_id);
+        let reference_id = match x.reference_id {
+            Some(r) => Some(bigdecimal_to_u128!(r)),
+            None => None,
+        };
+        let channel = match channels.get(&channel_id) {
+            Some(c) => c.clone(),
+            None => continue,
+        };
+
+        messages.push(Message {
+            id,
+            content: x.content,
+            channel,
+            channel_id,
+            author_id,
+            author: Some(User {
+                id: author_id,
+                name: x.author_name,
+                avatar: x.avatar,
+                guilds: None,
+                flags: UserFlags::from_bits_truncate(x.author_flags),
+                discriminator: x.author_discriminator,
+                pronouns: x.author_pronouns.and_then(Pronouns::from_i16),
+                is_bot: UserFlags::from_bits_truncate(x.author_flags)
+                    .contains(UserFlags::BOT_ACCOUNT),
+            }),
+            edited_at: x.edited_at,
+            embeds: embeds.remove(&id).unwrap_or_default(),
+            nonce: None,
+            reactions: Some(reactions.remove(&id).unwrap_or_default()),
+            reference_id,
+            referenced_message: reference_id
+                .and_then(|r| referenced_messages.get(&r).cloned())
+                .map(Box::new),
+            mentions: super::mentions::from_bigdecimals(x.mentions),
+            mention_roles: super::mentions::from_bigdecimals(x.mention_roles),
+            mention_everyone: x.mention_everyone,
+            attachments: Some(attachments.remove(&id).unwrap_or_default()),
+        });
+    }
+
+    Ok(crate::Json {
+        obj: MessageSearchResults { messages, total },
+        code: 200,
+    })
+}
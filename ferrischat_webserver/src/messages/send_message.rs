@@ -1,18 +1,30 @@
+use super::attachments::MessageCreateBody;
+use super::nonce::{claim_nonce, complete_nonce, release_nonce, NonceClaim};
 use crate::ws::fire_event;
 use crate::WebServerError;
-use axum::extract::{Json, Path};
+use axum::extract::Path;
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::request_json::MessageCreateJson;
-use ferrischat_common::types::{Channel, ErrorJson, Message, ModelType, User, UserFlags};
+use ferrischat_common::types::{
+    Attachment, ChannelType, ErrorJson, Message, ModelType, User, UserFlags,
+};
 use ferrischat_common::ws::WsOutboundEvent;
 use ferrischat_snowflake_generator::generate_snowflake;
 
 /// POST `/v0/channels/{channel_id}/messages`
+///
+/// If a nonce is passed and a message was already created with it recently,
+/// that message is returned with a 200 instead of a new one being created.
 pub async fn create_message(
     crate::Authorization(user_id, is_bot): crate::Authorization,
-    json: Json<MessageCreateJson>,
     Path(channel_id): Path<u128>,
+    MessageCreateBody { json, attachments }: MessageCreateBody,
 ) -> Result<crate::Json<Message>, WebServerError> {
-    let MessageCreateJson { content, nonce } = json.0;
+    let MessageCreateJson {
+        content,
+        nonce,
+        reference_id,
+    } = json;
 
     if content.len() > 10240 {
         return Err(ErrorJson::new_400(
@@ -37,29 +49,144 @@ pub async fn create_message(
 
     let db = get_db_or_fail!();
 
-    let channel = sqlx::query!(
-        "SELECT * FROM channels WHERE id = $1",
-        bigdecimal_channel_id
-    )
-    .fetch_optional(db)
-    .await?
-    .ok_or_else(|| ErrorJson::new_404("channel not found".to_string()))?;
-
-    let channel_obj = Channel {
-        id: channel_id,
-        name: channel.name,
-        guild_id: bigdecimal_to_u128!(channel.guild_id),
-    };
+    crate::require_channel_access(channel_id, user_id, GuildPermissions::SEND_MESSAGES).await?;
 
-    sqlx::query!(
-        "INSERT INTO messages VALUES ($1, $2, $3, $4)",
-        bigdecimal_message_id,
-        content,
-        bigdecimal_channel_id,
-        bigdecimal_author_id
-    )
-    .execute(db)
-    .await?;
+    let channel_obj = crate::channels::fetch_channel(db, channel_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404("channel not found".to_string()))?;
+
+    // blocking someone closes any direct message with them, but leaves group ones alone
+    if channel_obj.channel_type == ChannelType::DirectMessage {
+        let others: Vec<u128> = crate::channels::private::get_recipients(db, channel_id)
+            .await?
+            .into_iter()
+            .map(|u| u.id)
+            .filter(|id| *id != user_id)
+            .collect();
+        if crate::users::has_block_between(db, user_id, &others).await? {
+            return Err(ErrorJson::new_403(
+                "you can't message users you've blocked or who have blocked you".to_string(),
+            )
+            .into());
+        }
+    }
+
+    // replies can only refer to messages in the same channel
+    if let Some(reference_id) = reference_id {
+        let reference_channel_id = sqlx::query!(
+            "SELECT channel_id FROM messages WHERE id = $1",
+            u128_to_bigdecimal!(reference_id)
+        )
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown message with ID {}", reference_id)))?
+        .channel_id;
+        if reference_channel_id != bigdecimal_channel_id {
+            return Err(ErrorJson::new_400(
+                "replies must be in the same channel as the message they refer to".to_string(),
+            )
+            .into());
+        }
+    }
+    let referenced_message =
+        super::references::get_referenced_message(db, &channel_obj, reference_id).await?;
+
+    let mentions = super::mentions::check_mentions(db, &channel_obj, user_id, &content).await?;
+
+    if let Some(ref nonce) = nonce {
+        match claim_nonce(user_id, channel_id, nonce).await? {
+            NonceClaim::Claimed => {}
+            NonceClaim::Created(original_id) => {
+                let mut original = super::fetch_message(channel_id, original_id, user_id).await?;
+                original.nonce = Some(nonce.clone());
+                return Ok(crate::Json {
+                    obj: original,
+                    code: 200,
+                });
+            }
+            NonceClaim::Pending => {
+                return Err(ErrorJson::new_409(
+                    "a message with this nonce is still being created".to_string(),
+                )
+                .into())
+            }
+        }
+    }
+
+    // files are stored before the message exists, so it never refers to a missing file
+    let mut stored_ids = Vec::new();
+    let stored = async {
+        let storage = crate::storage::get_storage()?;
+        let mut attachment_objs = Vec::with_capacity(attachments.len());
+        for attachment in attachments {
+            let attachment_id = generate_snowflake::<0>(ModelType::Attachment as u8, node_id);
+            storage
+                .put(&attachment_id.to_string(), attachment.data.clone())
+                .await?;
+            stored_ids.push(u128_to_bigdecimal!(attachment_id));
+            attachment_objs.push(Attachment {
+                id: attachment_id,
+                message_id,
+                filename: attachment.filename,
+                content_type: attachment.content_type,
+                size: i64::try_from(attachment.data.len()).unwrap_or(i64::MAX),
+            });
+        }
+
+        let mut tx = db.begin().await?;
+        sqlx::query!(
+            "INSERT INTO messages (id, content, channel_id, author_id, reference_id, mentions, mention_roles, mention_everyone) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            bigdecimal_message_id,
+            content,
+            bigdecimal_channel_id,
+            bigdecimal_author_id,
+            reference_id.map(|r| u128_to_bigdecimal!(r)),
+            &super::mentions::to_bigdecimals(&mentions.users)[..],
+            &super::mentions::to_bigdecimals(&mentions.roles)[..],
+            mentions.everyone
+        )
+        .execute(&mut tx)
+        .await?;
+        super::read_states::record_new_message(
+            &mut tx,
+            channel_id,
+            channel_obj.guild_id,
+            author_id,
+            message_id,
+            &mentions,
+        )
+        .await?;
+        for attachment in &attachment_objs {
+            sqlx::query!(
+                "INSERT INTO attachments VALUES ($1, $2, $3, $4, $5)",
+                u128_to_bigdecimal!(attachment.id),
+                bigdecimal_message_id,
+                attachment.filename,
+                attachment.content_type,
+                attachment.size
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok::<_, WebServerError>(attachment_objs)
+    }
+    .await;
+    let attachment_objs = match stored {
+        Ok(a) => a,
+        Err(e) => {
+            // the message was never created, so nothing refers to the files stored for it
+            super::attachments::delete_stored_attachments(stored_ids).await;
+            // let the client retry with the same nonce, since the message doesn't exist
+            if let Some(ref nonce) = nonce {
+                release_nonce(user_id, channel_id, nonce).await?;
+            }
+            return Err(e);
+        }
+    };
+    if let Some(ref nonce) = nonce {
+        complete_nonce(user_id, channel_id, nonce, message_id).await?;
+    }
 
     let r = sqlx::query!("SELECT * FROM users WHERE id = $1", bigdecimal_author_id)
         .fetch_one(db)
@@ -87,6 +214,13 @@ pub async fn create_message(
         edited_at: None,
         embeds: vec![],
         nonce,
+        reactions: None,
+        reference_id,
+        referenced_message,
+        mentions: mentions.users,
+        mention_roles: mentions.roles,
+        mention_everyone: mentions.everyone,
+        attachments: Some(attachment_objs),
     };
 
     let event = WsOutboundEvent::MessageCreate {
@@ -94,6 +228,21 @@ pub async fn create_message(
     };
 
     fire_event(&event).await?;
+    crate::embeds::queue_unfurl(&msg_obj);
+    // the message is what they were typing, so they're done,
+    // but it's already been sent, so failing to say so isn't worth an error
+    if let Some(ref author) = msg_obj.author {
+        if crate::channels::typing::clear_typing(&msg_obj.channel, author)
+            .await
+            .is_err()
+        {
+            warn!(
+                "failed to clear typing indicator of {} in {}",
+                author.id, msg_obj.channel_id
+            );
+        }
+    }
+    crate::channels::threads::record_thread_activity(db, &msg_obj.channel, author_id).await?;
 
     Ok(crate::Json {
         obj: msg_obj,
@@ -0,0 +1,169 @@
+use crate::WebServerError;
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::ErrorJson;
+use ferrischat_perms::Rank;
+
+/// Fetch the permissions `user_id` has in `guild_id`, and make sure they include everything in `required`.
+///
+/// # Errors
+/// Returns a HTTP 403 if the user is not a member of the guild or is missing any of `required`.
+pub async fn require_guild_permissions(
+    guild_id: u128,
+    user_id: u128,
+    required: GuildPermissions,
+) -> Result<GuildPermissions, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let perms = ferrischat_perms::get_guild_permissions(db, guild_id, user_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_403("you are not a member of this guild".to_string()))?;
+
+    if perms.contains(required) {
+        Ok(perms)
+    } else {
+        Err(ErrorJson::new_403(format!("missing permissions: {:?}", required - perms)).into())
+    }
+}
+
+/// What recipients of a private channel can do in it.
+pub const PRIVATE_CHANNEL_PERMISSIONS: GuildPermissions = GuildPermissions::from_bits_truncate(
+    GuildPermissions::READ_MESSAGES.bits() | GuildPermissions::SEND_MESSAGES.bits(),
+);
+
+/// Same as [`require_guild_permissions`], but for the channel `channel_id`,
+/// taking the channel's permission overwrites into account.
+///
+/// Returns the ID of the guild the channel belongs to along with the user's permissions in the channel.
+///
+/// # Errors
+/// Returns a HTTP 404 if the channel does not exist, a HTTP 400 if it is a private channel,
+/// and a HTTP 403 under the same conditions as [`require_guild_permissions`].
+pub async fn require_channel_permissions(
+    channel_id: u128,
+    user_id: u128,
+    required: GuildPermissions,
+) -> Result<(u128, GuildPermissions), WebServerError> {
+    match require_channel_access(channel_id, user_id, required).await? {
+        (Some(guild_id), perms) => Ok((guild_id, perms)),
+        (None, _) => {
+            Err(ErrorJson::new_400("this can't be done in a private channel".to_string()).into())
+        }
+    }
+}
+
+/// Same as [`require_channel_permissions`], but private channels are allowed too,
+/// where recipients have [`PRIVATE_CHANNEL_PERMISSIONS`].
+///
+/// Returns the ID of the guild the channel belongs to, or `None` if it is a private channel,
+/// along with the user's permissions in the channel.
+///
+/// # Errors
+/// Returns a HTTP 404 if the channel does not exist, and a HTTP 403 if the user is not a member
+/// of its guild or a recipient of the private channel, or is missing any of `required`.
+pub async fn require_channel_access(
+    channel_id: u128,
+    user_id: u128,
+    required: GuildPermissions,
+) -> Result<(Option<u128>, GuildPermissions), WebServerError> {
+    let db = get_db_or_fail!();
+
+    let channel = sqlx::query!(
+        r#"SELECT guild_id, EXISTS(SELECT 1 FROM channel_recipients WHERE channel_id = $1 AND user_id = $2) AS "is_recipient!" FROM channels WHERE id = $1"#,
+        u128_to_bigdecimal!(channel_id),
+        u128_to_bigdecimal!(user_id)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("Unknown channel with ID {}", channel_id)))?;
+
+    let (guild_id, perms) = match channel.guild_id {
+        Some(guild_id) => {
+            let guild_id = bigdecimal_to_u128!(guild_id);
+            let perms =
+                ferrischat_perms::get_channel_permissions(db, guild_id, channel_id, user_id)
+                    .await?
+                    .ok_or_else(|| {
+                        ErrorJson::new_403("you are not a member of this guild".to_string())
+                    })?;
+            (Some(guild_id), perms)
+        }
+        None if channel.is_recipient => (None, PRIVATE_CHANNEL_PERMISSIONS),
+        None => {
+            return Err(
+                ErrorJson::new_403("you are not a recipient of this channel".to_string()).into(),
+            )
+        }
+    };
+
+    if perms.contains(required) {
+        Ok((guild_id, perms))
+    } else {
+        Err(ErrorJson::new_403(format!("missing permissions: {:?}", required - perms)).into())
+    }
+}
+
+/// Make sure `user_id` ranks strictly above `other` in `guild_id`'s role hierarchy.
+///
+/// # Errors
+/// Returns a HTTP 403 if the user is not a member of the guild or does not outrank `other`.
+pub async fn require_rank_above(
+    guild_id: u128,
+    user_id: u128,
+    other: Rank,
+) -> Result<(), WebServerError> {
+    let rank = ferrischat_perms::get_rank(get_db_or_fail!(), guild_id, user_id)
+        .await?
+        .ok_or_else(|| ErrorJson::new_403("you are not a member of this guild".to_string()))?;
+
+    if rank > other {
+        Ok(())
+    } else {
+        Err(ErrorJson::new_403(
+            "you cannot manage roles or members ranked at or above your highest role".to_string(),
+        )
+        .into())
+    }
+}
+
+/// Make sure `held` includes every permission in `granted`,
+/// so that nobody can hand out permissions they don't have themselves.
+///
+/// The guild owner and administrators hold every permission, so this never stops them.
+///
+/// # Errors
+/// Returns a HTTP 403 if any of `granted` is missing from `held`.
+pub fn require_can_grant(
+    held: GuildPermissions,
+    granted: GuildPermissions,
+) -> Result<(), WebServerError> {
+    if held.contains(granted) {
+        Ok(())
+    } else {
+        Err(ErrorJson::new_403(format!(
+            "you cannot grant permissions you don't have: {:?}",
+            granted - held
+        ))
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_only_grant_held_permissions() {
+        let held = GuildPermissions::MANAGE_ROLES | GuildPermissions::SEND_MESSAGES;
+        assert!(require_can_grant(held, GuildPermissions::empty()).is_ok());
+        assert!(require_can_grant(held, GuildPermissions::SEND_MESSAGES).is_ok());
+        assert!(require_can_grant(held, held).is_ok());
+        assert!(require_can_grant(held, GuildPermissions::ADMINISTRATOR).is_err());
+        assert!(require_can_grant(
+            held,
+            GuildPermissions::SEND_MESSAGES | GuildPermissions::MENTION_EVERYONE
+        )
+        .is_err());
+        // owners and administrators resolve to every permission
+        assert!(require_can_grant(GuildPermissions::all(), GuildPermissions::all()).is_ok());
+    }
+}
@@ -0,0 +1,56 @@
+use super::Storage;
+use bytes::Bytes;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+/// A [`Storage`] backend that keeps each file under its key in a directory on the local filesystem.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Create a backend rooted at `root`. The directory is created when the first file is stored.
+    #[must_use]
+    pub const fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path_for(&self, key: &str) -> std::io::Result<PathBuf> {
+        if super::is_valid_key(key) {
+            Ok(self.root.join(key))
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid storage key: {:?}", key),
+            ))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: Bytes) -> std::io::Result<()> {
+        let path = self.path_for(key)?;
+        tokio::fs::create_dir_all(&self.root).await?;
+
+        // write to a temporary file first so readers never see a partially written file
+        let tmp_path = self.root.join(format!(".{}.tmp", key));
+        tokio::fs::write(&tmp_path, &data).await?;
+        tokio::fs::rename(&tmp_path, &path).await
+    }
+
+    async fn get(&self, key: &str) -> std::io::Result<Option<Bytes>> {
+        match tokio::fs::read(self.path_for(key)?).await {
+            Ok(data) => Ok(Some(Bytes::from(data))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> std::io::Result<()> {
+        match tokio::fs::remove_file(self.path_for(key)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+mod local;
+
+pub use local::LocalStorage;
+
+use crate::WebServerError;
+use bytes::Bytes;
+use std::lazy::SyncOnceCell as OnceCell;
+
+/// The storage backend user uploaded files are kept in.
+pub static STORAGE: OnceCell<Box<dyn Storage>> = OnceCell::new();
+
+/// A place to keep user uploaded files in, such as message attachments.
+///
+/// Files are identified by keys made up of ASCII letters, digits, `-` and `_`.
+/// Implementations should reject any other key, so they can be used as path components as-is.
+#[async_trait::async_trait]
+pub trait Storage: Send + Sync {
+    /// Store `data` under `key`, replacing anything already stored there.
+    async fn put(&self, key: &str, data: Bytes) -> std::io::Result<()>;
+
+    /// Fetch the data stored under `key`, or `None` if there isn't any.
+    async fn get(&self, key: &str) -> std::io::Result<Option<Bytes>>;
+
+    /// Remove the data stored under `key`. Removing a key that doesn't exist is not an error.
+    async fn delete(&self, key: &str) -> std::io::Result<()>;
+}
+
+/// Whether `key` is valid for use with a [`Storage`] backend.
+#[must_use]
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Set up the global storage backend, keeping files under `$FERRISCHAT_HOME/storage`.
+///
+/// # Panics
+/// If the storage backend was already set up.
+pub fn init_storage() {
+    let root = std::path::PathBuf::from(
+        std::env::var("FERRISCHAT_HOME").unwrap_or_else(|_| "/etc/ferrischat/".to_string()),
+    )
+    .join("storage");
+
+    if STORAGE.set(Box::new(LocalStorage::new(root))).is_err() {
+        panic!("storage was already set up: did you call init_storage() twice?");
+    }
+}
+
+/// Get the global storage backend.
+///
+/// # Errors
+/// Returns an error if [`init_storage`] hasn't been called yet.
+pub fn get_storage() -> Result<&'static dyn Storage, WebServerError> {
+    STORAGE
+        .get()
+        .map(AsRef::as_ref)
+        .ok_or(WebServerError::MissingStorage)
+}
@@ -0,0 +1,34 @@
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::types::ErrorJson;
+
+/// PUT `/v0/users/me/blocks/{user_id}`
+///
+/// Blocked users can't start private channels with, or send direct messages to, the user blocking them.
+/// Blocking a user that's already blocked does nothing.
+pub async fn block_user(
+    Path(user_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<http::StatusCode, WebServerError> {
+    if user_id == auth_user {
+        return Err(ErrorJson::new_400("you can't block yourself".to_string()).into());
+    }
+
+    let db = get_db_or_fail!();
+    let bigdecimal_user_id = u128_to_bigdecimal!(user_id);
+
+    sqlx::query!("SELECT id FROM users WHERE id = $1", bigdecimal_user_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| ErrorJson::new_404(format!("Unknown user with ID {}", user_id)))?;
+
+    sqlx::query!(
+        "INSERT INTO user_blocks VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        u128_to_bigdecimal!(auth_user),
+        bigdecimal_user_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(http::StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,37 @@
+use crate::WebServerError;
+use ferrischat_common::types::{Pronouns, User, UserFlags};
+use num_traits::ToPrimitive;
+
+/// GET `/v0/users/me/blocks`
+pub async fn get_blocks(
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<crate::Json<Vec<User>>, WebServerError> {
+    let db = get_db_or_fail!();
+
+    let users = sqlx::query!(
+        "SELECT u.* FROM user_blocks b INNER JOIN users u ON u.id = b.blocked_id WHERE b.user_id = $1",
+        u128_to_bigdecimal!(auth_user)
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .filter_map(|u| {
+        let flags = UserFlags::from_bits_truncate(u.flags);
+        Some(User {
+            id: u.id.with_scale(0).into_bigint_and_exponent().0.to_u128()?,
+            name: u.name,
+            avatar: u.avatar,
+            guilds: None,
+            flags,
+            discriminator: u.discriminator,
+            pronouns: u.pronouns.and_then(Pronouns::from_i16),
+            is_bot: flags.contains(UserFlags::BOT_ACCOUNT),
+        })
+    })
+    .collect();
+
+    Ok(crate::Json {
+        obj: users,
+        code: 200,
+    })
+}
@@ -0,0 +1,28 @@
+mod block_user;
+mod get_blocks;
+mod unblock_user;
+
+pub use block_user::*;
+pub use get_blocks::*;
+pub use unblock_user::*;
+
+use crate::WebServerError;
+use sqlx::{Pool, Postgres};
+
+/// Whether `user_id` has blocked, or been blocked by, any of `others`.
+pub async fn has_block_between(
+    db: &Pool<Postgres>,
+    user_id: u128,
+    others: &[u128],
+) -> Result<bool, WebServerError> {
+    let others: Vec<_> = others.iter().map(|id| u128_to_bigdecimal!(*id)).collect();
+
+    Ok(sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM user_blocks WHERE (user_id = $1 AND blocked_id = ANY($2)) OR (blocked_id = $1 AND user_id = ANY($2))) AS "exists!""#,
+        u128_to_bigdecimal!(user_id),
+        &others[..]
+    )
+    .fetch_one(db)
+    .await?
+    .exists)
+}
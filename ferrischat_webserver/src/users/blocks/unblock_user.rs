@@ -0,0 +1,22 @@
+use crate::WebServerError;
+use axum::extract::Path;
+use ferrischat_common::types::ErrorJson;
+
+/// DELETE `/v0/users/me/blocks/{user_id}`
+pub async fn unblock_user(
+    Path(user_id): Path<u128>,
+    crate::Authorization(auth_user, _): crate::Authorization,
+) -> Result<http::StatusCode, WebServerError> {
+    let db = get_db_or_fail!();
+
+    sqlx::query!(
+        "DELETE FROM user_blocks WHERE user_id = $1 AND blocked_id = $2 RETURNING blocked_id",
+        u128_to_bigdecimal!(auth_user),
+        u128_to_bigdecimal!(user_id)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ErrorJson::new_404(format!("user {} is not blocked", user_id)))?;
+
+    Ok(http::StatusCode::NO_CONTENT)
+}
@@ -24,9 +24,13 @@ pub async fn delete_bot(
         return Err(ErrorJson::new_403("you are not the owner of this bot".to_string()).into());
     }
 
-    sqlx::query!("DELETE FROM users WHERE id = $1", bigdecimal_user_id)
-        .execute(db)
-        .await?;
+    let deleted = sqlx::query!(
+        r#"DELETE FROM users WHERE id = $1 RETURNING ARRAY(SELECT a.id FROM attachments a INNER JOIN messages m ON m.id = a.message_id WHERE m.author_id = users.id) AS "attachment_ids!""#,
+        bigdecimal_user_id
+    )
+    .fetch_one(db)
+    .await?;
+    crate::messages::attachments::delete_stored_attachments(deleted.attachment_ids).await;
 
     Ok(http::StatusCode::NO_CONTENT)
 }
@@ -68,6 +68,7 @@ pub async fn invite_bot(
         }),
         guild_id: Some(guild_id),
         guild: None,
+        nickname: None,
     };
 
     let event = WsOutboundEvent::MemberCreate {
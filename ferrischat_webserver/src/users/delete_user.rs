@@ -20,13 +20,14 @@ pub async fn delete_user(
     let db = get_db_or_fail!();
 
     // Drop the user.
-    sqlx::query!(
-        "DELETE FROM users WHERE id = $1 RETURNING (id)",
+    let deleted = sqlx::query!(
+        r#"DELETE FROM users WHERE id = $1 RETURNING (id), ARRAY(SELECT a.id FROM attachments a INNER JOIN messages m ON m.id = a.message_id WHERE m.author_id = users.id) AS "attachment_ids!""#,
         bigdecimal_user_id,
     )
     .fetch_optional(db)
     .await?
     .ok_or_else(|| ErrorJson::new_404("account not found".to_string()))?;
+    crate::messages::attachments::delete_stored_attachments(deleted.attachment_ids).await;
 
     Ok(http::StatusCode::NO_CONTENT)
 }
@@ -1,6 +1,8 @@
 use crate::WebServerError;
 use axum::extract::Path;
-use ferrischat_common::types::{Channel, ErrorJson, Guild, GuildFlags, Member, User, UserFlags};
+use ferrischat_common::types::{
+    Channel, ChannelType, ErrorJson, Guild, GuildFlags, Member, User, UserFlags,
+};
 use num_traits::cast::ToPrimitive;
 
 /// GET `/v0/users/{user_id}`
@@ -87,8 +89,9 @@ pub async fn get_user(
                         name: x.name.clone(),
                         channels: Some(
                             sqlx::query!(
-                                "SELECT * FROM channels WHERE guild_id = $1",
-                                x.id.clone()
+                                "SELECT * FROM channels WHERE guild_id = $1 AND channel_type != $2 ORDER BY position, id",
+                                x.id.clone(),
+                                ChannelType::Thread as i16
                             )
                             .fetch_all(db)
                             .await?
@@ -102,12 +105,19 @@ pub async fn get_user(
                                         .0
                                         .to_u128()?,
                                     name: x.name.clone(),
-                                    guild_id: x
-                                        .guild_id
-                                        .with_scale(0)
-                                        .into_bigint_and_exponent()
-                                        .0
-                                        .to_u128()?,
+                                    guild_id: Some(id),
+                                    channel_type: ChannelType::from_i16(x.channel_type)
+                                        .unwrap_or_default(),
+                                    parent_id: x.parent_id.as_ref().and_then(|p| {
+                                        p.with_scale(0).into_bigint_and_exponent().0.to_u128()
+                                    }),
+                                    position: x.position,
+                                    topic: x.topic.clone(),
+                                    nsfw: x.nsfw,
+                                    owner_id: None,
+                                    recipients: None,
+                                    thread_metadata: None,
+                                    permission_overwrites: None,
                                 })
                             })
                             .collect(),
@@ -163,6 +173,7 @@ pub async fn get_user(
                                             .0
                                             .to_u128(),
                                         guild: None,
+                                        nickname: x.nickname,
                                     };
 
                                     members.push(member);
@@ -171,6 +182,7 @@ pub async fn get_user(
                             })
                         },
                         roles: None,
+                        revision_retention_days: None,
                     };
                     guilds.push(g);
                 }
@@ -1,3 +1,4 @@
+mod blocks;
 mod bots;
 mod create_user;
 mod delete_user;
@@ -6,6 +7,7 @@ mod get_me;
 mod get_user;
 mod verify_user;
 
+pub use blocks::*;
 pub use bots::*;
 pub use create_user::*;
 pub use delete_user::*;
@@ -14,7 +16,7 @@ pub use get_me::*;
 pub use get_user::*;
 pub use verify_user::*;
 
-use axum::routing::{get, patch, post};
+use axum::routing::{get, patch, post, put};
 use axum::Router;
 
 pub fn generate_users_route() -> axum::Router {
@@ -48,6 +50,14 @@ pub fn generate_users_route() -> axum::Router {
             expand_version!("users/me/bots/:bot_id"),
             patch(edit_bot).delete(delete_bot),
         )
+        // GET    /users/me/blocks
+        .route(expand_version!("users/me/blocks"), get(get_blocks))
+        // PUT    /users/me/blocks/:user_id
+        // DELETE /users/me/blocks/:user_id
+        .route(
+            expand_version!("users/me/blocks/:user_id"),
+            put(block_user).delete(unblock_user),
+        )
         // POST /bots/:bot_id/add/:guild_id
         .route(
             expand_version!("bots/:bot_id/add/:guild_id"),
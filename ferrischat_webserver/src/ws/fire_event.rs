@@ -1,5 +1,7 @@
 use crate::WebServerError;
-use ferrischat_common::types::{Channel, Guild, Invite, Member, Message, Role};
+use ferrischat_common::types::{
+    Channel, Guild, Invite, Member, Message, MessagePin, MessageReaction, Role, ThreadMember,
+};
 use ferrischat_common::ws::WsOutboundEvent;
 use ferrischat_redis::redis::AsyncCommands;
 
@@ -10,41 +12,36 @@ fn get_event_name(event: &WsOutboundEvent) -> String {
          * Message Events *
          *****************/
         WsOutboundEvent::MessageDelete {
-            message:
-                Message {
-                    channel: Channel { guild_id, .. },
-                    ..
-                },
+            message: Message { channel, .. },
         }
         | WsOutboundEvent::MessageUpdate {
-            old:
-                Message {
-                    channel: Channel { guild_id, .. },
-                    ..
-                },
+            old: Message { channel, .. },
             ..
         }
         | WsOutboundEvent::MessageCreate {
-            message:
-                Message {
-                    channel: Channel { guild_id, .. },
-                    ..
-                },
-        } => format!("message_{}", *guild_id),
+            message: Message { channel, .. },
+        } => channel_scoped_name("message", channel),
+        // reactions, pins and bulk deletes can only happen in guild channels
+        WsOutboundEvent::MessageReactionAdd {
+            reaction: MessageReaction { guild_id, .. },
+        }
+        | WsOutboundEvent::MessageReactionRemove {
+            reaction: MessageReaction { guild_id, .. },
+        }
+        | WsOutboundEvent::MessagePin {
+            pin: MessagePin { guild_id, .. },
+        }
+        | WsOutboundEvent::MessageUnpin {
+            pin: MessagePin { guild_id, .. },
+        }
+        | WsOutboundEvent::MessageBulkDelete { guild_id, .. } => format!("message_{}", *guild_id),
 
         /*****************
          * Channel Events *
          *****************/
-        WsOutboundEvent::ChannelCreate {
-            channel: Channel { guild_id, .. },
-        }
-        | WsOutboundEvent::ChannelUpdate {
-            old: Channel { guild_id, .. },
-            ..
-        }
-        | WsOutboundEvent::ChannelDelete {
-            channel: Channel { guild_id, .. },
-        } => format!("channel_{}", *guild_id),
+        WsOutboundEvent::ChannelCreate { channel }
+        | WsOutboundEvent::ChannelUpdate { old: channel, .. }
+        | WsOutboundEvent::ChannelDelete { channel } => channel_scoped_name("channel", channel),
 
         /***************
          * Guild Events *
@@ -128,14 +125,8 @@ fn get_event_name(event: &WsOutboundEvent) -> String {
         /****************
          * Typing Events *
          ****************/
-        WsOutboundEvent::TypingStart {
-            channel: Channel { guild_id, .. },
-            ..
-        }
-        | WsOutboundEvent::TypingEnd {
-            channel: Channel { guild_id, .. },
-            ..
-        } => format!("typing_{}", guild_id),
+        WsOutboundEvent::TypingStart { channel, .. }
+        | WsOutboundEvent::TypingEnd { channel, .. } => channel_scoped_name("typing", channel),
 
         /*********************
          * Member Role Events *
@@ -149,10 +140,40 @@ fn get_event_name(event: &WsOutboundEvent) -> String {
             ..
         } => format!("member_role_{}", guild_id),
 
+        /********************
+         * Read State Events *
+         ********************/
+        // read states are private, so these are only sent to the user's own sessions
+        WsOutboundEvent::ReadStateUpdate { user_id, .. } => format!("read_state_{}", user_id),
+
+        /****************
+         * Thread Events *
+         ****************/
+        WsOutboundEvent::ThreadCreate { thread }
+        | WsOutboundEvent::ThreadUpdate { old: thread, .. } => {
+            channel_scoped_name("thread", thread)
+        }
+        WsOutboundEvent::ThreadMemberAdd {
+            member: ThreadMember { guild_id, .. },
+        }
+        | WsOutboundEvent::ThreadMemberRemove {
+            member: ThreadMember { guild_id, .. },
+        } => format!("thread_{}", guild_id),
+
         _ => panic!("called `fire_event` with an unsupported event type"),
     }
 }
 
+/// Events in a guild channel go to everyone in the guild who can see the channel,
+/// and events in a private channel go to its recipients.
+#[inline]
+fn channel_scoped_name(kind: &str, channel: &Channel) -> String {
+    match channel.guild_id {
+        Some(guild_id) => format!("{}_{}", kind, guild_id),
+        None => format!("private_{}", channel.id),
+    }
+}
+
 pub async fn fire_event(event: &WsOutboundEvent) -> Result<(), WebServerError> {
     let event_name = get_event_name(event);
     let message = simd_json::to_vec(event)?;
@@ -167,3 +188,26 @@ pub async fn fire_event(event: &WsOutboundEvent) -> Result<(), WebServerError> {
         .map_err(WebServerError::from)
         .map(|_| ())
 }
+
+/// Send `event` to every session of each of `user_ids`, whatever they're subscribed to.
+///
+/// This is for events about private channels that the users may not be subscribed to yet,
+/// such as one being created, or that they've just left.
+pub async fn fire_event_to_users(
+    event: &WsOutboundEvent,
+    user_ids: &[u128],
+) -> Result<(), WebServerError> {
+    let message = simd_json::to_vec(event)?;
+
+    let mut conn = ferrischat_redis::REDIS_MANAGER
+        .get()
+        .ok_or(WebServerError::MissingRedis)?
+        .get()
+        .await?;
+    for user_id in user_ids {
+        conn.publish::<_, _, Option<u32>>(format!("user_{}", user_id), &message[..])
+            .await?;
+    }
+
+    Ok(())
+}
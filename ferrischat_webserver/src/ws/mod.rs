@@ -1,7 +1,7 @@
 mod fire_event;
 mod info;
 
-pub use fire_event::fire_event;
+pub use fire_event::{fire_event, fire_event_to_users};
 pub use info::ws_info;
 
 use axum::routing::get;
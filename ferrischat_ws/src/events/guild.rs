@@ -1,6 +1,7 @@
+use crate::perms::has_guild_permissions;
+use ferrischat_common::perms::GuildPermissions;
 use ferrischat_common::ws::WsOutboundEvent;
 use sqlx::{Pool, Postgres};
-use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 
 pub async fn handle_guild_tx<'a>(
@@ -9,28 +10,14 @@ pub async fn handle_guild_tx<'a>(
     user_id: u128,
     guild_id: u128,
 ) -> Result<bool, CloseFrame<'a>> {
-    // FIXME: once implemented, do a query to check the user has permissions to read messages in here
-    let bigint_user_id = u128_to_bigdecimal!(user_id);
-    let bigint_guild_id = u128_to_bigdecimal!(guild_id);
-
     match msg {
         WsOutboundEvent::GuildDelete { .. } => Ok(true),
-        _ => {
-            match sqlx::query!(
-                "SELECT user_id FROM members WHERE user_id = $1 AND guild_id = $2",
-                bigint_user_id,
-                bigint_guild_id
-            )
-            .fetch_optional(db)
-            .await
-            {
-                Ok(Some(_)) => Ok(true),
-                Ok(None) => Ok(false),
-                Err(e) => Err(CloseFrame {
-                    code: CloseCode::from(5000),
-                    reason: format!("Internal database error: {}", e).into(),
-                }),
-            }
-        }
+        _ => has_guild_permissions(db, guild_id, user_id, GuildPermissions::empty()).await,
     }
 }
+
+/// `GuildCreate` is published on a channel keyed by the owner's ID,
+/// since nobody is subscribed to the guild itself yet.
+pub fn handle_guild_create_tx(msg: &WsOutboundEvent, user_id: u128, owner_id: u128) -> bool {
+    matches!(msg, WsOutboundEvent::GuildCreate { .. }) && user_id == owner_id
+}
@@ -1,7 +1,7 @@
 use crate::error_handling::WsEventHandlerError;
 use dashmap::DashMap;
 use ferrischat_auth::{split_token, verify_token};
-use ferrischat_common::types::UserFlags;
+use ferrischat_common::types::{ChannelType, ReadState, UserFlags};
 use ferrischat_common::ws::{Intents, WsOutboundEvent};
 use num_traits::ToPrimitive;
 use sqlx::{Pool, Postgres};
@@ -117,6 +117,7 @@ pub async fn handle_identify_rx<'a>(
                                 }),
                                 guild_id: Some(id),
                                 guild: None,
+                                nickname: x.nickname.clone(),
                             })
                         })
                         .collect(),
@@ -124,27 +125,42 @@ pub async fn handle_identify_rx<'a>(
             };
 
             let channels = {
-                let resp = sqlx::query!("SELECT * FROM channels WHERE guild_id = $1", x.id.clone())
-                    .fetch_all(db)
-                    .await?;
-
-                Some(
-                    resp.iter()
-                        .filter_map(|x| {
-                            Some(ferrischat_common::types::Channel {
-                                id: x
-                                    .id
-                                    .clone()
-                                    .with_scale(0)
-                                    .into_bigint_and_exponent()
-                                    .0
-                                    .to_u128()?,
-                                name: x.name.clone(),
-                                guild_id: id,
-                            })
-                        })
-                        .collect(),
+                let resp = sqlx::query!(
+                    "SELECT * FROM channels WHERE guild_id = $1 AND channel_type != $2 ORDER BY position, id",
+                    x.id.clone(),
+                    ChannelType::Thread as i16
                 )
+                .fetch_all(db)
+                .await?;
+
+                let mut channels = Vec::with_capacity(resp.len());
+                for x in resp {
+                    let channel_id = match x.id.with_scale(0).into_bigint_and_exponent().0.to_u128()
+                    {
+                        Some(id) => id,
+                        None => continue,
+                    };
+
+                    channels.push(ferrischat_common::types::Channel {
+                        id: channel_id,
+                        name: x.name,
+                        guild_id: Some(id),
+                        channel_type: ChannelType::from_i16(x.channel_type).unwrap_or_default(),
+                        parent_id: x
+                            .parent_id
+                            .and_then(|p| p.with_scale(0).into_bigint_and_exponent().0.to_u128()),
+                        position: x.position,
+                        topic: x.topic,
+                        nsfw: x.nsfw,
+                        owner_id: None,
+                        recipients: None,
+                        thread_metadata: None,
+                        permission_overwrites: Some(
+                            ferrischat_perms::get_permission_overwrites(db, channel_id).await?,
+                        ),
+                    });
+                }
+                Some(channels)
             };
 
             guilds.push(ferrischat_common::types::Guild {
@@ -154,8 +170,9 @@ pub async fn handle_identify_rx<'a>(
                 channels,
                 flags: ferrischat_common::types::GuildFlags::from_bits_truncate(flags),
                 members,
-                roles: None,
+                roles: Some(ferrischat_perms::get_guild_roles(db, id).await?),
                 icon,
+                revision_retention_days: None,
             });
         }
         Some(guilds)
@@ -182,8 +199,91 @@ pub async fn handle_identify_rx<'a>(
         }
     };
 
+    let private_channels = {
+        let resp = sqlx::query!(
+            "SELECT c.* FROM channels c INNER JOIN channel_recipients r ON r.channel_id = c.id WHERE r.user_id = $1 ORDER BY c.id DESC",
+            bigdecimal_user_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let mut channels = Vec::with_capacity(resp.len());
+        for x in resp {
+            let channel_id = match x.id.with_scale(0).into_bigint_and_exponent().0.to_u128() {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let recipients = sqlx::query!(
+                "SELECT u.* FROM channel_recipients r INNER JOIN users u ON u.id = r.user_id WHERE r.channel_id = $1 ORDER BY u.id",
+                x.id
+            )
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .filter_map(|u| {
+                let flags = UserFlags::from_bits_truncate(u.flags);
+                Some(ferrischat_common::types::User {
+                    id: u.id.with_scale(0).into_bigint_and_exponent().0.to_u128()?,
+                    name: u.name,
+                    avatar: u.avatar,
+                    guilds: None,
+                    flags,
+                    discriminator: u.discriminator,
+                    pronouns: u
+                        .pronouns
+                        .and_then(ferrischat_common::types::Pronouns::from_i16),
+                    is_bot: flags.contains(UserFlags::BOT_ACCOUNT),
+                })
+            })
+            .collect();
+
+            channels.push(ferrischat_common::types::Channel {
+                id: channel_id,
+                name: x.name,
+                guild_id: None,
+                channel_type: ChannelType::from_i16(x.channel_type).unwrap_or_default(),
+                parent_id: None,
+                position: x.position,
+                topic: x.topic,
+                nsfw: x.nsfw,
+                owner_id: x
+                    .owner_id
+                    .and_then(|o| o.with_scale(0).into_bigint_and_exponent().0.to_u128()),
+                recipients: Some(recipients),
+                thread_metadata: None,
+                permission_overwrites: None,
+            });
+        }
+        channels
+    };
+
+    // read states for channels the user can no longer see are kept, but not sent
+    let read_states = sqlx::query!(
+        "SELECT r.* FROM read_states r INNER JOIN channels c ON c.id = r.channel_id WHERE r.user_id = $1 AND (EXISTS (SELECT 1 FROM members m WHERE m.guild_id = c.guild_id AND m.user_id = r.user_id) OR EXISTS (SELECT 1 FROM channel_recipients cr WHERE cr.channel_id = c.id AND cr.user_id = r.user_id))",
+        bigdecimal_user_id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .filter_map(|x| {
+        Some(ReadState {
+            channel_id: x.channel_id.with_scale(0).into_bigint_and_exponent().0.to_u128()?,
+            last_read_id: match x.last_read_id {
+                Some(id) => Some(id.with_scale(0).into_bigint_and_exponent().0.to_u128()?),
+                None => None,
+            },
+            mention_count: x.mention_count,
+        })
+    })
+    .collect();
+
     inter_tx
-        .send(WsOutboundEvent::IdentifyAccepted { user })
+        .send(WsOutboundEvent::IdentifyAccepted {
+            user,
+            private_channels,
+            read_states,
+        })
         .await
         .as_ref()?;
 
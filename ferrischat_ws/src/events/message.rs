@@ -1,31 +1,49 @@
+use crate::perms::{has_channel_permissions, has_guild_permissions};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{Message, MessagePin, MessageReaction};
 use ferrischat_common::ws::WsOutboundEvent;
 use sqlx::{Pool, Postgres};
-use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 
 pub async fn handle_message_tx<'a>(
     db: &Pool<Postgres>,
-    _msg: &WsOutboundEvent,
+    msg: &WsOutboundEvent,
     user_id: u128,
     guild_id: u128,
 ) -> Result<bool, CloseFrame<'a>> {
-    // FIXME: once implemented, do a query to check the user has permissions to read messages in here
-    let bigint_user_id = u128_to_bigdecimal!(user_id);
-    let bigint_guild_id = u128_to_bigdecimal!(guild_id);
-
-    match sqlx::query!(
-        "SELECT guild_id FROM members WHERE user_id = $1 AND guild_id = $2",
-        bigint_user_id,
-        bigint_guild_id
-    )
-    .fetch_optional(db)
-    .await
-    {
-        Ok(Some(_)) => Ok(true),
-        Ok(None) => Ok(false),
-        Err(e) => Err(CloseFrame {
-            code: CloseCode::from(5000),
-            reason: format!("Internal database error: {}", e).into(),
-        }),
+    match msg {
+        WsOutboundEvent::MessageCreate {
+            message: Message { channel_id, .. },
+        }
+        | WsOutboundEvent::MessageUpdate {
+            new: Message { channel_id, .. },
+            ..
+        }
+        | WsOutboundEvent::MessageDelete {
+            message: Message { channel_id, .. },
+        }
+        | WsOutboundEvent::MessageReactionAdd {
+            reaction: MessageReaction { channel_id, .. },
+        }
+        | WsOutboundEvent::MessageReactionRemove {
+            reaction: MessageReaction { channel_id, .. },
+        }
+        | WsOutboundEvent::MessagePin {
+            pin: MessagePin { channel_id, .. },
+        }
+        | WsOutboundEvent::MessageUnpin {
+            pin: MessagePin { channel_id, .. },
+        }
+        | WsOutboundEvent::MessageBulkDelete { channel_id, .. } => {
+            has_channel_permissions(
+                db,
+                guild_id,
+                *channel_id,
+                user_id,
+                GuildPermissions::READ_MESSAGES,
+            )
+            .await
+        }
+        _ => has_guild_permissions(db, guild_id, user_id, GuildPermissions::READ_MESSAGES).await,
     }
 }
@@ -3,15 +3,27 @@ mod guild;
 mod identify;
 mod invite;
 mod member;
+mod member_role;
 mod message;
 mod ping;
 mod pong;
+mod private_channel;
+mod read_state;
+mod role;
+mod thread;
+mod typing;
 
 pub use channel::*;
 pub use guild::*;
 pub use identify::*;
 pub use invite::*;
 pub use member::*;
+pub use member_role::*;
 pub use message::*;
 pub use ping::*;
 pub use pong::*;
+pub use private_channel::*;
+pub use read_state::*;
+pub use role::*;
+pub use thread::*;
+pub use typing::*;
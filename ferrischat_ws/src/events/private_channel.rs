@@ -0,0 +1,32 @@
+use ferrischat_common::ws::WsOutboundEvent;
+use sqlx::{Pool, Postgres};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+/// Events in private channels are published on a channel keyed by the channel's ID,
+/// and go to whoever is still one of its recipients.
+pub async fn handle_private_tx<'a>(
+    db: &Pool<Postgres>,
+    _: &WsOutboundEvent,
+    user_id: u128,
+    channel_id: u128,
+) -> Result<bool, CloseFrame<'a>> {
+    sqlx::query!(
+        "SELECT user_id FROM channel_recipients WHERE channel_id = $1 AND user_id = $2",
+        u128_to_bigdecimal!(channel_id),
+        u128_to_bigdecimal!(user_id)
+    )
+    .fetch_optional(db)
+    .await
+    .map(|r| r.is_some())
+    .map_err(|e| CloseFrame {
+        code: CloseCode::from(5000),
+        reason: format!("Internal database error: {}", e).into(),
+    })
+}
+
+/// Events published on a channel keyed by a user's ID only ever go to that user,
+/// for things they can't be subscribed to yet, like being added to a private channel.
+pub fn handle_user_tx(user_id: u128, target_id: u128) -> bool {
+    user_id == target_id
+}
@@ -0,0 +1,7 @@
+use ferrischat_common::ws::WsOutboundEvent;
+
+/// Read states are published on a channel keyed by the user's ID,
+/// and only ever go to that user's own sessions.
+pub fn handle_read_state_tx(msg: &WsOutboundEvent, user_id: u128, owner_id: u128) -> bool {
+    matches!(msg, WsOutboundEvent::ReadStateUpdate { .. }) && user_id == owner_id
+}
@@ -0,0 +1,19 @@
+use crate::perms::{meets_requirement, Requirement};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::ws::WsOutboundEvent;
+use sqlx::{Pool, Postgres};
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+/// Roles are visible to everyone in their guild.
+pub fn role_requirement(_msg: &WsOutboundEvent) -> Requirement {
+    Requirement::guild(GuildPermissions::empty())
+}
+
+pub async fn handle_role_tx<'a>(
+    db: &Pool<Postgres>,
+    msg: &WsOutboundEvent,
+    user_id: u128,
+    guild_id: u128,
+) -> Result<bool, CloseFrame<'a>> {
+    meets_requirement(db, guild_id, user_id, role_requirement(msg)).await
+}
@@ -0,0 +1,34 @@
+use crate::perms::{has_channel_permissions, has_guild_permissions};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::{Channel, ThreadMember};
+use ferrischat_common::ws::WsOutboundEvent;
+use sqlx::{Pool, Postgres};
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+/// Thread events go to everyone who can read the thread, which is everyone who can read its parent.
+pub async fn handle_thread_tx<'a>(
+    db: &Pool<Postgres>,
+    msg: &WsOutboundEvent,
+    user_id: u128,
+    guild_id: u128,
+) -> Result<bool, CloseFrame<'a>> {
+    match msg {
+        WsOutboundEvent::ThreadCreate {
+            thread: Channel { id, .. },
+        }
+        | WsOutboundEvent::ThreadUpdate {
+            new: Channel { id, .. },
+            ..
+        }
+        | WsOutboundEvent::ThreadMemberAdd {
+            member: ThreadMember { thread_id: id, .. },
+        }
+        | WsOutboundEvent::ThreadMemberRemove {
+            member: ThreadMember { thread_id: id, .. },
+        } => {
+            has_channel_permissions(db, guild_id, *id, user_id, GuildPermissions::READ_MESSAGES)
+                .await
+        }
+        _ => has_guild_permissions(db, guild_id, user_id, GuildPermissions::READ_MESSAGES).await,
+    }
+}
@@ -1,29 +1,30 @@
+use crate::perms::{meets_requirement, Requirement};
+use ferrischat_common::perms::GuildPermissions;
+use ferrischat_common::types::Channel;
 use ferrischat_common::ws::WsOutboundEvent;
-use sqlx::types::BigDecimal;
 use sqlx::{Pool, Postgres};
-use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 
+/// Someone typing is only shown to those who can read the channel they're typing in.
+pub fn typing_requirement(msg: &WsOutboundEvent) -> Requirement {
+    match msg {
+        WsOutboundEvent::TypingStart {
+            channel: Channel { id, .. },
+            ..
+        }
+        | WsOutboundEvent::TypingEnd {
+            channel: Channel { id, .. },
+            ..
+        } => Requirement::channel(*id, GuildPermissions::READ_MESSAGES),
+        _ => Requirement::guild(GuildPermissions::READ_MESSAGES),
+    }
+}
+
 pub async fn handle_typing_tx<'a>(
     db: &Pool<Postgres>,
     msg: &WsOutboundEvent,
-    bigdecimal_uid: BigDecimal,
+    user_id: u128,
     guild_id: u128,
 ) -> Result<bool, CloseFrame<'a>> {
-    // FIXME: once implemented, do a query to check the user has permissions to view channel in here
-
-    match sqlx::query!(
-        "SELECT user_id FROM members WHERE user_id = $1 AND guild_id = $2",
-        bigdecimal_uid,
-        u128_to_bigdecimal!(guild_id)
-    )
-    .fetch_optional(db)
-    .await
-    {
-        Ok(Some(_)) => Ok(true),
-        Ok(None) => Ok(false),
-        Err(e) => Err(CloseFrame {
-            code: CloseCode::from(5000),
-            reason: format!("Internal database error: {}", e).into(),
-        }),
-    }
\ No newline at end of file
+    meets_requirement(db, guild_id, user_id, typing_requirement(msg)).await
+}
@@ -13,6 +13,7 @@ mod error_handling;
 mod events;
 mod handle_connection;
 mod init;
+mod perms;
 mod preload;
 mod redis_handler;
 mod rx_handler;
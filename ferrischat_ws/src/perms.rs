@@ -0,0 +1,89 @@
+use ferrischat_common::perms::GuildPermissions;
+use sqlx::{Pool, Postgres};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+/// Check whether `user_id` is a member of `guild_id` holding every permission in `required`.
+///
+/// This uses the same resolution rules as the HTTP API,
+/// so that nothing the API hides leaks out through the gateway.
+pub async fn has_guild_permissions<'a>(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+    user_id: u128,
+    required: GuildPermissions,
+) -> Result<bool, CloseFrame<'a>> {
+    match ferrischat_perms::get_guild_permissions(db, guild_id, user_id).await {
+        Ok(Some(perms)) => Ok(perms.contains(required)),
+        Ok(None) => Ok(false),
+        Err(e) => Err(CloseFrame {
+            code: CloseCode::from(5000),
+            reason: format!("Internal database error: {}", e).into(),
+        }),
+    }
+}
+
+/// Same as [`has_guild_permissions`], but taking the permission overwrites of `channel_id` into account.
+pub async fn has_channel_permissions<'a>(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+    channel_id: u128,
+    user_id: u128,
+    required: GuildPermissions,
+) -> Result<bool, CloseFrame<'a>> {
+    match ferrischat_perms::get_channel_permissions(db, guild_id, channel_id, user_id).await {
+        Ok(Some(perms)) => Ok(perms.contains(required)),
+        Ok(None) => Ok(false),
+        Err(e) => Err(CloseFrame {
+            code: CloseCode::from(5000),
+            reason: format!("Internal database error: {}", e).into(),
+        }),
+    }
+}
+
+/// What someone has to hold to be sent an event published on a guild.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Requirement {
+    /// The channel whose permission overwrites apply, or `None` for the guild as a whole.
+    pub channel_id: Option<u128>,
+    pub permissions: GuildPermissions,
+}
+
+impl Requirement {
+    /// Anyone in the guild is sent the event.
+    pub const fn guild(permissions: GuildPermissions) -> Self {
+        Self {
+            channel_id: None,
+            permissions,
+        }
+    }
+
+    /// Only those who hold `permissions` in `channel_id` are sent the event.
+    pub const fn channel(channel_id: u128, permissions: GuildPermissions) -> Self {
+        Self {
+            channel_id: Some(channel_id),
+            permissions,
+        }
+    }
+
+    /// Whether someone with `perms` is sent the event, `perms` being `None` if they aren't in the guild.
+    pub fn is_met_by(&self, perms: Option<GuildPermissions>) -> bool {
+        perms.map_or(false, |p| p.contains(self.permissions))
+    }
+}
+
+/// Check whether `user_id` meets `requirement` in `guild_id`.
+pub async fn meets_requirement<'a>(
+    db: &Pool<Postgres>,
+    guild_id: u128,
+    user_id: u128,
+    requirement: Requirement,
+) -> Result<bool, CloseFrame<'a>> {
+    match requirement.channel_id {
+        Some(channel_id) => {
+            has_channel_permissions(db, guild_id, channel_id, user_id, requirement.permissions)
+                .await
+        }
+        None => has_guild_permissions(db, guild_id, user_id, requirement.permissions).await,
+    }
+}
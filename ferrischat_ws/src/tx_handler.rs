@@ -6,6 +6,7 @@ use ferrischat_redis::redis::Msg;
 use futures_util::stream::SplitSink;
 use futures_util::SinkExt;
 use num_traits::ToPrimitive;
+use std::num::ParseIntError;
 use tokio::net::UnixStream;
 use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
@@ -13,6 +14,65 @@ use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::WebSocketStream;
 use uuid::Uuid;
 
+/// The kind of event published on a Redis channel, which decides who it's sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Channel,
+    Message,
+    Guild,
+    /// Keyed by the owner's ID rather than the guild's.
+    GuildCreate,
+    Member,
+    Invite,
+    /// Keyed by the ID of the user the read state belongs to.
+    ReadState,
+    /// Keyed by the private channel's ID.
+    Private,
+    /// Keyed by the ID of the only user who should see the event.
+    User,
+    Role,
+    Thread,
+    Typing,
+    MemberRole,
+}
+
+/// Why a Redis channel name couldn't be routed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RouteError<'a> {
+    MissingId,
+    InvalidId(ParseIntError),
+    UnknownKind(&'a str),
+}
+
+/// Split a Redis channel name, like `member_role_{id}`, into the kind of event published on it
+/// and the ID it's keyed by.
+///
+/// # Errors
+/// Returns an error if the name has no ID, the ID isn't a valid `u128`, or the kind isn't known.
+pub fn route_event(name: &str) -> Result<(EventKind, u128), RouteError<'_>> {
+    // the ID is always the last part of the name, but the type may contain underscores
+    // itself (`member_role_{id}`), so split from the right
+    let (item_name, obj_id) = name.rsplit_once('_').ok_or(RouteError::MissingId)?;
+    let obj_id = obj_id.parse::<u128>().map_err(RouteError::InvalidId)?;
+    let kind = match item_name {
+        "channel" => EventKind::Channel,
+        "message" => EventKind::Message,
+        "guild" => EventKind::Guild,
+        "gc" => EventKind::GuildCreate,
+        "member" => EventKind::Member,
+        "invite" => EventKind::Invite,
+        "read_state" => EventKind::ReadState,
+        "private" => EventKind::Private,
+        "user" => EventKind::User,
+        "role" => EventKind::Role,
+        "thread" => EventKind::Thread,
+        "typing" => EventKind::Typing,
+        "member_role" => EventKind::MemberRole,
+        t => return Err(RouteError::UnknownKind(t)),
+    };
+    Ok((kind, obj_id))
+}
+
 pub async fn tx_handler(
     mut tx: SplitSink<WebSocketStream<UnixStream>, Message>,
     mut closer_rx: futures::channel::oneshot::Receiver<Option<CloseFrame<'_>>>,
@@ -29,6 +89,8 @@ pub async fn tx_handler(
     }
 
     let mut redis_rx: Option<tokio::sync::mpsc::Receiver<Option<Msg>>> = None;
+    // kept around so we can subscribe to guilds created after this connection identified
+    let mut redis_tx: Option<tokio::sync::mpsc::Sender<Option<Msg>>> = None;
 
     let db = match ferrischat_db::DATABASE_POOL.get() {
         Some(db) => db,
@@ -112,36 +174,48 @@ pub async fn tx_handler(
                         })
                     }
                 };
-                let mut names = n.split('_');
-                let item_name = if let Some(n) = names.next() {
-                    n
-                } else {
-                    warn!(obj = %n, "event name was missing a type");
-                    continue;
-                };
-                let obj_id = match names.next().map(str::parse::<u128>) {
-                    Some(Ok(id)) => id,
-                    Some(Err(e)) => {
-                        warn!(obj = %n, "failed to parse object ID as u128: {}", e);
+                let (kind, obj_id) = match route_event(&n) {
+                    Ok(route) => route,
+                    Err(RouteError::MissingId) => {
+                        warn!(obj = %n, "object was missing an ID");
                         continue;
                     }
-                    None => {
-                        warn!(obj = %n, "object was missing an ID");
+                    Err(RouteError::InvalidId(e)) => {
+                        warn!(obj = %n, "failed to parse object ID as u128: {}", e);
                         continue;
                     }
-                };
-                let ret = match item_name {
-                    "channel" => handle_channel_tx(db, &outbound_message, uid, obj_id).await,
-                    "message" => handle_message_tx(db, &outbound_message, uid, obj_id).await,
-                    // note we don't handle the special `gc` case here
-                    "guild" => handle_guild_tx(db, &outbound_message, uid, obj_id).await,
-                    "member" => handle_member_tx(db, &outbound_message, uid, obj_id).await,
-                    "invite" => handle_invite_tx(db, &outbound_message, uid, obj_id).await,
-                    t => {
+                    Err(RouteError::UnknownKind(t)) => {
                         warn!("unknown event type {}", t);
                         continue;
                     }
                 };
+                let ret = match kind {
+                    EventKind::Channel => {
+                        handle_channel_tx(db, &outbound_message, uid, obj_id).await
+                    }
+                    EventKind::Message => {
+                        handle_message_tx(db, &outbound_message, uid, obj_id).await
+                    }
+                    EventKind::Guild => handle_guild_tx(db, &outbound_message, uid, obj_id).await,
+                    EventKind::GuildCreate => {
+                        Ok(handle_guild_create_tx(&outbound_message, uid, obj_id))
+                    }
+                    EventKind::Member => handle_member_tx(db, &outbound_message, uid, obj_id).await,
+                    EventKind::Invite => handle_invite_tx(db, &outbound_message, uid, obj_id).await,
+                    EventKind::ReadState => {
+                        Ok(handle_read_state_tx(&outbound_message, uid, obj_id))
+                    }
+                    EventKind::Private => {
+                        handle_private_tx(db, &outbound_message, uid, obj_id).await
+                    }
+                    EventKind::User => Ok(handle_user_tx(uid, obj_id)),
+                    EventKind::Role => handle_role_tx(db, &outbound_message, uid, obj_id).await,
+                    EventKind::Thread => handle_thread_tx(db, &outbound_message, uid, obj_id).await,
+                    EventKind::Typing => handle_typing_tx(db, &outbound_message, uid, obj_id).await,
+                    EventKind::MemberRole => {
+                        handle_member_role_tx(db, &outbound_message, uid, obj_id).await
+                    }
+                };
                 match ret {
                     Ok(true) => {
                         let payload = match msg.get_payload::<String>() {
@@ -160,6 +234,31 @@ pub async fn tx_handler(
                         if let Err(e) = tx.feed(Message::Text(payload)).await {
                             warn!("Error while sending message to WebSocket client: {:?}", e);
                         }
+
+                        // we weren't a member of this guild or a recipient of this private channel
+                        // when we subscribed, so do it now
+                        let new_sub = match &outbound_message {
+                            WsOutboundEvent::GuildCreate { guild } => Some(guild.id),
+                            WsOutboundEvent::ChannelCreate { channel }
+                                if kind == EventKind::User =>
+                            {
+                                Some(channel.id)
+                            }
+                            _ => None,
+                        };
+                        if let Some(new_sub) = new_sub {
+                            if let (Some(s), Some(redis_tx)) = (crate::SUB_TO_ME.get(), &redis_tx) {
+                                if s.send((format!("*{}*", new_sub), redis_tx.clone()))
+                                    .await
+                                    .is_err()
+                                {
+                                    break Some(CloseFrame {
+                                        code: CloseCode::from(5006),
+                                        reason: "Redis connection pool hung up connection".into(),
+                                    });
+                                }
+                            }
+                        }
                     }
                     Ok(false) => {}
                     Err(e) => {
@@ -189,8 +288,9 @@ pub async fn tx_handler(
                 }
             };
             if let Some(map_val) = uid_conn_map.get(&conn_id) {
-                let (redis_tx, redis_rx_2) = tokio::sync::mpsc::channel(250);
+                let (redis_tx_2, redis_rx_2) = tokio::sync::mpsc::channel(250);
                 redis_rx = Some(redis_rx_2);
+                let redis_tx = redis_tx.insert(redis_tx_2);
                 match crate::SUB_TO_ME.get() {
                     Some(s) => {
                         let user_id = *(map_val.value());
@@ -203,8 +303,9 @@ pub async fn tx_handler(
                                 reason: "Redis connection pool hung up connection".into(),
                             });
                         }
+                        // guilds and private channels are both subscribed to by ID alone
                         let resp = sqlx::query!(
-                            "SELECT guild_id FROM members WHERE user_id = $1",
+                            r#"SELECT guild_id AS "id!" FROM members WHERE user_id = $1 UNION SELECT channel_id FROM channel_recipients WHERE user_id = $1"#,
                             u128_to_bigdecimal!(user_id)
                         )
                         .fetch_all(db)
@@ -212,11 +313,7 @@ pub async fn tx_handler(
                         match resp {
                             Ok(resp) => {
                                 for guild in resp.iter().filter_map(|x| {
-                                    x.guild_id
-                                        .with_scale(0)
-                                        .into_bigint_and_exponent()
-                                        .0
-                                        .to_u128()
+                                    x.id.with_scale(0).into_bigint_and_exponent().0.to_u128()
                                 }) {
                                     if s.send((format!("*{}*", guild), redis_tx.clone()))
                                         .await
@@ -252,3 +349,264 @@ pub async fn tx_handler(
 
     (ret, tx)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrischat_common::perms::GuildPermissions;
+    use ferrischat_common::types::{
+        Channel, ChannelType, Guild, GuildFlags, ReadState, Role, User, UserFlags,
+    };
+
+    fn guild_create(owner_id: u128) -> WsOutboundEvent {
+        WsOutboundEvent::GuildCreate {
+            guild: Guild {
+                id: 1,
+                owner_id,
+                name: "guild".to_string(),
+                channels: None,
+                flags: GuildFlags::empty(),
+                members: None,
+                roles: None,
+                icon: None,
+                revision_retention_days: None,
+            },
+        }
+    }
+
+    fn read_state_update(user_id: u128) -> WsOutboundEvent {
+        WsOutboundEvent::ReadStateUpdate {
+            user_id,
+            read_state: ReadState {
+                channel_id: 1,
+                last_read_id: None,
+                mention_count: 0,
+            },
+        }
+    }
+
+    fn role(guild_id: u128) -> Role {
+        Role {
+            id: 2,
+            name: "role".to_string(),
+            color: None,
+            position: 1,
+            guild_id,
+            guild_permissions: GuildPermissions::empty(),
+        }
+    }
+
+    fn guild_channel(id: u128, guild_id: u128) -> Channel {
+        Channel {
+            id,
+            name: "channel".to_string(),
+            guild_id: Some(guild_id),
+            channel_type: ChannelType::Text,
+            parent_id: None,
+            position: 0,
+            topic: None,
+            nsfw: false,
+            owner_id: None,
+            recipients: None,
+            thread_metadata: None,
+            permission_overwrites: None,
+        }
+    }
+
+    fn user(id: u128) -> User {
+        User {
+            id,
+            name: "user".to_string(),
+            avatar: None,
+            guilds: None,
+            discriminator: 1,
+            flags: UserFlags::empty(),
+            pronouns: None,
+            is_bot: false,
+        }
+    }
+
+    #[test]
+    fn routes_every_kind() {
+        let cases = [
+            ("channel_1", EventKind::Channel),
+            ("message_1", EventKind::Message),
+            ("guild_1", EventKind::Guild),
+            ("gc_1", EventKind::GuildCreate),
+            ("member_1", EventKind::Member),
+            ("invite_1", EventKind::Invite),
+            ("read_state_1", EventKind::ReadState),
+            ("private_1", EventKind::Private),
+            ("user_1", EventKind::User),
+            ("role_1", EventKind::Role),
+            ("thread_1", EventKind::Thread),
+            ("typing_1", EventKind::Typing),
+            ("member_role_1", EventKind::MemberRole),
+        ];
+        for (name, kind) in cases {
+            assert_eq!(route_event(name), Ok((kind, 1)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn keeps_the_whole_id() {
+        let id = u128::MAX;
+        assert_eq!(
+            route_event(&format!("member_role_{}", id)),
+            Ok((EventKind::MemberRole, id))
+        );
+        assert_eq!(
+            route_event(&format!("read_state_{}", id)),
+            Ok((EventKind::ReadState, id))
+        );
+        assert_eq!(
+            route_event(&format!("private_{}", id)),
+            Ok((EventKind::Private, id))
+        );
+    }
+
+    #[test]
+    fn does_not_confuse_kinds_sharing_a_prefix() {
+        // `member_role` must not be routed as `member`, which goes to the whole guild
+        assert_eq!(
+            route_event("member_role_2").map(|(k, _)| k),
+            Ok(EventKind::MemberRole)
+        );
+        assert_eq!(
+            route_event("member_2").map(|(k, _)| k),
+            Ok(EventKind::Member)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        assert_eq!(route_event("channel"), Err(RouteError::MissingId));
+        assert!(matches!(
+            route_event("channel_abc"),
+            Err(RouteError::InvalidId(_))
+        ));
+        assert!(matches!(
+            route_event("channel_"),
+            Err(RouteError::InvalidId(_))
+        ));
+        assert_eq!(
+            route_event("role_member_1"),
+            Err(RouteError::UnknownKind("role_member"))
+        );
+        assert_eq!(
+            route_event("bogus_1"),
+            Err(RouteError::UnknownKind("bogus"))
+        );
+    }
+
+    #[test]
+    fn guild_create_only_reaches_owner() {
+        let (kind, owner_id) = route_event("gc_10").unwrap();
+        assert_eq!(kind, EventKind::GuildCreate);
+        assert!(handle_guild_create_tx(&guild_create(10), 10, owner_id));
+        assert!(!handle_guild_create_tx(&guild_create(10), 11, owner_id));
+        // only guild creations are sent this way
+        assert!(!handle_guild_create_tx(
+            &read_state_update(10),
+            10,
+            owner_id
+        ));
+    }
+
+    #[test]
+    fn read_state_only_reaches_its_user() {
+        let (kind, owner_id) = route_event("read_state_20").unwrap();
+        assert_eq!(kind, EventKind::ReadState);
+        assert!(handle_read_state_tx(&read_state_update(20), 20, owner_id));
+        assert!(!handle_read_state_tx(&read_state_update(20), 21, owner_id));
+        assert!(!handle_read_state_tx(&guild_create(20), 20, owner_id));
+    }
+
+    #[test]
+    fn user_events_only_reach_their_user() {
+        let (kind, target_id) = route_event("user_30").unwrap();
+        assert_eq!(kind, EventKind::User);
+        assert!(handle_user_tx(30, target_id));
+        assert!(!handle_user_tx(31, target_id));
+    }
+
+    #[test]
+    fn private_events_are_keyed_by_channel() {
+        // private channel events are checked against the recipients of the channel in the name,
+        // never a guild or a user
+        let (kind, channel_id) = route_event("private_40").unwrap();
+        assert_eq!(kind, EventKind::Private);
+        assert_eq!(channel_id, 40);
+        assert_ne!(
+            route_event("private_40").map(|(k, _)| k),
+            Ok(EventKind::User)
+        );
+    }
+
+    #[test]
+    fn role_events_reach_everyone_in_the_guild() {
+        let (kind, guild_id) = route_event("role_50").unwrap();
+        assert_eq!(kind, EventKind::Role);
+        assert_eq!(guild_id, 50);
+        let events = [
+            WsOutboundEvent::RoleCreate { role: role(50) },
+            WsOutboundEvent::RoleUpdate {
+                old: role(50),
+                new: role(50),
+            },
+        ];
+        for event in &events {
+            let requirement = role_requirement(event);
+            assert_eq!(requirement.channel_id, None);
+            // any member, even one without a single permission
+            assert!(requirement.is_met_by(Some(GuildPermissions::empty())));
+            // but nobody outside the guild
+            assert!(!requirement.is_met_by(None));
+        }
+    }
+
+    #[test]
+    fn typing_only_reaches_those_who_can_read_the_channel() {
+        let (kind, guild_id) = route_event("typing_60").unwrap();
+        assert_eq!(kind, EventKind::Typing);
+        assert_eq!(guild_id, 60);
+        let event = WsOutboundEvent::TypingStart {
+            channel: guild_channel(61, 60),
+            user: user(62),
+        };
+        let requirement = typing_requirement(&event);
+        // overwrites on the channel being typed in are what count, not the guild's permissions
+        assert_eq!(requirement.channel_id, Some(61));
+        assert!(requirement.is_met_by(Some(GuildPermissions::READ_MESSAGES)));
+        assert!(!requirement.is_met_by(Some(GuildPermissions::SEND_MESSAGES)));
+        assert!(!requirement.is_met_by(None));
+    }
+
+    #[test]
+    fn member_role_events_reach_everyone_in_the_guild() {
+        let (kind, guild_id) = route_event("member_role_70").unwrap();
+        assert_eq!(kind, EventKind::MemberRole);
+        assert_eq!(guild_id, 70);
+        let events = [
+            WsOutboundEvent::MemberRoleAdd {
+                user_id: 71,
+                role: role(70),
+            },
+            WsOutboundEvent::MemberRoleDelete {
+                user_id: 71,
+                role: role(70),
+            },
+        ];
+        for event in &events {
+            let requirement = member_role_requirement(event);
+            assert_eq!(requirement.channel_id, None);
+            assert!(requirement.is_met_by(Some(GuildPermissions::empty())));
+            assert!(!requirement.is_met_by(None));
+        }
+        // nor are they mistaken for member events
+        assert_ne!(
+            route_event("member_role_70").map(|(k, _)| k),
+            Ok(EventKind::Member)
+        );
+    }
+}
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS permission_overwrites
+(
+    channel_id  numeric(39) REFERENCES channels (id) ON DELETE CASCADE NOT NULL,
+    target_id   numeric(39)                                            NOT NULL,
+    target_type SMALLINT                                               NOT NULL,
+    allow       bytea                                                  NOT NULL DEFAULT '',
+    deny        bytea                                                  NOT NULL DEFAULT '',
+    PRIMARY KEY (channel_id, target_id)
+);
@@ -0,0 +1,8 @@
+-- Add migration script here
+
+-- every guild gets an everyone role sharing its ID.
+-- permissions are left empty, which the server reads as the default set for these roles.
+INSERT INTO roles (id, name, position, parent_guild)
+SELECT id, '@everyone', 0, id
+FROM guilds
+ON CONFLICT (id) DO NOTHING;
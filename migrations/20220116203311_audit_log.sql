@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS audit_log
+(
+    id          numeric(39) PRIMARY KEY                         NOT NULL,
+    guild_id    numeric(39) REFERENCES guilds ON DELETE CASCADE NOT NULL,
+    actor_id    numeric(39)                                     NOT NULL,
+    action_type SMALLINT                                        NOT NULL,
+    target_id   numeric(39),
+    before      jsonb,
+    after       jsonb,
+    reason      VARCHAR(512)
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_guild_id_idx ON audit_log (guild_id, id DESC);
@@ -0,0 +1,2 @@
+-- Add migration script here
+CREATE INDEX IF NOT EXISTS messages_channel_id_id_idx ON messages (channel_id, id);
@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS reactions
+(
+    message_id numeric(39) REFERENCES messages ON DELETE CASCADE NOT NULL,
+    user_id    numeric(39) REFERENCES users ON DELETE CASCADE    NOT NULL,
+    emoji      VARCHAR(64)                                      NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE                         NOT NULL DEFAULT now(),
+    PRIMARY KEY (message_id, user_id, emoji)
+);
+
+CREATE INDEX IF NOT EXISTS reactions_message_id_emoji_idx ON reactions (message_id, emoji);
@@ -0,0 +1,4 @@
+-- Add migration script here
+
+-- deliberately not a foreign key: replies keep pointing at messages that have since been deleted
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS reference_id numeric(39);
@@ -0,0 +1,9 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS pins
+(
+    channel_id numeric(39) REFERENCES channels ON DELETE CASCADE NOT NULL,
+    message_id numeric(39) REFERENCES messages ON DELETE CASCADE NOT NULL,
+    pinned_by  numeric(39)                                       NOT NULL,
+    pinned_at  TIMESTAMP WITH TIME ZONE                          NOT NULL DEFAULT now(),
+    PRIMARY KEY (channel_id, message_id)
+);
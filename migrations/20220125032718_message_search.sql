@@ -0,0 +1,5 @@
+-- Add migration script here
+
+-- an expression index rather than a generated column, so `SELECT m.*` doesn't start returning a tsvector
+CREATE INDEX IF NOT EXISTS messages_content_search_idx
+    ON messages USING GIN (to_tsvector('english', coalesce(content, '')));
@@ -0,0 +1,13 @@
+-- Add migration script here
+ALTER TABLE guilds ADD COLUMN IF NOT EXISTS revision_retention_days INT NOT NULL DEFAULT 30;
+
+CREATE TABLE IF NOT EXISTS message_revisions
+(
+    id         numeric(39) PRIMARY KEY                           NOT NULL,
+    message_id numeric(39) REFERENCES messages ON DELETE CASCADE NOT NULL,
+    guild_id   numeric(39) REFERENCES guilds ON DELETE CASCADE   NOT NULL,
+    content    text
+);
+
+CREATE INDEX IF NOT EXISTS message_revisions_guild_id_idx ON message_revisions (guild_id, id);
+CREATE INDEX IF NOT EXISTS message_revisions_message_id_idx ON message_revisions (message_id, id);
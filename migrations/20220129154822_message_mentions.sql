@@ -0,0 +1,7 @@
+-- Add migration script here
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS mentions numeric(39)[] NOT NULL DEFAULT '{}';
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS mention_roles numeric(39)[] NOT NULL DEFAULT '{}';
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS mention_everyone BOOLEAN NOT NULL DEFAULT false;
+
+-- lets clients find the messages that mention a user without scanning the whole table
+CREATE INDEX IF NOT EXISTS messages_mentions_idx ON messages USING GIN (mentions);
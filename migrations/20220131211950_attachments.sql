@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS attachments (
+    id numeric(39) PRIMARY KEY,
+    message_id numeric(39) REFERENCES messages ON DELETE CASCADE NOT NULL,
+    filename VARCHAR(256) NOT NULL,
+    content_type TEXT NOT NULL,
+    size BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS attachments_message_id_idx ON attachments (message_id, id);
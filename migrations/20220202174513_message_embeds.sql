@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS message_embeds (
+    message_id numeric(39) REFERENCES messages ON DELETE CASCADE NOT NULL,
+    position SMALLINT NOT NULL,
+    embed JSONB NOT NULL,
+    PRIMARY KEY (message_id, position)
+);
@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS read_states (
+    user_id numeric(39) REFERENCES users ON DELETE CASCADE NOT NULL,
+    channel_id numeric(39) REFERENCES channels ON DELETE CASCADE NOT NULL,
+    last_read_id numeric(39),
+    mention_count INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, channel_id)
+);
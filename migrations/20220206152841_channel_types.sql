@@ -0,0 +1,10 @@
+-- Add migration script here
+-- channel_type: 0 = text, 1 = category, 2 = announcement
+ALTER TABLE channels
+    ADD COLUMN IF NOT EXISTS channel_type SMALLINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS parent_id numeric(39) REFERENCES channels ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS position INT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS topic VARCHAR(1024),
+    ADD COLUMN IF NOT EXISTS nsfw BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX IF NOT EXISTS channels_parent_idx ON channels (parent_id);
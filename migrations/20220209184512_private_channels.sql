@@ -0,0 +1,18 @@
+-- Add migration script here
+-- private channels (channel_type 3 for direct messages, 4 for group direct messages) don't belong to a guild
+ALTER TABLE channels ALTER COLUMN guild_id DROP NOT NULL;
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS owner_id numeric(39) REFERENCES users ON DELETE SET NULL;
+
+CREATE TABLE IF NOT EXISTS channel_recipients (
+    channel_id numeric(39) REFERENCES channels ON DELETE CASCADE NOT NULL,
+    user_id numeric(39) REFERENCES users ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (channel_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS channel_recipients_user_idx ON channel_recipients (user_id);
+
+CREATE TABLE IF NOT EXISTS user_blocks (
+    user_id numeric(39) REFERENCES users ON DELETE CASCADE NOT NULL,
+    blocked_id numeric(39) REFERENCES users ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (user_id, blocked_id)
+);
+CREATE INDEX IF NOT EXISTS user_blocks_blocked_idx ON user_blocks (blocked_id);
@@ -0,0 +1,18 @@
+-- Add migration script here
+-- threads are channels with channel_type 5, whose parent_id is the channel they were started in
+ALTER TABLE channels
+    ADD COLUMN IF NOT EXISTS starter_message_id numeric(39) UNIQUE REFERENCES messages ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS archived BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN IF NOT EXISTS auto_archive_minutes INT,
+    ADD COLUMN IF NOT EXISTS last_activity_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS archived_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS channels_active_threads_idx ON channels (last_activity_at) WHERE channel_type = 5 AND NOT archived;
+
+CREATE TABLE IF NOT EXISTS thread_members (
+    thread_id numeric(39) REFERENCES channels ON DELETE CASCADE NOT NULL,
+    user_id numeric(39) REFERENCES users ON DELETE CASCADE NOT NULL,
+    joined_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    PRIMARY KEY (thread_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS thread_members_user_idx ON thread_members (user_id);
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE members ADD COLUMN IF NOT EXISTS nickname VARCHAR(32);